@@ -0,0 +1,323 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::env;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Config file that is read if `CONFIG_PATH` is not set; it is optional
+const DEFAULT_CONFIG_PATH: &str = "speedrun-survivors-api.toml";
+
+/// Server configuration; built from the defaults, then the config file, then environment variables
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub cors: CorsConfig,
+    pub session: SessionTimeouts,
+    pub rate_limit: RateLimitConfig,
+    /// Seconds between two runs of the expired session sweeper
+    pub sweep_interval: u64,
+    /// Serve `GET /stats/sessions`; off by default, as the counts are public to anyone who can reach the API
+    pub session_stats: bool,
+    pub rpc_url: String,
+    pub nft_collection: String,
+    pub db_path: String,
+    pub simulator_path: String,
+    /// Directory holding the `assets` folder of the game, the simulator loads its game data from there
+    pub simulator_assets: String,
+    /// Seconds a replay simulation may run before it is killed
+    pub simulator_timeout: u64,
+    /// Replay simulations that may run at once; further completions are rejected as busy
+    pub simulator_concurrency: usize,
+    /// Completed runs are recorded for this leaderboard season
+    pub season: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub bind: String,
+    pub port: u16,
+    /// Serve plain HTTP instead, e.g. when TLS is terminated by a reverse proxy
+    pub tls: bool,
+    pub cert_path: String,
+    pub key_path: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    /// Origins allowed to call the API; `*` allows any origin, an empty list none
+    pub allowed_origins: Vec<String>,
+    pub max_age: usize,
+}
+
+/// Seconds a session may stay in each phase
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct SessionTimeouts {
+    /// From `session_init` until the client signed the start of the game; after that another
+    /// `session_init` of the same wallet may replace the session
+    pub signature: u64,
+    /// From `session_init` until `game_start`; the session expires if the game was not started by then
+    pub start: u64,
+    /// From `game_start` until `game_complete`
+    pub game: u64,
+}
+
+/// Token bucket limits for the `/play` routes
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub per_ip: BucketConfig,
+    /// Applies to the signed requests of a pubkey, after their signature was verified
+    pub per_pubkey: BucketConfig,
+    /// Take the client IP from `Forwarded` / `X-Forwarded-For`; only enable this behind a reverse proxy
+    pub trust_forwarded_for: bool,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct BucketConfig {
+    /// Requests that can be made in a burst
+    pub capacity: u32,
+    /// Requests regained per second
+    pub refill_per_sec: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            server: ServerConfig::default(),
+            cors: CorsConfig::default(),
+            session: SessionTimeouts::default(),
+            rate_limit: RateLimitConfig::default(),
+            sweep_interval: 60,
+            session_stats: false,
+            rpc_url: String::new(),
+            nft_collection: String::new(),
+            db_path: "speedrun-survivors.db".to_string(),
+            simulator_path: "speedrun-survivors-sim".to_string(),
+            simulator_assets: ".".to_string(),
+            simulator_timeout: 120,
+            simulator_concurrency: std::thread::available_parallelism()
+                .map(usize::from)
+                .unwrap_or(1),
+            season: "1".to_string(),
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: "127.0.0.1".to_string(),
+            port: 8443,
+            tls: true,
+            cert_path: "cert/cert.pem".to_string(),
+            key_path: "cert/key.pem".to_string(),
+        }
+    }
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            max_age: 3600,
+        }
+    }
+}
+
+impl Default for SessionTimeouts {
+    fn default() -> Self {
+        Self {
+            signature: 30,
+            start: 600,
+            game: 3600,
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_ip: BucketConfig {
+                capacity: 30,
+                refill_per_sec: 5.0,
+            },
+            per_pubkey: BucketConfig {
+                capacity: 10,
+                refill_per_sec: 1.0,
+            },
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file at `CONFIG_PATH` (or the default path, if it exists) and apply env overrides
+    pub fn load() -> Result<Self> {
+        let mut config = match env::var("CONFIG_PATH") {
+            Ok(path) => Self::from_file(&path)?,
+            Err(_) if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::from_file(DEFAULT_CONFIG_PATH)?
+            }
+            Err(_) => Self::default(),
+        };
+
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_file(path: &str) -> Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {path}"))?;
+        let config =
+            toml::from_str(&data).with_context(|| format!("Invalid config file {path}"))?;
+        log::info!("Loaded config file {path}");
+        Ok(config)
+    }
+
+    fn apply_env(&mut self) -> Result<()> {
+        override_from_env("BIND_ADDRESS", &mut self.server.bind)?;
+        override_from_env("PORT", &mut self.server.port)?;
+        override_from_env("TLS", &mut self.server.tls)?;
+        override_from_env("TLS_CERT_PATH", &mut self.server.cert_path)?;
+        override_from_env("TLS_KEY_PATH", &mut self.server.key_path)?;
+        if let Ok(origins) = env::var("CORS_ALLOWED_ORIGINS") {
+            self.cors.allowed_origins = origins
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(String::from)
+                .collect();
+        }
+        override_from_env("SESSION_SIGNATURE_TIMEOUT", &mut self.session.signature)?;
+        override_from_env("SESSION_START_TIMEOUT", &mut self.session.start)?;
+        override_from_env("SESSION_GAME_TIMEOUT", &mut self.session.game)?;
+        override_from_env("SESSION_SWEEP_INTERVAL", &mut self.sweep_interval)?;
+        override_from_env("SESSION_STATS", &mut self.session_stats)?;
+        override_from_env(
+            "RATE_LIMIT_IP_CAPACITY",
+            &mut self.rate_limit.per_ip.capacity,
+        )?;
+        override_from_env(
+            "RATE_LIMIT_IP_REFILL",
+            &mut self.rate_limit.per_ip.refill_per_sec,
+        )?;
+        override_from_env(
+            "RATE_LIMIT_PUBKEY_CAPACITY",
+            &mut self.rate_limit.per_pubkey.capacity,
+        )?;
+        override_from_env(
+            "RATE_LIMIT_PUBKEY_REFILL",
+            &mut self.rate_limit.per_pubkey.refill_per_sec,
+        )?;
+        override_from_env(
+            "TRUST_FORWARDED_FOR",
+            &mut self.rate_limit.trust_forwarded_for,
+        )?;
+        override_from_env("RPC_URL", &mut self.rpc_url)?;
+        override_from_env("NFT_COLLECTION", &mut self.nft_collection)?;
+        override_from_env("DB_PATH", &mut self.db_path)?;
+        override_from_env("SIMULATOR_PATH", &mut self.simulator_path)?;
+        override_from_env("SIMULATOR_ASSETS", &mut self.simulator_assets)?;
+        override_from_env("SIMULATOR_TIMEOUT", &mut self.simulator_timeout)?;
+        override_from_env("SIMULATOR_CONCURRENCY", &mut self.simulator_concurrency)?;
+        override_from_env("SEASON", &mut self.season)?;
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.rpc_url.is_empty() {
+            bail!("No RPC URL configured; set rpc_url in the config file or RPC_URL");
+        }
+        if self.nft_collection.is_empty() {
+            bail!("No NFT collection configured; set nft_collection in the config file or NFT_COLLECTION");
+        }
+        for bucket in [self.rate_limit.per_ip, self.rate_limit.per_pubkey] {
+            if bucket.capacity == 0 || bucket.refill_per_sec <= 0.0 {
+                bail!("Rate limits need a capacity and refill rate above zero");
+            }
+        }
+        if self.simulator_timeout == 0 || self.simulator_concurrency == 0 {
+            bail!("The replay simulator needs a timeout and concurrency above zero");
+        }
+        Ok(())
+    }
+}
+
+fn override_from_env<T: FromStr>(name: &str, value: &mut T) -> Result<()>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    if let Ok(raw) = env::var(name) {
+        *value = raw
+            .parse()
+            .with_context(|| format!("Invalid value for {name}: {raw}"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_overrides_the_config_file() {
+        let mut config: Config = toml::from_str(
+            r#"
+            rpc_url = "https://file"
+            [session]
+            signature = 10
+            start = 100
+            game = 1000
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.nft_collection, "");
+        assert_eq!(
+            (
+                config.session.signature,
+                config.session.start,
+                config.session.game
+            ),
+            (10, 100, 1000)
+        );
+
+        // The only test touching the environment, so it can not race with another one
+        let vars = [
+            ("SESSION_SIGNATURE_TIMEOUT", "45"),
+            ("SESSION_START_TIMEOUT", "300"),
+            ("SESSION_GAME_TIMEOUT", "1800"),
+            ("NFT_COLLECTION", "collection"),
+        ];
+        for (name, value) in vars {
+            env::set_var(name, value);
+        }
+        config.apply_env().unwrap();
+        assert_eq!(
+            (
+                config.session.signature,
+                config.session.start,
+                config.session.game
+            ),
+            (45, 300, 1800)
+        );
+        assert_eq!(config.rpc_url, "https://file");
+        assert_eq!(config.nft_collection, "collection");
+        config.validate().unwrap();
+
+        env::set_var("SESSION_SIGNATURE_TIMEOUT", "soon");
+        let error = config.apply_env().unwrap_err();
+        assert!(
+            error.to_string().contains("SESSION_SIGNATURE_TIMEOUT"),
+            "{error}"
+        );
+
+        for (name, _) in vars {
+            env::remove_var(name);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use actix_web::http::{header, StatusCode};
+use actix_web::{HttpResponse, ResponseError};
+use speedrun_survivors_model::{ApiErrorBody, ApiErrorCode, ReplayVerdict};
+use std::fmt;
+use std::time::Duration;
+
+/// Every error a request can end with; serialized as `ApiErrorBody` with the status of its `ApiErrorCode`
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request body could not be parsed
+    BadRequest(String),
+    NoSuchSession,
+    /// A session that is neither expired nor completed exists for this pubkey
+    GameActive,
+    /// Entropy, session state or timing do not match the stored session
+    RequestDataDoesNotMatch,
+    SignatureInvalid,
+    TooManyNfts,
+    NftNotOwned,
+    /// The NFTs sent with `game_complete` differ from the ones verified at `game_start`
+    NftLoadoutMismatch,
+    /// A game with this entropy was completed before
+    EntropyUsed,
+    /// The run was recorded, but the replay does not confirm the claimed outcome
+    ReplayRejected(ReplayVerdict),
+    /// The player has no verified run on the requested board
+    NotRanked,
+    RateLimited(Duration),
+    /// Every replay simulator slot is taken; the client should retry later
+    Busy,
+    /// A service the API depends on, e.g. the RPC provider, failed
+    Upstream(anyhow::Error),
+    Internal(anyhow::Error),
+}
+
+impl ApiError {
+    pub fn code(&self) -> ApiErrorCode {
+        match self {
+            ApiError::BadRequest(_) => ApiErrorCode::BadRequest,
+            ApiError::NoSuchSession => ApiErrorCode::NoSuchSession,
+            ApiError::GameActive => ApiErrorCode::GameActive,
+            ApiError::RequestDataDoesNotMatch => ApiErrorCode::RequestDataDoesNotMatch,
+            ApiError::SignatureInvalid => ApiErrorCode::SignatureInvalid,
+            ApiError::TooManyNfts => ApiErrorCode::TooManyNfts,
+            ApiError::NftNotOwned => ApiErrorCode::NftNotOwned,
+            ApiError::NftLoadoutMismatch => ApiErrorCode::NftLoadoutMismatch,
+            ApiError::EntropyUsed => ApiErrorCode::EntropyUsed,
+            ApiError::ReplayRejected(_) => ApiErrorCode::ReplayRejected,
+            ApiError::NotRanked => ApiErrorCode::NotRanked,
+            ApiError::RateLimited(_) => ApiErrorCode::RateLimited,
+            ApiError::Busy => ApiErrorCode::Busy,
+            ApiError::Upstream(_) => ApiErrorCode::Upstream,
+            ApiError::Internal(_) => ApiErrorCode::Internal,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::BadRequest(reason) => write!(f, "Invalid request: {reason}"),
+            ApiError::NoSuchSession => write!(f, "No session exists for this pubkey"),
+            ApiError::GameActive => write!(f, "A game is active for this pubkey"),
+            ApiError::RequestDataDoesNotMatch => {
+                write!(f, "The request does not match the session")
+            }
+            ApiError::SignatureInvalid => write!(f, "The signature is invalid"),
+            ApiError::TooManyNfts => write!(f, "Too many or duplicate NFTs equipped"),
+            ApiError::NftNotOwned => write!(f, "An equipped NFT is not owned by this pubkey"),
+            ApiError::NftLoadoutMismatch => {
+                write!(f, "The NFTs differ from the ones the game was started with")
+            }
+            ApiError::EntropyUsed => write!(f, "A game with this entropy was completed before"),
+            ApiError::ReplayRejected(verdict) => write!(f, "The replay was rejected: {verdict:?}"),
+            ApiError::NotRanked => write!(f, "No verified run on this board"),
+            ApiError::RateLimited(_) => write!(f, "Too many requests"),
+            ApiError::Busy => write!(f, "The server is busy, try again later"),
+            // Internal details are logged, never sent to the client
+            ApiError::Upstream(_) => write!(f, "An upstream service failed"),
+            ApiError::Internal(_) => write!(f, "Internal server error"),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.code().status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            ApiError::Upstream(e) | ApiError::Internal(e) => {
+                log::error!("Request failed with {}: {e:?}", self.code().as_str())
+            }
+            _ => log::info!("Request rejected with {}: {self}", self.code().as_str()),
+        }
+
+        let mut response = HttpResponse::build(self.status_code());
+        if let ApiError::RateLimited(retry_after) = self {
+            // Round up, a client retrying after a truncated delay would be rejected again
+            let seconds = retry_after.as_secs_f64().ceil().min(u32::MAX as f64) as u64;
+            response.insert_header((header::RETRY_AFTER, seconds.max(1)));
+        }
+
+        response.json(ApiErrorBody::new(self.code(), self.to_string()))
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        ApiError::Internal(e)
+    }
+}
@@ -1,15 +1,28 @@
-use actix_web::{guard, web::scope, Scope};
+use actix_web::web::{scope, JsonConfig};
+use actix_web::{guard, Scope};
+
+use crate::error::ApiError;
 
-mod model;
 mod routes;
 
+/// Largest request body accepted; replays make `game_complete` by far the biggest request
+pub const MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
 pub fn client_routes() -> Scope {
     scope("/play")
         .guard(guard::Header("content-type", "application/json"))
+        .app_data(
+            JsonConfig::default()
+                .limit(MAX_BODY_SIZE)
+                .error_handler(|e, _req| ApiError::BadRequest(e.to_string()).into()),
+        )
         .service(routes::nft_list)
         .service(routes::session_get)
         .service(routes::session_init)
         .service(routes::session_cancel)
         .service(routes::game_start)
         .service(routes::game_complete)
+        .service(routes::balance)
+        .service(routes::leaderboard)
+        .service(routes::leaderboard_rank)
 }
@@ -1,24 +1,52 @@
-use actix_web::web::Data;
-use actix_web::{post, web::Json, HttpResponse, Responder};
-use std::sync::RwLock;
+use actix_web::web::{self, Data};
+use actix_web::{post, web::Json, HttpResponse};
+use speedrun_survivors_model::*;
+use speedrun_survivors_replay::TICKS_PER_SECOND;
+use std::collections::HashSet;
 
-use crate::game_client_routes::model::*;
-use crate::helius_rpc::test_api;
-use crate::utils::{unixtime, weak_random_base64_string};
-use crate::{Session, SessionStatus, Sessions};
+use crate::config::Config;
+use crate::error::ApiError;
+use crate::helius_rpc::HeliusClient;
+use crate::rate_limit::PubkeyRateLimit;
+use crate::replay::{GameCatalog, ReplayVerifier};
+use crate::storage::{blocking, CompletedRun, Session, SessionStatus, Storage};
+use crate::utils::{secure_random_base64_string, unixtime};
+
+/// Most NFTs a player can equip for one run; must match the client
+const MAX_NUM_NFT: usize = 6;
+
+/// A collision of 264 random bits means the RNG is broken; retrying a few times is plenty
+const ENTROPY_ATTEMPTS: usize = 3;
+
+/// Seconds a replay may run ahead of the wall clock, covering clock skew and request latency
+const REPLAY_TIME_SLACK: u64 = 10;
+
+/// Leaderboard entries / transactions returned per page, unless the client asks for fewer
+const PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
 
 #[post("/nft_list")]
-async fn nft_list(req_data: Json<NftListRequest>) -> impl Responder {
-    println!("{req_data:?}");
-    HttpResponse::Ok().json(NftListResponse { nft_list: None })
+async fn nft_list(
+    helius: Data<HeliusClient>,
+    req_data: Json<NftListRequest>,
+) -> Result<Json<NftListResponse>, ApiError> {
+    log::info!("nft_list pubkey={}", req_data.pubkey);
+
+    let nft_list = helius
+        .nfts_by_owner(&req_data.pubkey)
+        .await
+        .map_err(ApiError::Upstream)?;
+
+    Ok(Json(NftListResponse { nft_list }))
 }
 
 #[post("/session_get")]
 async fn session_get(
-    state_sessions: Data<RwLock<Sessions>>,
+    storage: Data<dyn Storage>,
+    config: Data<Config>,
     req_data: Json<SessionGetRequest>,
-) -> impl Responder {
-    println!("session_get request data: {req_data:?}");
+) -> Result<Json<SessionGetResponse>, ApiError> {
+    log::info!("session_get pubkey={}", req_data.pubkey);
 
     // Construct default response
     let mut response = SessionGetResponse::default();
@@ -26,201 +54,762 @@ async fn session_get(
     // Current time
     let now = unixtime();
 
-    // Attempt to lock and read the database
-    if let Ok(mut sessions) = state_sessions.read() {
-        if let Some(entry) = sessions.data.get(&req_data.pubkey) {
-            match entry.is_expired(now) {
-                true => {
-                    response.state = SessionStateClient::Expired;
-                }
-                false => {
-                    response.state = SessionStateClient::Active;
-                    response.entropy = entry.entropy.clone().into();
-                }
-            };
-        }
+    // Read the database
+    let pubkey = req_data.pubkey.clone();
+    if let Some(entry) = blocking(&storage, move |storage| storage.session_get(&pubkey)).await? {
+        match entry.is_expired(now, &config.session) {
+            true => {
+                response.state = SessionStateClient::Expired;
+            }
+            false => {
+                response.state = SessionStateClient::Active;
+                response.entropy = entry.entropy.into();
+            }
+        };
     }
 
-    HttpResponse::Ok().json(response)
+    Ok(Json(response))
 }
 
 #[post("/session_init")]
 async fn session_init(
-    state_sessions: Data<RwLock<Sessions>>,
+    storage: Data<dyn Storage>,
+    config: Data<Config>,
     req_data: Json<SessionInitRequest>,
-) -> impl Responder {
-    println!("session_init request data: {req_data:?}");
-
-    // Construct default response
-    let mut response = SessionInitResponse::default();
+) -> Result<Json<SessionInitResponse>, ApiError> {
+    log::info!("session_init pubkey={}", req_data.pubkey);
 
     // Current time
     let now = unixtime();
 
-    // New entropy
-    let new_entropy = weak_random_base64_string(44);
-
-    // Attempt to lock and update the database
-    if let Ok(mut sessions) = state_sessions.write() {
-        sessions
-            .data
-            .entry(req_data.pubkey.clone())
-            .and_modify(|entry| {
-                // Return if the session is not expired, otherwise overwrite the data if it timed out, or the game was never started
-                if !entry.is_expired(now) {
-                    response.result = SessionInitResult::ErrorGameActive;
-                    return;
-                }
-
-                entry.entropy = new_entropy.clone();
-                entry.state = SessionStatus::AwaitingSignature;
-                entry.unixtime = now;
-            })
-            .or_insert(Session {
-                entropy: new_entropy.clone(),
-                state: SessionStatus::AwaitingSignature,
-                unixtime: now,
-            });
-
-        // Did we find an active game?
-        if response.result == SessionInitResult::ErrorGameActive {
-            return HttpResponse::Ok().json(response);
-        }
-
-        // The response is positive if we got here
-        response.result = SessionInitResult::Success;
-        response.entropy = Some(new_entropy);
+    // Fail if the session is still in use, otherwise overwrite the data if it timed out, or the game was never started
+    let pubkey = req_data.pubkey.clone();
+    let session = blocking(&storage, move |storage| storage.session_get(&pubkey)).await?;
+    if session.is_some_and(|entry| !entry.is_replaceable(now, &config.session)) {
+        return Err(ApiError::GameActive);
     }
 
-    HttpResponse::Ok().json(response)
+    // New entropy; it becomes the replay GUID and so must never have been issued before
+    let pubkey = req_data.pubkey.clone();
+    let new_entropy = blocking(&storage, move |storage| {
+        for _ in 0..ENTROPY_ATTEMPTS {
+            let candidate = secure_random_base64_string(44);
+            if storage.entropy_register(&candidate, &pubkey, now)? {
+                return Ok(Some(candidate));
+            }
+            log::warn!("Generated entropy was issued before: {candidate}");
+        }
+        Ok(None)
+    })
+    .await?;
+    let Some(new_entropy) = new_entropy else {
+        return Err(anyhow::anyhow!("Failed to generate unused entropy").into());
+    };
+
+    let entry = Session {
+        entropy: new_entropy.clone(),
+        state: SessionStatus::AwaitingSignature,
+        unixtime: now,
+        nft_list: Vec::new(),
+        hero: String::new(),
+        map: String::new(),
+    };
+    let pubkey = req_data.pubkey.clone();
+    blocking(&storage, move |storage| {
+        storage.session_put(&pubkey, &entry)
+    })
+    .await?;
+
+    Ok(Json(SessionInitResponse {
+        entropy: new_entropy,
+    }))
 }
 
 #[post("/session_cancel")]
 async fn session_cancel(
-    state_sessions: Data<RwLock<Sessions>>,
+    storage: Data<dyn Storage>,
+    pubkey_limit: Data<PubkeyRateLimit>,
     req_data: Json<SessionCancelRequest>,
-) -> impl Responder {
-    println!("session_cancel request data: {req_data:?}");
-
-    // Construct default response
-    let mut response = SessionCancelResponse::default();
+) -> Result<HttpResponse, ApiError> {
+    log::info!("session_cancel pubkey={}", req_data.pubkey);
 
-    // Check the signature TODO
+    let pubkey = req_data.pubkey.clone();
+    let entry = blocking(&storage, move |storage| storage.session_get(&pubkey))
+        .await?
+        .ok_or(ApiError::NoSuchSession)?;
 
-    // Delete session data from the DB TODO
+    // Create expected signed message
+    let signed_message = cancel_game_message(&req_data.pubkey, &entry.entropy);
 
-    //TEST helius RPC API
-    let res = test_api().await;
-    match res {
-        Ok(itm) => {
-            println!("item {itm:?}");
-        }
-        Err(e) => {
-            println!("error {e:?}");
-        }
+    // Verify the given signature
+    if !verify_signature(&req_data.pubkey, &signed_message, &req_data.signature) {
+        return Err(ApiError::SignatureInvalid);
     }
+    // Only a signed request counts against the pubkey, others could not use up its budget
+    pubkey_limit.check(&req_data.pubkey)?;
 
-    HttpResponse::Ok().json(response)
+    // Delete session data from the DB
+    let pubkey = req_data.pubkey.clone();
+    blocking(&storage, move |storage| storage.session_delete(&pubkey)).await?;
+
+    Ok(HttpResponse::NoContent().finish())
 }
 
 #[post("/game_start")]
 async fn game_start(
-    state_sessions: Data<RwLock<Sessions>>,
+    storage: Data<dyn Storage>,
+    config: Data<Config>,
+    helius: Data<HeliusClient>,
+    catalog: Data<GameCatalog>,
+    pubkey_limit: Data<PubkeyRateLimit>,
     req_data: Json<GameStartRequest>,
-) -> impl Responder {
-    println!("game_start request data: {req_data:?}");
-
-    // Construct default response
-    let mut response = GameStartResponse::default();
+) -> Result<HttpResponse, ApiError> {
+    log::info!(
+        "game_start pubkey={} entropy={} nft_list={:?} hero={} map={}",
+        req_data.pubkey,
+        req_data.entropy,
+        req_data.nft_list,
+        req_data.hero,
+        req_data.map
+    );
 
     // Current time
     let now = unixtime();
 
     // Find the data
-    if let Ok(mut sessions) = state_sessions.write() {
-        if let Some(entry) = sessions.data.get_mut(&req_data.pubkey) {
-            // Compare given entropy and expected state
-            if entry.entropy != req_data.entropy
-                || entry.state != SessionStatus::AwaitingSignature
-                || entry.unixtime + 600 < now
-            {
-                response.result = GameStartResult::ErrorRequestDataDoesNotMatch;
-                return HttpResponse::Ok().json(response);
-            }
+    let pubkey = req_data.pubkey.clone();
+    let mut entry = blocking(&storage, move |storage| storage.session_get(&pubkey))
+        .await?
+        .ok_or(ApiError::NoSuchSession)?;
+
+    // Compare given entropy and expected state
+    if entry.entropy != req_data.entropy
+        || entry.state != SessionStatus::AwaitingSignature
+        || entry.unixtime + config.session.start < now
+    {
+        return Err(ApiError::RequestDataDoesNotMatch);
+    }
 
-            // Create expected signed message
-            let signed_message = format!("START GAME {}:{}", &req_data.pubkey, &req_data.entropy);
+    // Create expected signed message
+    let equipped = req_data.nft_list.clone().unwrap_or_default();
+    let signed_message = start_game_message(
+        &req_data.pubkey,
+        &req_data.entropy,
+        &equipped,
+        &req_data.hero,
+        &req_data.map,
+    );
+
+    // Verify the given signature
+    if !verify_signature(&req_data.pubkey, &signed_message, &req_data.signature) {
+        return Err(ApiError::SignatureInvalid);
+    }
+    pubkey_limit.check(&req_data.pubkey)?;
 
-            // Verify the given signature
-            // TODO IMPLEMENT ME must match req_data.signature
-            if false {
-                response.result = GameStartResult::ErrorSignatureInvalid;
-                return HttpResponse::Ok().json(response);
-            }
+    // Only a run the simulator can replay may be started; the values end up in its arguments
+    catalog.check(&req_data.hero, &req_data.map)?;
 
-            // Update state to GameStarted
-            entry.state = SessionStatus::GameStarted;
-            entry.unixtime = now;
+    // Limit the number of equipped NFTs; each may only be equipped once
+    let unique: HashSet<&String> = equipped.iter().collect();
+    if equipped.len() > MAX_NUM_NFT || unique.len() != equipped.len() {
+        return Err(ApiError::TooManyNfts);
+    }
 
-            // The response will be success
-            response.result = GameStartResult::Success;
-        } else {
-            response.result = GameStartResult::ErrorNoSuchSession;
+    // The wallet must own every equipped NFT
+    if !equipped.is_empty() {
+        let owned = helius
+            .nfts_by_owner(&req_data.pubkey)
+            .await
+            .map_err(ApiError::Upstream)?;
+        if !equipped
+            .iter()
+            .all(|key| owned.iter().any(|nft| &nft.key == key))
+        {
+            return Err(ApiError::NftNotOwned);
         }
     }
 
-    HttpResponse::Ok().json(response)
+    // Update state to GameStarted and lock the loadout, hero and map
+    entry.state = SessionStatus::GameStarted;
+    entry.unixtime = now;
+    entry.nft_list = equipped;
+    entry.hero = req_data.hero.clone();
+    entry.map = req_data.map.clone();
+    let pubkey = req_data.pubkey.clone();
+    blocking(&storage, move |storage| {
+        storage.session_put(&pubkey, &entry)
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
 }
 
 #[post("/game_complete")]
 async fn game_complete(
-    state_sessions: Data<RwLock<Sessions>>,
+    storage: Data<dyn Storage>,
+    config: Data<Config>,
+    verifier: Data<ReplayVerifier>,
+    pubkey_limit: Data<PubkeyRateLimit>,
     req_data: Json<GameCompleteRequest>,
-) -> impl Responder {
-    println!("game_complete request data: {req_data:?}");
-
-    // Construct default response
-    let mut response = GameCompleteResponse::default();
+) -> Result<Json<GameCompleteResponse>, ApiError> {
+    log::info!(
+        "game_complete pubkey={} entropy={} hero={} map={} outcome={:?}",
+        req_data.pubkey,
+        req_data.entropy,
+        req_data.hero,
+        req_data.map,
+        req_data.outcome
+    );
 
     // Current time
     let now = unixtime();
 
-    if let Ok(mut sessions) = state_sessions.write() {
-        if let Some(entry) = sessions.data.get_mut(&req_data.pubkey) {
-            // Compare given entropy and expected state
-            if entry.entropy != req_data.entropy
-                || entry.state != SessionStatus::GameStarted
-                || entry.unixtime + 3600 < now
-            {
-                response.result = GameCompleteResult::ErrorRequestDataDoesNotMatch;
-                return HttpResponse::Ok().json(response);
-            }
+    let pubkey = req_data.pubkey.clone();
+    let mut entry = blocking(&storage, move |storage| storage.session_get(&pubkey))
+        .await?
+        .ok_or(ApiError::NoSuchSession)?;
+
+    // Create expected signed message
+    let signed_message = complete_game_message(
+        &req_data.pubkey,
+        &req_data.entropy,
+        &req_data.nft_list.clone().unwrap_or_default(),
+        &req_data.hero,
+        &req_data.map,
+    );
+
+    // Verify the given signature
+    if !verify_signature(&req_data.pubkey, &signed_message, &req_data.signature) {
+        return Err(ApiError::SignatureInvalid);
+    }
+    pubkey_limit.check(&req_data.pubkey)?;
 
-            // Create expected signed message
-            let signed_message = format!(
-                "COMPLETE GAME {}:{}:{}",
-                &req_data.pubkey,
-                &req_data.entropy,
-                &req_data.nft_list.clone().unwrap_or_default().join("")
-            );
-
-            // Verify the given signature
-            // TODO IMPLEMENT ME must match req_data.signature
-            if false {
-                response.result = GameCompleteResult::ErrorSignatureInvalid;
-                return HttpResponse::Ok().json(response);
-            }
+    // Each entropy completes only once, so a signed completion can not be replayed
+    // Checked before the session, which was reset by the first completion, so a retry learns it got through
+    let entropy = req_data.entropy.clone();
+    if blocking(&storage, move |storage| storage.entropy_completed(&entropy)).await? {
+        return Err(ApiError::EntropyUsed);
+    }
+
+    // Compare given entropy and expected state
+    if entry.entropy != req_data.entropy
+        || entry.state != SessionStatus::GameStarted
+        || entry.unixtime + config.session.game < now
+    {
+        return Err(ApiError::RequestDataDoesNotMatch);
+    }
+
+    // The loadout must be the one verified when the game started
+    let mut submitted = req_data.nft_list.clone().unwrap_or_default();
+    let mut locked = entry.nft_list.clone();
+    submitted.sort();
+    locked.sort();
+    if submitted != locked {
+        return Err(ApiError::NftLoadoutMismatch);
+    }
+
+    // So must the hero and map, which were checked against the catalog then
+    if req_data.hero != entry.hero || req_data.map != entry.map {
+        return Err(ApiError::RequestDataDoesNotMatch);
+    }
+
+    // Re-simulate the replay with the locked loadout and compare it with the claimed outcome
+    let slot = verifier.try_slot().ok_or(ApiError::Busy)?;
+    let max_ticks = (now.saturating_sub(entry.unixtime) + REPLAY_TIME_SLACK)
+        .saturating_mul(TICKS_PER_SECOND.into());
+    let request = req_data.clone();
+    let loadout = entry.nft_list.clone();
+    let check = web::block(move || verifier.verify(slot, &request, &loadout, max_ticks))
+        .await
+        .map_err(|e| ApiError::Internal(e.into()))??;
+    let verdict = check.verdict;
+
+    // Credit the coins the server recomputed
+    let reward = match (verdict, &check.recomputed) {
+        (ReplayVerdict::Valid, Some(recomputed)) => run_reward(recomputed),
+        _ => 0,
+    };
+    let transaction = (reward > 0).then(|| LedgerTransaction {
+        kind: LedgerKind::RunReward,
+        amount: reward as i64,
+        reference: req_data.entropy.clone(),
+        unixtime: now,
+    });
+
+    // Record the run, its replay and the reward at once and only now consume the entropy, so a
+    // completion the simulator could not verify (busy, crashed, timed out) can be retried
+    // A verdict is final: a rejected run burns the entropy as well, otherwise a client could
+    // resubmit claimed outcomes until one matches; a concurrent completion of the same entropy loses here
+    let run = CompletedRun {
+        pubkey: req_data.pubkey.clone(),
+        entropy: req_data.entropy.clone(),
+        nft_list: entry.nft_list.clone(),
+        hero: entry.hero.clone(),
+        map: entry.map.clone(),
+        season: config.season.clone(),
+        outcome: req_data.outcome.clone(),
+        verdict,
+        started_at: entry.unixtime,
+        completed_at: now,
+    };
+    let replay = req_data.replay.clone();
+    if !blocking(&storage, move |storage| {
+        storage.run_complete(&run, &replay, transaction.as_ref())
+    })
+    .await?
+    {
+        return Err(ApiError::EntropyUsed);
+    }
+
+    // Reset state
+    entry.state = SessionStatus::AwaitingSignature;
+    entry.unixtime = 0;
+    entry.nft_list.clear();
+    entry.hero.clear();
+    entry.map.clear();
+    let pubkey = req_data.pubkey.clone();
+    blocking(&storage, move |storage| {
+        storage.session_put(&pubkey, &entry)
+    })
+    .await?;
+
+    // Only accept the result if the replay confirms it
+    match verdict {
+        ReplayVerdict::Valid => Ok(Json(GameCompleteResponse {
+            replay: verdict,
+            reward,
+        })),
+        _ => Err(ApiError::ReplayRejected(verdict)),
+    }
+}
+
+/// Coins credited for a verified run
+fn run_reward(outcome: &RunOutcome) -> u64 {
+    outcome.coins
+}
+
+#[post("/balance")]
+async fn balance(
+    storage: Data<dyn Storage>,
+    req_data: Json<BalanceRequest>,
+) -> Result<Json<BalanceResponse>, ApiError> {
+    log::info!("balance pubkey={} page={}", req_data.pubkey, req_data.page);
+
+    let page_size = req_data
+        .page_size
+        .unwrap_or(PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let offset = req_data.page.saturating_mul(page_size);
+
+    let pubkey = req_data.pubkey.clone();
+    let response = blocking(&storage, move |storage| {
+        Ok(BalanceResponse {
+            balance: storage.ledger_balance(&pubkey)?,
+            transactions: storage.ledger_history(&pubkey, offset, page_size)?,
+        })
+    })
+    .await?;
+
+    Ok(Json(response))
+}
 
-            // Reset state
-            entry.state = SessionStatus::AwaitingSignature;
-            entry.unixtime = 0;
+#[post("/leaderboard")]
+async fn leaderboard(
+    storage: Data<dyn Storage>,
+    req_data: Json<LeaderboardRequest>,
+) -> Result<Json<LeaderboardResponse>, ApiError> {
+    log::info!(
+        "leaderboard filter={:?} page={}",
+        req_data.filter,
+        req_data.page
+    );
+
+    let page_size = req_data
+        .page_size
+        .unwrap_or(PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let offset = req_data.page.saturating_mul(page_size);
+
+    let filter = req_data.filter.clone();
+    let entries = blocking(&storage, move |storage| {
+        storage.leaderboard(&filter, offset, page_size)
+    })
+    .await?;
+
+    Ok(Json(LeaderboardResponse { entries }))
+}
+
+#[post("/leaderboard/rank")]
+async fn leaderboard_rank(
+    storage: Data<dyn Storage>,
+    req_data: Json<LeaderboardRankRequest>,
+) -> Result<Json<LeaderboardRankResponse>, ApiError> {
+    log::info!(
+        "leaderboard_rank pubkey={} filter={:?}",
+        req_data.pubkey,
+        req_data.filter
+    );
+
+    let filter = req_data.filter.clone();
+    let pubkey = req_data.pubkey.clone();
+    let entry = blocking(&storage, move |storage| {
+        storage.leaderboard_rank(&filter, &pubkey)
+    })
+    .await?
+    .ok_or(ApiError::NotRanked)?;
+
+    Ok(Json(LeaderboardRankResponse { entry }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{BucketConfig, RateLimitConfig};
+    use crate::game_client_routes::client_routes;
+    use crate::rate_limit::SystemClock;
+    use crate::storage::MemoryStorage;
+    use actix_web::body::MessageBody;
+    use actix_web::dev::{Service, ServiceResponse};
+    use actix_web::http::StatusCode;
+    use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+    use actix_web::App;
+    use ed25519_dalek::{Signer, SigningKey};
+    use rand::rngs::OsRng;
+    use serde::Serialize;
+    use serde_json::Value;
+    #[cfg(unix)]
+    use speedrun_survivors_replay::{Replay, ReplayAction, ReplayInput};
+    use std::sync::Arc;
+
+    /// The directory of the game assets, for the hero roster
+    const GAME_ASSETS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../speedrun-survivors-bevy");
+
+    struct Wallet {
+        key: SigningKey,
+        pubkey: String,
+    }
+
+    impl Wallet {
+        fn new() -> Self {
+            let key = SigningKey::generate(&mut OsRng);
+            let pubkey = bs58::encode(key.verifying_key().as_bytes()).into_string();
+            Self { key, pubkey }
+        }
+
+        fn sign(&self, message: &str) -> String {
+            bs58::encode(self.key.sign(message.as_bytes()).to_bytes()).into_string()
+        }
+    }
+
+    fn config() -> Config {
+        Config {
+            rate_limit: RateLimitConfig {
+                per_pubkey: BucketConfig {
+                    capacity: 2,
+                    refill_per_sec: 0.01,
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    async fn app(
+        config: Config,
+    ) -> impl Service<
+        actix_http::Request,
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+    > {
+        let storage: Arc<dyn Storage> = Arc::new(MemoryStorage::default());
+        let catalog = GameCatalog::load(&Config {
+            simulator_assets: GAME_ASSETS.to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        init_service(
+            App::new()
+                .app_data(Data::from(storage))
+                .app_data(Data::new(
+                    HeliusClient::new("http://127.0.0.1:1/", "collection").unwrap(),
+                ))
+                .app_data(Data::new(ReplayVerifier::new(&config)))
+                .app_data(Data::new(catalog))
+                .app_data(Data::new(PubkeyRateLimit::new(
+                    &config.rate_limit,
+                    Arc::new(SystemClock),
+                )))
+                .app_data(Data::new(config))
+                .service(client_routes()),
+        )
+        .await
+    }
+
+    fn post(ip: &str, route: &str, body: impl Serialize) -> actix_http::Request {
+        TestRequest::post()
+            .uri(&format!("/play/{route}"))
+            .peer_addr(format!("{ip}:1234").parse().unwrap())
+            .set_json(body)
+            .to_request()
+    }
+
+    async fn session_init(
+        app: &impl Service<
+            actix_http::Request,
+            Response = ServiceResponse<impl MessageBody>,
+            Error = actix_web::Error,
+        >,
+        ip: &str,
+        pubkey: &str,
+    ) -> String {
+        let request = SessionInitRequest {
+            pubkey: pubkey.to_string(),
+        };
+        let res = call_service(app, post(ip, "session_init", request)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: SessionInitResponse = read_body_json(res).await;
+        body.entropy
+    }
+
+    fn start(wallet: &Wallet, entropy: &str, hero: &str, map: &str) -> GameStartRequest {
+        GameStartRequest {
+            pubkey: wallet.pubkey.clone(),
+            entropy: entropy.to_string(),
+            nft_list: None,
+            hero: hero.to_string(),
+            map: map.to_string(),
+            signature: wallet.sign(&start_game_message(&wallet.pubkey, entropy, &[], hero, map)),
+        }
+    }
 
-            // The response will be success
-            response.result = GameCompleteResult::Success;
-        } else {
-            response.result = GameCompleteResult::ErrorNoSuchSession;
+    fn cancel(wallet: &Wallet, entropy: &str) -> SessionCancelRequest {
+        SessionCancelRequest {
+            pubkey: wallet.pubkey.clone(),
+            signature: wallet.sign(&cancel_game_message(&wallet.pubkey, entropy)),
         }
     }
 
-    HttpResponse::Ok().json(response)
+    #[actix_web::test]
+    async fn unsigned_requests_do_not_limit_the_pubkey() {
+        let app = app(config()).await;
+        let victim = Wallet::new();
+        let entropy = session_init(&app, "10.0.0.1", &victim.pubkey).await;
+
+        // Another client floods the signed routes with the pubkey of the victim
+        let forged = SessionCancelRequest {
+            pubkey: victim.pubkey.clone(),
+            ..cancel(&Wallet::new(), &entropy)
+        };
+        for _ in 0..5 {
+            let res = call_service(&app, post("10.0.0.2", "session_cancel", &forged)).await;
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        // The victim still has its whole budget
+        let res = call_service(
+            &app,
+            post("10.0.0.1", "session_cancel", cancel(&victim, &entropy)),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let entropy = session_init(&app, "10.0.0.1", &victim.pubkey).await;
+        let res = call_service(
+            &app,
+            post("10.0.0.1", "session_cancel", cancel(&victim, &entropy)),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        // Which is only spent by its own signed requests
+        let entropy = session_init(&app, "10.0.0.1", &victim.pubkey).await;
+        let res = call_service(
+            &app,
+            post("10.0.0.1", "session_cancel", cancel(&victim, &entropy)),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        let body: Value = read_body_json(res).await;
+        assert_eq!(body["error"], "RateLimited");
+    }
+
+    #[actix_web::test]
+    async fn unknown_hero_or_map_is_rejected_at_start() {
+        let app = app(Config::default()).await;
+        let wallet = Wallet::new();
+        let entropy = session_init(&app, "10.0.0.1", &wallet.pubkey).await;
+
+        // Neither reaches the simulator or the leaderboard, and the session can still be started
+        for (hero, map) in [("Nobody", "Map1"), ("Pepe", "--nfts"), ("Pepe", "map1")] {
+            let res = call_service(
+                &app,
+                post(
+                    "10.0.0.1",
+                    "game_start",
+                    start(&wallet, &entropy, hero, map),
+                ),
+            )
+            .await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{hero} {map}");
+            let body: Value = read_body_json(res).await;
+            assert_eq!(body["error"], "BadRequest");
+        }
+
+        let res = call_service(
+            &app,
+            post(
+                "10.0.0.1",
+                "game_start",
+                start(&wallet, &entropy, "Orca", "Map2"),
+            ),
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    /// Stand-in simulator that recomputes `OUTCOME` for any replay; written once, a file still
+    /// open for writing in one test can not be executed by another
+    #[cfg(unix)]
+    fn simulator() -> &'static str {
+        use std::os::unix::fs::PermissionsExt;
+        use std::sync::OnceLock;
+
+        static SIMULATOR: OnceLock<String> = OnceLock::new();
+        SIMULATOR.get_or_init(|| {
+            let path = std::env::temp_dir().join(format!("routes-sim-{}", std::process::id()));
+            std::fs::write(
+                &path,
+                format!("#!/bin/sh\ncat > /dev/null\necho '{OUTCOME}'\n"),
+            )
+            .unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            path.to_string_lossy().into_owned()
+        })
+    }
+
+    #[cfg(unix)]
+    const OUTCOME: &str = r#"{"ticks":120,"kills":3,"coins":40,"level":2}"#;
+
+    /// Start a game without NFTs and return a signed completion of it claiming `outcome`
+    #[cfg(unix)]
+    async fn played_game(
+        app: &impl Service<
+            actix_http::Request,
+            Response = ServiceResponse<impl MessageBody>,
+            Error = actix_web::Error,
+        >,
+        wallet: &Wallet,
+        outcome: RunOutcome,
+    ) -> GameCompleteRequest {
+        let entropy = session_init(app, "10.0.0.1", &wallet.pubkey).await;
+        let start = start(wallet, &entropy, "Pepe", "Map1");
+        let res = call_service(app, post("10.0.0.1", "game_start", start)).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let mut replay = Replay {
+            end_tick: outcome.ticks,
+            ..Default::default()
+        };
+        replay.push(10, ReplayInput::Press(ReplayAction::MoveUp));
+        GameCompleteRequest {
+            pubkey: wallet.pubkey.clone(),
+            entropy: entropy.clone(),
+            nft_list: None,
+            hero: "Pepe".to_string(),
+            map: "Map1".to_string(),
+            replay: replay.to_base64().unwrap(),
+            outcome,
+            signature: wallet.sign(&complete_game_message(
+                &wallet.pubkey,
+                &entropy,
+                &[],
+                "Pepe",
+                "Map1",
+            )),
+        }
+    }
+
+    #[cfg(unix)]
+    fn simulated_config() -> Config {
+        Config {
+            simulator_path: simulator().to_string(),
+            simulator_timeout: 10,
+            simulator_concurrency: 4,
+            ..Default::default()
+        }
+    }
+
+    #[cfg(unix)]
+    #[actix_web::test]
+    async fn valid_run_is_credited() {
+        let app = app(simulated_config()).await;
+        let wallet = Wallet::new();
+        let complete = played_game(&app, &wallet, serde_json::from_str(OUTCOME).unwrap()).await;
+
+        let res = call_service(&app, post("10.0.0.1", "game_complete", &complete)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: GameCompleteResponse = read_body_json(res).await;
+        assert_eq!((body.replay, body.reward), (ReplayVerdict::Valid, 40));
+    }
+
+    #[cfg(unix)]
+    #[actix_web::test]
+    async fn rejected_run_can_not_be_resubmitted() {
+        let app = app(simulated_config()).await;
+        let wallet = Wallet::new();
+        let mut claimed: RunOutcome = serde_json::from_str(OUTCOME).unwrap();
+        claimed.coins += 1000;
+        let mut complete = played_game(&app, &wallet, claimed).await;
+
+        let res = call_service(&app, post("10.0.0.1", "game_complete", &complete)).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: Value = read_body_json(res).await;
+        assert_eq!(body["error"], "ReplayRejected");
+
+        // The verdict is final, not even the outcome the server recomputed is accepted now
+        complete.outcome = serde_json::from_str(OUTCOME).unwrap();
+        let res = call_service(&app, post("10.0.0.1", "game_complete", &complete)).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let body: Value = read_body_json(res).await;
+        assert_eq!(body["error"], "EntropyUsed");
+
+        // The session was reset, the next game can be played
+        played_game(&app, &wallet, serde_json::from_str(OUTCOME).unwrap()).await;
+    }
+
+    #[cfg(unix)]
+    #[actix_web::test]
+    async fn completion_is_bound_to_the_locked_hero_and_map() {
+        let app = app(simulated_config()).await;
+        let wallet = Wallet::new();
+        let complete = played_game(&app, &wallet, serde_json::from_str(OUTCOME).unwrap()).await;
+
+        for (hero, map) in [("Orca", "Map1"), ("Pepe", "Map2")] {
+            let other = GameCompleteRequest {
+                hero: hero.to_string(),
+                map: map.to_string(),
+                ..complete.clone()
+            };
+            let res = call_service(&app, post("10.0.0.1", "game_complete", &other)).await;
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+            // Signing another hero or map does not move the run to its board either
+            let relabelled = GameCompleteRequest {
+                signature: wallet.sign(&complete_game_message(
+                    &wallet.pubkey,
+                    &complete.entropy,
+                    &[],
+                    hero,
+                    map,
+                )),
+                ..other
+            };
+            let res = call_service(&app, post("10.0.0.1", "game_complete", &relabelled)).await;
+            assert_eq!(res.status(), StatusCode::CONFLICT);
+            let body: Value = read_body_json(res).await;
+            assert_eq!(body["error"], "RequestDataDoesNotMatch");
+        }
+
+        let res = call_service(&app, post("10.0.0.1", "game_complete", &complete)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
 }
@@ -1,32 +1,103 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use speedrun_survivors_model::{NftData, NftKind};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
-pub async fn test_api() -> Result<Value> {
-    // let client = reqwest::Client::new();
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(8))
-        .build()?;
+/// Largest page size the DAS API allows
+const PAGE_LIMIT: u32 = 1000;
+/// Stop paging after this many pages; no wallet should hold that many of our assets
+const MAX_PAGES: u32 = 10;
 
-    let req_data = GetAssetsByOwnerRequest::default_with_address(
-        "86xCnPeV69n6t3DnyGvkKobf9FdN2H9oiVDdaMpo2MMY".to_string(),
-    );
-    let response = client
-        .post("https://api.mainnet-beta.solana.com")
-        .json(&req_data)
-        .send()
-        .await?
-        .json::<Value>()
-        .await?;
-    Ok(response)
-
-    //TODO process to essential data
+/// Client for the Helius DAS (digital asset standard) JSON-RPC API
+#[derive(Clone)]
+pub struct HeliusClient {
+    client: reqwest::Client,
+    rpc_url: String,
+    collection: String,
+    next_id: Arc<AtomicU64>,
 }
 
-#[derive(Deserialize, Debug)]
-pub struct APIResponse {
-    blah: String,
+impl HeliusClient {
+    pub fn new(rpc_url: &str, collection: &str) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(8))
+            .build()?;
+
+        Ok(Self {
+            client,
+            rpc_url: rpc_url.to_string(),
+            collection: collection.to_string(),
+            next_id: Arc::new(AtomicU64::new(1)),
+        })
+    }
+
+    /// All NFTs of our collection owned by `owner`
+    /// Assets without a known kind are skipped
+    pub async fn nfts_by_owner(&self, owner: &str) -> Result<Vec<NftData>> {
+        let mut nfts = Vec::new();
+
+        for page in 1..=MAX_PAGES {
+            let assets = self.assets_by_owner(owner, page).await?;
+            let last_page = assets.items.len() < PAGE_LIMIT as usize;
+
+            nfts.extend(
+                assets
+                    .items
+                    .into_iter()
+                    .filter(|asset| !asset.burnt && asset.in_collection(&self.collection))
+                    .filter_map(Asset::into_nft_data),
+            );
+
+            if last_page {
+                return Ok(nfts);
+            }
+        }
+
+        log::warn!("Assets of {owner} exceed {MAX_PAGES} pages, the list is incomplete");
+        Ok(nfts)
+    }
+
+    /// A single page of `getAssetsByOwner`
+    async fn assets_by_owner(&self, owner: &str, page: u32) -> Result<AssetList> {
+        let req_data = GetAssetsByOwnerRequest {
+            json_rpc: "2.0".to_string(),
+            id: self.next_id.fetch_add(1, Ordering::Relaxed).to_string(),
+            method: "getAssetsByOwner".to_string(),
+            params: GetAssetsByOwnerRequestParams {
+                owner_address: owner.to_string(),
+                page,
+                limit: PAGE_LIMIT,
+            },
+        };
+
+        let response = self
+            .client
+            .post(&self.rpc_url)
+            .json(&req_data)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<RpcResponse<AssetList>>()
+            .await?;
+
+        match response {
+            RpcResponse {
+                error: Some(error), ..
+            } => bail!(
+                "getAssetsByOwner failed: {} ({})",
+                error.message,
+                error.code
+            ),
+            RpcResponse {
+                result: Some(result),
+                ..
+            } => Ok(result),
+            _ => bail!("getAssetsByOwner returned neither result nor error"),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -46,17 +117,317 @@ struct GetAssetsByOwnerRequestParams {
     limit: u32,
 }
 
-impl GetAssetsByOwnerRequest {
-    fn default_with_address(address: String) -> Self {
-        Self {
-            json_rpc: "2.0".to_string(),
-            id: "unique_id_todo".to_string(), //TODO
-            method: "getAssetsByOwner".to_string(),
-            params: GetAssetsByOwnerRequestParams {
-                owner_address: address,
-                page: 1,
-                limit: 1000,
-            },
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct AssetList {
+    #[serde(default)]
+    items: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    id: String,
+    #[serde(default)]
+    content: AssetContent,
+    #[serde(default)]
+    grouping: Vec<AssetGroup>,
+    #[serde(default)]
+    burnt: bool,
+}
+
+#[derive(Deserialize, Default)]
+struct AssetContent {
+    #[serde(default)]
+    metadata: AssetMetadata,
+    #[serde(default)]
+    links: AssetLinks,
+    #[serde(default)]
+    files: Vec<AssetFile>,
+}
+
+#[derive(Deserialize, Default)]
+struct AssetMetadata {
+    #[serde(default)]
+    attributes: Vec<AssetAttribute>,
+}
+
+#[derive(Deserialize)]
+struct AssetAttribute {
+    #[serde(default)]
+    trait_type: String,
+    #[serde(default)]
+    value: Value,
+}
+
+#[derive(Deserialize, Default)]
+struct AssetLinks {
+    image: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AssetFile {
+    uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AssetGroup {
+    group_key: String,
+    group_value: String,
+}
+
+impl Asset {
+    fn in_collection(&self, collection: &str) -> bool {
+        self.grouping
+            .iter()
+            .any(|group| group.group_key == "collection" && group.group_value == collection)
+    }
+
+    /// The kind is taken from the `kind` attribute of the metadata
+    fn kind(&self) -> Option<NftKind> {
+        let attribute = self
+            .content
+            .metadata
+            .attributes
+            .iter()
+            .find(|attribute| attribute.trait_type.eq_ignore_ascii_case("kind"))?;
+
+        match attribute.value.as_str()? {
+            "Hero" => Some(NftKind::Hero),
+            "Weapon" => Some(NftKind::Weapon),
+            "PowerUp" => Some(NftKind::PowerUp),
+            _ => None,
         }
     }
+
+    /// Prefer the image link, fall back to the first file
+    fn image_url(&self) -> Option<String> {
+        self.content
+            .links
+            .image
+            .clone()
+            .or_else(|| self.content.files.iter().find_map(|file| file.uri.clone()))
+    }
+
+    fn into_nft_data(self) -> Option<NftData> {
+        Some(NftData {
+            kind: self.kind()?,
+            image_url: self.image_url().unwrap_or_default(),
+            key: self.id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::web::{self, Data, Json};
+    use actix_web::{App, HttpResponse, HttpServer};
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    const OWNER: &str = "owner";
+    const COLLECTION: &str = "collection";
+
+    /// Stand-in for the RPC provider; answers each page with `respond` and records the pages asked for
+    struct MockRpc {
+        respond: Box<dyn Fn(u32) -> HttpResponse + Send + Sync>,
+        pages: Mutex<Vec<u32>>,
+    }
+
+    async fn mock_rpc(mock: Data<MockRpc>, req: Json<Value>) -> HttpResponse {
+        assert_eq!(req["method"], "getAssetsByOwner");
+        assert_eq!(req["params"]["ownerAddress"], OWNER);
+        assert_eq!(req["params"]["limit"], PAGE_LIMIT);
+        let page = req["params"]["page"].as_u64().unwrap() as u32;
+        mock.pages.lock().unwrap().push(page);
+        (mock.respond)(page)
+    }
+
+    fn serve(
+        respond: impl Fn(u32) -> HttpResponse + Send + Sync + 'static,
+    ) -> (HeliusClient, Data<MockRpc>) {
+        let mock = Data::new(MockRpc {
+            respond: Box::new(respond),
+            pages: Mutex::new(Vec::new()),
+        });
+        let app_mock = Data::clone(&mock);
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(Data::clone(&app_mock))
+                .route("/", web::post().to(mock_rpc))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+
+        let client = HeliusClient::new(&format!("http://{addr}/"), COLLECTION).unwrap();
+        (client, mock)
+    }
+
+    fn result(items: Vec<Value>) -> HttpResponse {
+        HttpResponse::Ok().json(json!({"jsonrpc": "2.0", "id": "1", "result": {"items": items}}))
+    }
+
+    fn asset(id: &str, kind: &str) -> Value {
+        json!({
+            "id": id,
+            "content": {
+                "metadata": {"attributes": [{"trait_type": "kind", "value": kind}]},
+                "links": {"image": format!("https://img/{id}.png")},
+            },
+            "grouping": [{"group_key": "collection", "group_value": COLLECTION}],
+            "burnt": false,
+        })
+    }
+
+    fn keys(nfts: &[NftData]) -> Vec<&str> {
+        nfts.iter().map(|nft| nft.key.as_str()).collect()
+    }
+
+    #[actix_web::test]
+    async fn pages_until_a_short_page() {
+        let (client, mock) = serve(|page| {
+            let len = match page {
+                1 => PAGE_LIMIT,
+                _ => 1,
+            };
+            result(
+                (0..len)
+                    .map(|idx| asset(&format!("{page}-{idx}"), "Hero"))
+                    .collect(),
+            )
+        });
+
+        let nfts = client.nfts_by_owner(OWNER).await.unwrap();
+        assert_eq!(nfts.len(), PAGE_LIMIT as usize + 1);
+        assert_eq!(nfts.last().unwrap().key, "2-0");
+        assert_eq!(*mock.pages.lock().unwrap(), vec![1, 2]);
+    }
+
+    #[actix_web::test]
+    async fn stops_after_max_pages() {
+        let (client, mock) = serve(|page| {
+            result(
+                (0..PAGE_LIMIT)
+                    .map(|idx| asset(&format!("{page}-{idx}"), "Weapon"))
+                    .collect(),
+            )
+        });
+
+        let nfts = client.nfts_by_owner(OWNER).await.unwrap();
+        assert_eq!(nfts.len(), (PAGE_LIMIT * MAX_PAGES) as usize);
+        assert_eq!(
+            *mock.pages.lock().unwrap(),
+            (1..=MAX_PAGES).collect::<Vec<_>>()
+        );
+    }
+
+    #[actix_web::test]
+    async fn skips_other_collections_and_burnt_assets() {
+        let (client, _) = serve(|_| {
+            let mut other_collection = asset("other", "Hero");
+            other_collection["grouping"][0]["group_value"] = json!("someone else");
+            let mut no_collection = asset("ungrouped", "Hero");
+            no_collection["grouping"] = json!([]);
+            let mut burnt = asset("burnt", "Hero");
+            burnt["burnt"] = json!(true);
+            result(vec![
+                asset("ours", "Hero"),
+                other_collection,
+                no_collection,
+                burnt,
+            ])
+        });
+
+        let nfts = client.nfts_by_owner(OWNER).await.unwrap();
+        assert_eq!(keys(&nfts), vec!["ours"]);
+    }
+
+    #[actix_web::test]
+    async fn maps_the_kind_attribute() {
+        let (client, _) = serve(|_| {
+            let mut capitalized = asset("capitalized", "PowerUp");
+            capitalized["content"]["metadata"]["attributes"][0]["trait_type"] = json!("Kind");
+            let mut numeric = asset("numeric", "Hero");
+            numeric["content"]["metadata"]["attributes"][0]["value"] = json!(1);
+            let mut untyped = asset("untyped", "Hero");
+            untyped["content"]["metadata"]["attributes"] = json!([]);
+            result(vec![
+                asset("hero", "Hero"),
+                asset("weapon", "Weapon"),
+                asset("power-up", "PowerUp"),
+                asset("skin", "Skin"),
+                capitalized,
+                numeric,
+                untyped,
+            ])
+        });
+
+        let nfts = client.nfts_by_owner(OWNER).await.unwrap();
+        let kinds: Vec<(&str, NftKind)> = nfts
+            .iter()
+            .map(|nft| (nft.key.as_str(), nft.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("hero", NftKind::Hero),
+                ("weapon", NftKind::Weapon),
+                ("power-up", NftKind::PowerUp),
+                ("capitalized", NftKind::PowerUp),
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn image_falls_back_to_the_files() {
+        let (client, _) = serve(|_| {
+            let mut from_file = asset("from-file", "Hero");
+            from_file["content"]["links"] = json!({});
+            from_file["content"]["files"] =
+                json!([{"mime": "image/png"}, {"uri": "https://img/file.png"}]);
+            let mut no_image = asset("no-image", "Hero");
+            no_image["content"]["links"] = json!({});
+            result(vec![asset("linked", "Hero"), from_file, no_image])
+        });
+
+        let nfts = client.nfts_by_owner(OWNER).await.unwrap();
+        let images: Vec<&str> = nfts.iter().map(|nft| nft.image_url.as_str()).collect();
+        assert_eq!(
+            images,
+            vec!["https://img/linked.png", "https://img/file.png", ""]
+        );
+    }
+
+    #[actix_web::test]
+    async fn rpc_errors_fail_the_request() {
+        let (client, _) = serve(|_| {
+            HttpResponse::Ok().json(json!({
+                "jsonrpc": "2.0",
+                "id": "1",
+                "error": {"code": -32602, "message": "Invalid owner"},
+            }))
+        });
+        let error = client.nfts_by_owner(OWNER).await.unwrap_err();
+        assert!(error.to_string().contains("Invalid owner"), "{error}");
+
+        let (client, _) = serve(|_| HttpResponse::Ok().json(json!({"jsonrpc": "2.0", "id": "1"})));
+        assert!(client.nfts_by_owner(OWNER).await.is_err());
+
+        let (client, _) = serve(|_| HttpResponse::TooManyRequests().finish());
+        assert!(client.nfts_by_owner(OWNER).await.is_err());
+    }
 }
@@ -1,81 +1,111 @@
+mod config;
+mod error;
 mod game_client_routes;
 mod helius_rpc;
+mod rate_limit;
+mod replay;
+mod storage;
+mod sweeper;
 mod utils;
 
 use actix_cors::Cors;
 use actix_web::web::Data;
-use actix_web::{middleware, web, App, HttpResponse, HttpServer, Responder};
-use anyhow::{bail, Result};
-use rustls::{Certificate, PrivateKey, ServerConfig};
+use actix_web::{http, middleware, web, App, HttpResponse, HttpServer, Responder};
+use anyhow::{bail, Context, Result};
+use config::{Config, CorsConfig, ServerConfig};
+use helius_rpc::HeliusClient;
+use rate_limit::{PubkeyRateLimit, RateLimit, SystemClock};
+use replay::{GameCatalog, ReplayVerifier};
+use rustls::{Certificate, PrivateKey};
 use rustls_pemfile::{certs, pkcs8_private_keys};
-use std::collections::HashMap;
-use std::env;
 use std::fs::File;
 use std::io::BufReader;
-use std::sync::RwLock;
+use std::sync::Arc;
+use storage::{MemoryStorage, SqliteStorage, Storage};
 
-#[derive(Debug, PartialEq)]
-enum SessionStatus {
-    AwaitingSignature,
-    GameStarted,
-}
+#[actix_web::main]
+async fn main() -> Result<()> {
+    env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
 
-#[derive(Debug)]
-struct Session {
-    entropy: String,
-    state: SessionStatus,
-    unixtime: u64,
-}
+    let config = Config::load()?;
 
-impl Session {
-    fn is_expired(&self, now: u64) -> bool {
-        // In case of AwaitingSignature we must wait some time for the client to sign, otherwise there might be a DoS opportunity
-        (self.state == SessionStatus::AwaitingSignature && self.unixtime + 30 < now)
-            || self.unixtime + 3600 < now
-    }
-}
+    let state_storage = configure_storage(&config)?;
 
-#[derive(Default)]
-struct Sessions {
-    data: HashMap<String, Session>,
-}
+    sweeper::spawn_sweeper(Data::clone(&state_storage), &config);
 
-#[actix_web::main]
-async fn main() -> Result<()> {
-    env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
+    let helius = HeliusClient::new(&config.rpc_url, &config.nft_collection)?;
 
-    let conf_ssl = configure_ssl()?;
+    let bind = (config.server.bind.clone(), config.server.port);
+    let conf_ssl = match config.server.tls {
+        true => Some(configure_ssl(&config.server)?),
+        false => None,
+    };
 
-    let conf_env = configure_from_env()?;
+    let verifier = Data::new(ReplayVerifier::new(&config));
+    let catalog = Data::new(GameCatalog::load(&config)?);
 
-    let state_sessions = Data::new(RwLock::new(Sessions::default()));
+    let rate_limit = RateLimit::new(&config.rate_limit, Arc::new(SystemClock));
+    let pubkey_limit = Data::new(PubkeyRateLimit::new(
+        &config.rate_limit,
+        Arc::new(SystemClock),
+    ));
 
-    log::info!("Starting HTTPS server at https://localhost:8443");
-    HttpServer::new(move || {
-        let conf_cors = configure_cors();
+    let conf_server = config.clone();
+    let server = HttpServer::new(move || {
+        let conf_cors = configure_cors(&conf_server.cors);
 
         App::new()
             .wrap(middleware::Logger::default())
             .wrap(conf_cors)
-            .app_data(Data::clone(&state_sessions))
-            .app_data(web::Data::new(conf_env.clone()))
-            .service(game_client_routes::client_routes())
+            .app_data(Data::clone(&state_storage))
+            .app_data(web::Data::new(conf_server.clone()))
+            .app_data(web::Data::new(helius.clone()))
+            .app_data(Data::clone(&verifier))
+            .app_data(Data::clone(&catalog))
+            .app_data(Data::clone(&pubkey_limit))
+            .service(game_client_routes::client_routes().wrap(rate_limit.clone()))
+            .configure(|cfg| {
+                if conf_server.session_stats {
+                    cfg.service(
+                        web::resource("/stats/sessions")
+                            .wrap(rate_limit.clone())
+                            .route(web::get().to(sweeper::session_stats)),
+                    );
+                }
+            })
             .default_service(web::route().to(version))
-    })
-    .bind_rustls_021(("127.0.0.1", 8443), conf_ssl)?
-    .run()
-    .await
-    .map_err(anyhow::Error::from)
+    });
+
+    let server = match conf_ssl {
+        Some(conf_ssl) => {
+            log::info!("Starting HTTPS server at https://{}:{}", bind.0, bind.1);
+            server.bind_rustls_021(bind.clone(), conf_ssl)
+        }
+        None => {
+            log::warn!("TLS is disabled, serve this behind a reverse proxy that terminates TLS");
+            log::info!("Starting HTTP server at http://{}:{}", bind.0, bind.1);
+            server.bind(bind.clone())
+        }
+    }
+    .with_context(|| format!("Failed to bind {}:{}", bind.0, bind.1))?;
+
+    server.run().await.map_err(anyhow::Error::from)
 }
 
-fn configure_ssl() -> Result<ServerConfig> {
-    let config = ServerConfig::builder()
+fn configure_ssl(conf_server: &ServerConfig) -> Result<rustls::ServerConfig> {
+    let config = rustls::ServerConfig::builder()
         .with_safe_defaults()
         .with_no_client_auth();
 
     // Load the files
-    let cert_file = &mut BufReader::new(File::open("cert/cert.pem").unwrap());
-    let key_file = &mut BufReader::new(File::open("cert/key.pem").unwrap());
+    let cert_file = &mut BufReader::new(
+        File::open(&conf_server.cert_path)
+            .with_context(|| format!("Failed to open certificate {}", conf_server.cert_path))?,
+    );
+    let key_file = &mut BufReader::new(
+        File::open(&conf_server.key_path)
+            .with_context(|| format!("Failed to open private key {}", conf_server.key_path))?,
+    );
 
     // Convert
     let cert_chain = certs(cert_file)?.into_iter().map(Certificate).collect();
@@ -86,39 +116,52 @@ fn configure_ssl() -> Result<ServerConfig> {
 
     // Must have the key files
     if keys.is_empty() {
-        bail!("Error loading certificate or key files");
+        bail!("No PKCS#8 private key found in {}", conf_server.key_path);
     }
 
-    Ok(config.with_single_cert(cert_chain, keys.remove(0)).unwrap())
+    config
+        .with_single_cert(cert_chain, keys.remove(0))
+        .context("Invalid certificate or private key")
 }
 
-fn configure_cors() -> Cors {
-    Cors::default()
-        // .allowed_origin("*") //TODO restrict
-        // .allowed_origin_fn(|origin, _req_head| {
-        //     origin.as_bytes().ends_with(b".rust-lang.org")
-        // })
-        // .allowed_methods(vec!["GET", "POST"])
-        // .allowed_headers(vec![http::header::AUTHORIZATION, http::header::ACCEPT])
-        // .allowed_header(http::header::CONTENT_TYPE)
-        .max_age(3600)
-}
+fn configure_cors(conf_cors: &CorsConfig) -> Cors {
+    let cors = Cors::default()
+        .allowed_methods(vec!["GET", "POST"])
+        .allowed_header(http::header::CONTENT_TYPE)
+        .max_age(conf_cors.max_age);
 
-fn configure_from_env() -> Result<EnvConfig> {
-    let rpc_url = env::var("RPC_URL")?;
+    if conf_cors.allowed_origins.iter().any(|origin| origin == "*") {
+        return cors.allow_any_origin();
+    }
 
-    Ok(EnvConfig { rpc_url })
+    conf_cors
+        .allowed_origins
+        .iter()
+        .fold(cors, |cors, origin| cors.allowed_origin(origin))
 }
 
-#[derive(Clone)]
-pub struct EnvConfig {
-    rpc_url: String,
+fn configure_storage(config: &Config) -> Result<Data<dyn Storage>> {
+    let storage: Arc<dyn Storage> = match config.db_path.as_str() {
+        ":memory:" => {
+            log::warn!("Using in-memory storage, all data will be lost on shutdown");
+            Arc::new(MemoryStorage::default())
+        }
+        path => {
+            log::info!("Opening database at {path}");
+            Arc::new(
+                SqliteStorage::open(path)
+                    .with_context(|| format!("Failed to open database {path}"))?,
+            )
+        }
+    };
+
+    Ok(Data::from(storage))
 }
 
 async fn version() -> impl Responder {
-    HttpResponse::Ok().body(format!(
-        "{{\"app\":\"{}\",\"version\":\"{}\"}}",
-        env!("CARGO_PKG_NAME"),
-        env!("CARGO_PKG_VERSION")
-    ))
+    HttpResponse::Ok().json(speedrun_survivors_model::VersionResponse {
+        app: env!("CARGO_PKG_NAME").to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        api_version: speedrun_survivors_model::API_VERSION,
+    })
 }
@@ -0,0 +1,485 @@
+use actix_web::body::EitherBody;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::{Error, ResponseError};
+use std::collections::HashMap;
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::{BucketConfig, RateLimitConfig};
+use crate::error::ApiError;
+
+/// Most keys a limiter tracks; beyond that buckets that are full again are dropped, or else the
+/// least recently used ones
+const MAX_BUCKETS: usize = 100_000;
+
+/// Least recently used buckets dropped at once, so the eviction doesn't run for every new key
+const EVICTED_AT_ONCE: usize = MAX_BUCKETS / 10;
+
+/// Source of the current time, so the limiter can be driven by a controllable clock
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Classic token bucket: holds up to `capacity` tokens and regains `refill_per_sec` tokens per second
+#[derive(Clone, Copy, Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn full(config: &BucketConfig, now: Instant) -> Self {
+        Self {
+            tokens: config.capacity as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, config: &BucketConfig, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * config.refill_per_sec).min(config.capacity as f64);
+        self.updated = now;
+    }
+
+    /// Take a token; if there is none, returns how long until the next one is available
+    fn take(&mut self, config: &BucketConfig, now: Instant) -> Result<(), Duration> {
+        self.refill(config, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+
+        let missing = 1.0 - self.tokens;
+        Err(Duration::try_from_secs_f64(missing / config.refill_per_sec).unwrap_or(Duration::MAX))
+    }
+
+    fn is_full(&self, config: &BucketConfig, now: Instant) -> bool {
+        let mut bucket = *self;
+        bucket.refill(config, now);
+        bucket.tokens >= config.capacity as f64
+    }
+}
+
+/// One token bucket per key, e.g. per IP address or per pubkey
+pub struct RateLimiter {
+    config: BucketConfig,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: BucketConfig) -> Self {
+        Self {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token from the bucket of `key`; on rejection returns the time until a retry can succeed
+    pub fn check(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let Ok(mut buckets) = self.buckets.lock() else {
+            // Never lock players out because of a poisoned lock
+            return Ok(());
+        };
+
+        if buckets.len() >= MAX_BUCKETS && !buckets.contains_key(key) {
+            self.evict(&mut buckets, now);
+        }
+
+        buckets
+            .entry(key.to_string())
+            .or_insert_with(|| TokenBucket::full(&self.config, now))
+            .take(&self.config, now)
+    }
+
+    /// Make room for new keys
+    fn evict(&self, buckets: &mut HashMap<String, TokenBucket>, now: Instant) {
+        // Dropping a full bucket is the same as keeping it
+        buckets.retain(|_, bucket| !bucket.is_full(&self.config, now));
+        if buckets.len() < MAX_BUCKETS {
+            return;
+        }
+
+        // Otherwise the keys idle the longest get a fresh bucket should they come back
+        let mut by_age = buckets
+            .iter()
+            .map(|(key, bucket)| (bucket.updated, key))
+            .collect::<Vec<_>>();
+        by_age.select_nth_unstable(EVICTED_AT_ONCE - 1);
+        let stalest = by_age[..EVICTED_AT_ONCE]
+            .iter()
+            .map(|(_, key)| key.to_string())
+            .collect::<Vec<_>>();
+        for key in stalest {
+            buckets.remove(&key);
+        }
+    }
+}
+
+/// Per-pubkey limit of the signed routes
+/// Only checked once the signature proved the caller holds the key, so nobody can drain the
+/// budget of another wallet by sending its pubkey
+pub struct PubkeyRateLimit {
+    limiter: RateLimiter,
+    clock: Arc<dyn Clock>,
+}
+
+impl PubkeyRateLimit {
+    pub fn new(config: &RateLimitConfig, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            limiter: RateLimiter::new(config.per_pubkey),
+            clock,
+        }
+    }
+
+    pub fn check(&self, pubkey: &str) -> Result<(), ApiError> {
+        self.limiter
+            .check(pubkey, self.clock.now())
+            .map_err(|retry_after| {
+                log::warn!("Rate limited pubkey {pubkey}");
+                ApiError::RateLimited(retry_after)
+            })
+    }
+}
+
+/// Middleware limiting requests per client IP
+#[derive(Clone)]
+pub struct RateLimit {
+    per_ip: Arc<RateLimiter>,
+    trust_forwarded_for: bool,
+    clock: Arc<dyn Clock>,
+}
+
+impl RateLimit {
+    /// The limits are shared by every clone, create it once and not per worker
+    pub fn new(config: &RateLimitConfig, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            per_ip: Arc::new(RateLimiter::new(config.per_ip)),
+            trust_forwarded_for: config.trust_forwarded_for,
+            clock,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            limits: self.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    limits: RateLimit,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let limits = self.limits.clone();
+
+        Box::pin(async move {
+            let ip = match limits.trust_forwarded_for {
+                true => req.connection_info().realip_remote_addr().map(String::from),
+                false => req.peer_addr().map(|addr| addr.ip().to_string()),
+            };
+            if let Some(ip) = ip {
+                if let Err(retry_after) = limits.per_ip.check(&ip, limits.clock.now()) {
+                    log::warn!("Rate limited IP {ip}");
+                    return Ok(req
+                        .into_response(ApiError::RateLimited(retry_after).error_response())
+                        .map_into_right_body());
+                }
+            }
+
+            service
+                .call(req)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_client_routes::MAX_BODY_SIZE;
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+    use actix_web::{web, App, HttpResponse};
+
+    /// Time only moves when a test advances it
+    struct ManualClock(Mutex<Instant>);
+
+    impl ManualClock {
+        fn new() -> Arc<Self> {
+            Arc::new(Self(Mutex::new(Instant::now())))
+        }
+
+        fn advance(&self, by: Duration) {
+            *self.0.lock().unwrap() += by;
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn bucket(capacity: u32, refill_per_sec: f64) -> BucketConfig {
+        BucketConfig {
+            capacity,
+            refill_per_sec,
+        }
+    }
+
+    #[test]
+    fn bucket_allows_a_burst_of_capacity() {
+        let clock = ManualClock::new();
+        let limiter = RateLimiter::new(bucket(3, 1.0));
+        for _ in 0..3 {
+            assert!(limiter.check("key", clock.now()).is_ok());
+        }
+        assert!(limiter.check("key", clock.now()).is_err());
+        // Other keys have their own bucket
+        assert!(limiter.check("other", clock.now()).is_ok());
+    }
+
+    #[test]
+    fn bucket_refills_over_time() {
+        let clock = ManualClock::new();
+        let limiter = RateLimiter::new(bucket(2, 4.0));
+        assert!(limiter.check("key", clock.now()).is_ok());
+        assert!(limiter.check("key", clock.now()).is_ok());
+        assert!(limiter.check("key", clock.now()).is_err());
+
+        clock.advance(Duration::from_millis(250));
+        assert!(limiter.check("key", clock.now()).is_ok());
+        assert!(limiter.check("key", clock.now()).is_err());
+
+        // Never beyond the capacity, however long the key was idle
+        clock.advance(Duration::from_secs(3600));
+        assert!(limiter.check("key", clock.now()).is_ok());
+        assert!(limiter.check("key", clock.now()).is_ok());
+        assert!(limiter.check("key", clock.now()).is_err());
+    }
+
+    #[test]
+    fn rejection_tells_when_to_retry() {
+        let clock = ManualClock::new();
+        let limiter = RateLimiter::new(bucket(1, 0.5));
+        assert!(limiter.check("key", clock.now()).is_ok());
+        assert_eq!(
+            limiter.check("key", clock.now()),
+            Err(Duration::from_secs(2))
+        );
+
+        clock.advance(Duration::from_millis(1500));
+        let retry_after = limiter.check("key", clock.now()).unwrap_err();
+        assert!(
+            (retry_after.as_secs_f64() - 0.5).abs() < 1e-6,
+            "{retry_after:?}"
+        );
+
+        clock.advance(retry_after);
+        assert!(limiter.check("key", clock.now()).is_ok());
+    }
+
+    #[test]
+    fn full_buckets_are_evicted_at_the_limit() {
+        let clock = ManualClock::new();
+        let limiter = RateLimiter::new(bucket(2, 1.0));
+        for idx in 0..MAX_BUCKETS - 1 {
+            limiter.check(&idx.to_string(), clock.now()).unwrap();
+        }
+        limiter.check("busy", clock.now()).unwrap();
+        limiter.check("busy", clock.now()).unwrap();
+        assert_eq!(limiter.buckets.lock().unwrap().len(), MAX_BUCKETS);
+
+        // Once refilled, dropping a bucket is the same as keeping it; the drained one stays
+        clock.advance(Duration::from_secs(1));
+        limiter.check("new", clock.now()).unwrap();
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.len(), 2);
+        assert!(buckets.contains_key("busy") && buckets.contains_key("new"));
+    }
+
+    #[test]
+    fn least_recently_used_buckets_are_evicted_when_none_is_full() {
+        let clock = ManualClock::new();
+        let limiter = RateLimiter::new(bucket(2, 1.0));
+        for idx in 0..MAX_BUCKETS {
+            clock.advance(Duration::from_micros(1));
+            limiter.check(&idx.to_string(), clock.now()).unwrap();
+        }
+
+        // Every bucket still misses a token, yet the limit holds
+        limiter.check("new", clock.now()).unwrap();
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.len(), MAX_BUCKETS - EVICTED_AT_ONCE + 1);
+        assert!(buckets.contains_key("new"));
+        assert!(!buckets.contains_key(&(EVICTED_AT_ONCE - 1).to_string()));
+        assert!(buckets.contains_key(&EVICTED_AT_ONCE.to_string()));
+        assert!(buckets.contains_key(&(MAX_BUCKETS - 1).to_string()));
+    }
+
+    async fn ok() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_web::test]
+    async fn middleware_rejects_with_retry_after() {
+        let clock = ManualClock::new();
+        let config = RateLimitConfig {
+            per_ip: bucket(1, 0.5),
+            per_pubkey: bucket(1, 0.25),
+            trust_forwarded_for: false,
+        };
+        let app = init_service(
+            App::new()
+                .wrap(RateLimit::new(&config, clock.clone()))
+                .route("/", web::post().to(ok)),
+        )
+        .await;
+        let request = |ip: &str, body: &str| {
+            TestRequest::post()
+                .uri("/")
+                .peer_addr(format!("{ip}:1234").parse().unwrap())
+                .set_payload(body.to_string())
+                .to_request()
+        };
+
+        let res = call_service(&app, request("10.0.0.1", "{}")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = call_service(&app, request("10.0.0.1", "{}")).await;
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "2");
+        let body: serde_json::Value = read_body_json(res).await;
+        assert_eq!(body["error"], "RateLimited");
+
+        clock.advance(Duration::from_secs(2));
+        let res = call_service(&app, request("10.0.0.1", "{}")).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // An unauthenticated pubkey in the body is left to the handlers
+        let body = r#"{"pubkey":"pubkey"}"#;
+        for ip in ["10.0.0.2", "10.0.0.3", "10.0.0.4"] {
+            let res = call_service(&app, request(ip, body)).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+    }
+
+    #[test]
+    fn pubkey_limit_reports_retry_after() {
+        let clock = ManualClock::new();
+        let limit = PubkeyRateLimit::new(&config(), clock.clone());
+        for _ in 0..100 {
+            assert!(limit.check("pubkey").is_ok());
+        }
+        let error = limit.check("pubkey").unwrap_err();
+        assert!(
+            matches!(error, ApiError::RateLimited(retry_after) if retry_after == Duration::from_secs(1))
+        );
+        assert!(limit.check("other").is_ok());
+
+        clock.advance(Duration::from_secs(1));
+        assert!(limit.check("pubkey").is_ok());
+    }
+
+    fn config() -> RateLimitConfig {
+        RateLimitConfig {
+            per_ip: BucketConfig {
+                capacity: 100,
+                refill_per_sec: 1.0,
+            },
+            per_pubkey: BucketConfig {
+                capacity: 100,
+                refill_per_sec: 1.0,
+            },
+            trust_forwarded_for: false,
+        }
+    }
+
+    /// A body as the game sends it: the pubkey up front, followed by a large replay
+    fn body(len: usize) -> String {
+        let head = r#"{"pubkey":"pubkey","replay":""#;
+        let tail = r#""}"#;
+        format!("{head}{}{tail}", "A".repeat(len - head.len() - tail.len()))
+    }
+
+    async fn echo_len(body: web::Json<serde_json::Value>) -> HttpResponse {
+        HttpResponse::Ok().body(
+            body["replay"]
+                .as_str()
+                .unwrap_or_default()
+                .len()
+                .to_string(),
+        )
+    }
+
+    #[actix_web::test]
+    async fn bodies_up_to_the_json_limit_pass() {
+        let limits = RateLimit::new(&config(), Arc::new(SystemClock));
+        let app = init_service(
+            App::new().service(
+                web::scope("/play")
+                    .app_data(web::JsonConfig::default().limit(MAX_BODY_SIZE))
+                    .wrap(limits)
+                    .route("/game_complete", web::post().to(echo_len)),
+            ),
+        )
+        .await;
+
+        // Larger than the default payload limit of 256 KiB
+        let req = TestRequest::post()
+            .uri("/play/game_complete")
+            .insert_header(("content-type", "application/json"))
+            .peer_addr("10.0.0.1:1234".parse().unwrap())
+            .set_payload(body(MAX_BODY_SIZE))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = TestRequest::post()
+            .uri("/play/game_complete")
+            .insert_header(("content-type", "application/json"))
+            .peer_addr("10.0.0.1:1234".parse().unwrap())
+            .set_payload(body(MAX_BODY_SIZE + 1))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,404 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use speedrun_survivors_model::{GameCompleteRequest, ReplayVerdict, RunOutcome, MAP_IDS};
+use speedrun_survivors_replay::Replay;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::error::ApiError;
+
+/// Exit code used by the simulator when the replay data can not be decoded
+const SIMULATOR_EXIT_UNDECODABLE: i32 = 2;
+
+/// How often a running simulator is checked against its deadline
+const SIMULATOR_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// The hero roster of the game, below `Config::simulator_assets`
+const HERO_ROSTER_PATH: &str = "assets/data/roster.heroes.ron";
+
+/// The verdict on a replay and the outcome the server recomputed from it, if it could be simulated
+pub struct ReplayCheck {
+    pub verdict: ReplayVerdict,
+    pub recomputed: Option<RunOutcome>,
+}
+
+impl ReplayCheck {
+    fn rejected(verdict: ReplayVerdict) -> Self {
+        Self {
+            verdict,
+            recomputed: None,
+        }
+    }
+}
+
+/// The heroes and maps the simulator can play a run with
+pub struct GameCatalog {
+    heroes: Vec<String>,
+}
+
+/// The part of the hero roster the server needs
+#[derive(Deserialize)]
+struct Roster {
+    heroes: Vec<RosterHero>,
+}
+
+#[derive(Deserialize)]
+struct RosterHero {
+    id: String,
+}
+
+impl GameCatalog {
+    /// Read the hero ids from the roster the simulator loads
+    pub fn load(config: &Config) -> Result<Self> {
+        let path = Path::new(&config.simulator_assets).join(HERO_ROSTER_PATH);
+        let data = std::fs::read(&path).with_context(|| {
+            format!(
+                "Failed to read the hero roster {}, check SIMULATOR_ASSETS",
+                path.display()
+            )
+        })?;
+        let roster: Roster = ron::de::from_bytes(&data)
+            .with_context(|| format!("Invalid hero roster {}", path.display()))?;
+        Ok(Self {
+            heroes: roster.heroes.into_iter().map(|hero| hero.id).collect(),
+        })
+    }
+
+    /// Reject a hero or map the simulator does not know, before it is stored or simulated
+    pub fn check(&self, hero: &str, map: &str) -> Result<(), ApiError> {
+        if !self.heroes.iter().any(|id| id == hero) {
+            return Err(ApiError::BadRequest(format!("Unknown hero {hero}")));
+        }
+        if !MAP_IDS.contains(&map) {
+            return Err(ApiError::BadRequest(format!("Unknown map {map}")));
+        }
+        Ok(())
+    }
+}
+
+/// Re-simulates replays with the headless simulator
+/// Each simulation runs with a deadline and only a limited number run at once
+pub struct ReplayVerifier {
+    simulator: String,
+    /// Passed as `BEVY_ASSET_ROOT`; the simulator would look next to its own executable otherwise
+    assets: String,
+    timeout: Duration,
+    max_running: usize,
+    running: Arc<AtomicUsize>,
+}
+
+/// Permission to run one simulation; frees its slot when dropped
+pub struct SimulatorSlot {
+    running: Arc<AtomicUsize>,
+}
+
+impl Drop for SimulatorSlot {
+    fn drop(&mut self) {
+        self.running.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl ReplayVerifier {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            simulator: config.simulator_path.clone(),
+            assets: config.simulator_assets.clone(),
+            timeout: Duration::from_secs(config.simulator_timeout),
+            max_running: config.simulator_concurrency,
+            running: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Reserve a slot for a simulation; `None` if the limit of simulations is running
+    pub fn try_slot(&self) -> Option<SimulatorSlot> {
+        self.running
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |running| {
+                (running < self.max_running).then_some(running + 1)
+            })
+            .ok()?;
+        Some(SimulatorSlot {
+            running: Arc::clone(&self.running),
+        })
+    }
+
+    /// Re-simulate a replay headlessly and compare the outcome against what the client claims
+    /// The session entropy seeds the RNG of the simulation and `nft_list` is the loadout locked at
+    /// `game_start`, so the same inputs must lead to the same outcome
+    /// Replays longer than `max_ticks` could not have been played in the time since the game started
+    pub fn verify(
+        &self,
+        _slot: SimulatorSlot,
+        request: &GameCompleteRequest,
+        nft_list: &[String],
+        max_ticks: u64,
+    ) -> Result<ReplayCheck> {
+        let Ok(replay) = Replay::from_base64(&request.replay) else {
+            return Ok(ReplayCheck::rejected(ReplayVerdict::Undecodable));
+        };
+        if u64::from(replay.end_tick) > max_ticks {
+            log::warn!(
+                "Replay for {} ends at tick {}, only {max_ticks} ticks passed since the game started",
+                request.entropy,
+                replay.end_tick
+            );
+            return Ok(ReplayCheck::rejected(ReplayVerdict::Implausible));
+        }
+
+        // Values are attached with `=`, so none of them can be taken for an option of its own
+        let mut child = Command::new(&self.simulator)
+            .args([
+                format!("--seed={}", request.entropy),
+                format!("--hero={}", request.hero),
+                format!("--map={}", request.map),
+                format!("--nfts={}", nft_list.join(",")),
+                "--replay=-".to_string(),
+            ])
+            .env("BEVY_ASSET_ROOT", &self.assets)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to start replay simulator {}", self.simulator))?;
+
+        // Feed the replay through stdin and drain the output on separate threads, so neither side
+        // blocks on a full pipe while the deadline is watched
+        let input = replay.encode()?;
+        let mut stdin = child
+            .stdin
+            .take()
+            .context("Replay simulator stdin unavailable")?;
+        // A simulator rejecting the replay may exit before reading all of it; its exit code tells
+        let writer = thread::spawn(move || stdin.write_all(&input));
+        let stdout = read_to_end(child.stdout.take())?;
+        let stderr = read_to_end(child.stderr.take())?;
+
+        let status = self.wait(&mut child)?;
+        let _ = writer.join();
+        let stdout = stdout.join().unwrap_or_default();
+        let stderr = stderr.join().unwrap_or_default();
+
+        if !status.success() {
+            // The simulator rejects replays it can not parse; anything else is a server side problem
+            if status.code() == Some(SIMULATOR_EXIT_UNDECODABLE) {
+                return Ok(ReplayCheck::rejected(ReplayVerdict::Undecodable));
+            }
+            bail!(
+                "Replay simulator failed with {status}: {}",
+                String::from_utf8_lossy(&stderr)
+            );
+        }
+
+        let recomputed: RunOutcome = serde_json::from_slice(&stdout)
+            .context("Replay simulator returned an invalid run summary")?;
+
+        let verdict = match recomputed == request.outcome {
+            true => ReplayVerdict::Valid,
+            false => {
+                log::warn!(
+                    "Replay mismatch for {}: claimed {:?}, recomputed {recomputed:?}",
+                    request.entropy,
+                    request.outcome
+                );
+                ReplayVerdict::Mismatch
+            }
+        };
+
+        Ok(ReplayCheck {
+            verdict,
+            recomputed: Some(recomputed),
+        })
+    }
+
+    /// Wait for the simulator to exit; kill it once the timeout is exceeded
+    fn wait(&self, child: &mut Child) -> Result<ExitStatus> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok(status);
+            }
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                bail!("Replay simulator timed out after {:?}", self.timeout);
+            }
+            thread::sleep(SIMULATOR_POLL_INTERVAL);
+        }
+    }
+}
+
+fn read_to_end(pipe: Option<impl Read + Send + 'static>) -> Result<thread::JoinHandle<Vec<u8>>> {
+    let mut pipe = pipe.context("Replay simulator output unavailable")?;
+    Ok(thread::spawn(move || {
+        let mut data = Vec::new();
+        let _ = pipe.read_to_end(&mut data);
+        data
+    }))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use speedrun_survivors_replay::{ReplayAction, ReplayInput};
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+    use std::sync::OnceLock;
+
+    const OUTCOME: &str = r#"{"ticks":120,"kills":3,"coins":40,"level":2}"#;
+
+    /// Stand-in simulators; all written before the first one runs, a file still open for writing
+    /// in one test can not be executed by another
+    struct Simulators {
+        /// Prints `OUTCOME` if it got the expected loadout and assets, fails otherwise
+        echo: PathBuf,
+        hang: PathBuf,
+        undecodable: PathBuf,
+    }
+
+    fn simulators() -> &'static Simulators {
+        static SIMULATORS: OnceLock<Simulators> = OnceLock::new();
+        SIMULATORS.get_or_init(|| {
+            let dir = std::env::temp_dir().join(format!("replay-verifier-{}", std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let script = |name: &str, body: &str| {
+                let path = dir.join(name);
+                std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+                std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+                path
+            };
+            Simulators {
+                echo: script(
+                    "echo",
+                    &format!(
+                        "cat > /dev/null\n[ \"$BEVY_ASSET_ROOT\" = /game ] || exit 1\ncase \"$*\" in *\"--nfts=a,b \"*) echo '{OUTCOME}' ;; *) exit 1 ;; esac"
+                    ),
+                ),
+                hang: script("hang", "exec sleep 30"),
+                undecodable: script("undecodable", "exit 2"),
+            }
+        })
+    }
+
+    fn verifier(simulator: &std::path::Path, concurrency: usize) -> ReplayVerifier {
+        ReplayVerifier::new(&Config {
+            simulator_path: simulator.to_string_lossy().into_owned(),
+            simulator_assets: "/game".to_string(),
+            simulator_timeout: 1,
+            simulator_concurrency: concurrency,
+            ..Default::default()
+        })
+    }
+
+    fn request(end_tick: u32) -> GameCompleteRequest {
+        let mut replay = Replay {
+            end_tick,
+            ..Default::default()
+        };
+        replay.push(10, ReplayInput::Press(ReplayAction::MoveUp));
+        GameCompleteRequest {
+            pubkey: "pubkey".to_string(),
+            entropy: "entropy".to_string(),
+            nft_list: None,
+            hero: "hero".to_string(),
+            map: "map".to_string(),
+            replay: replay.to_base64().unwrap(),
+            outcome: serde_json::from_str(OUTCOME).unwrap(),
+            signature: String::new(),
+        }
+    }
+
+    fn nfts() -> Vec<String> {
+        vec!["a".to_string(), "b".to_string()]
+    }
+
+    #[test]
+    fn valid_replay_is_simulated_with_the_loadout_and_assets() {
+        let verifier = verifier(&simulators().echo, 1);
+        let check = verifier
+            .verify(verifier.try_slot().unwrap(), &request(120), &nfts(), 600)
+            .unwrap();
+        assert_eq!(check.verdict, ReplayVerdict::Valid);
+
+        let mut claimed = request(120);
+        claimed.outcome.kills += 1;
+        let check = verifier
+            .verify(verifier.try_slot().unwrap(), &claimed, &nfts(), 600)
+            .unwrap();
+        assert_eq!(check.verdict, ReplayVerdict::Mismatch);
+
+        // A different loadout makes the stand-in fail, as a real simulator would diverge
+        assert!(verifier
+            .verify(verifier.try_slot().unwrap(), &request(120), &[], 600)
+            .is_err());
+    }
+
+    #[test]
+    fn catalog_knows_the_shipped_heroes_and_maps() {
+        let catalog = GameCatalog::load(&Config {
+            simulator_assets: concat!(env!("CARGO_MANIFEST_DIR"), "/../speedrun-survivors-bevy")
+                .to_string(),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(catalog.check("Pepe", "Map1").is_ok());
+        assert!(catalog.check("BonkInu", "Map4").is_ok());
+        assert!(catalog.check("Nobody", "Map1").is_err());
+        assert!(catalog.check("Pepe", "--nfts").is_err());
+
+        assert!(GameCatalog::load(&Config::default()).is_err());
+    }
+
+    #[test]
+    fn replay_longer_than_the_game_is_implausible() {
+        // Never started, the simulator path does not exist
+        let verifier = verifier(std::path::Path::new("/nonexistent"), 1);
+        let check = verifier
+            .verify(verifier.try_slot().unwrap(), &request(601), &nfts(), 600)
+            .unwrap();
+        assert_eq!(check.verdict, ReplayVerdict::Implausible);
+    }
+
+    #[test]
+    fn undecodable_replays_are_rejected() {
+        let verifier = verifier(&simulators().undecodable, 1);
+        let mut garbage = request(120);
+        garbage.replay = "not a replay".to_string();
+        let check = verifier
+            .verify(verifier.try_slot().unwrap(), &garbage, &nfts(), 600)
+            .unwrap();
+        assert_eq!(check.verdict, ReplayVerdict::Undecodable);
+
+        let check = verifier
+            .verify(verifier.try_slot().unwrap(), &request(120), &nfts(), 600)
+            .unwrap();
+        assert_eq!(check.verdict, ReplayVerdict::Undecodable);
+    }
+
+    #[test]
+    fn hanging_simulator_is_killed() {
+        let verifier = verifier(&simulators().hang, 1);
+        let started = Instant::now();
+        let result = verifier.verify(verifier.try_slot().unwrap(), &request(120), &nfts(), 600);
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn slots_are_limited() {
+        let verifier = verifier(&simulators().echo, 2);
+        let first = verifier.try_slot().unwrap();
+        let second = verifier.try_slot().unwrap();
+        assert!(verifier.try_slot().is_none());
+
+        drop(first);
+        let third = verifier.try_slot().unwrap();
+        assert!(verifier.try_slot().is_none());
+        drop((second, third));
+        assert_eq!(verifier.running.load(Ordering::Acquire), 0);
+    }
+}
@@ -0,0 +1,268 @@
+use anyhow::{anyhow, Result};
+use speedrun_survivors_model::{
+    LeaderboardEntry, LeaderboardFilter, LeaderboardKind, LedgerTransaction, ReplayVerdict,
+};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use super::{AbandonedRun, CompletedRun, Session, SessionCounts, Storage};
+use crate::config::SessionTimeouts;
+
+/// Non persistent storage, all data is lost when the server stops
+#[derive(Default)]
+pub struct MemoryStorage {
+    data: RwLock<MemoryData>,
+}
+
+#[derive(Default)]
+struct MemoryData {
+    sessions: HashMap<String, Session>,
+    runs: Vec<CompletedRun>,
+    replays: HashMap<String, String>,
+    abandoned_runs: Vec<AbandonedRun>,
+    /// Every entropy ever issued and whether it was completed
+    entropies: HashMap<String, bool>,
+    /// Ledger transactions in insertion order, with the pubkey they belong to
+    ledger: Vec<(String, LedgerTransaction)>,
+}
+
+impl MemoryData {
+    /// Returns false if a transaction of the same kind and reference exists already
+    fn ledger_append(&mut self, pubkey: &str, transaction: &LedgerTransaction) -> bool {
+        if self.ledger.iter().any(|(_, existing)| {
+            existing.kind == transaction.kind && existing.reference == transaction.reference
+        }) {
+            return false;
+        }
+        self.ledger.push((pubkey.to_string(), transaction.clone()));
+        true
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn session_get(&self, pubkey: &str) -> Result<Option<Session>> {
+        let data = self
+            .data
+            .read()
+            .map_err(|_| anyhow!("Storage lock poisoned"))?;
+        Ok(data.sessions.get(pubkey).cloned())
+    }
+
+    fn session_put(&self, pubkey: &str, session: &Session) -> Result<()> {
+        let mut data = self
+            .data
+            .write()
+            .map_err(|_| anyhow!("Storage lock poisoned"))?;
+        data.sessions.insert(pubkey.to_string(), session.clone());
+        Ok(())
+    }
+
+    fn session_delete(&self, pubkey: &str) -> Result<()> {
+        let mut data = self
+            .data
+            .write()
+            .map_err(|_| anyhow!("Storage lock poisoned"))?;
+        data.sessions.remove(pubkey);
+        Ok(())
+    }
+
+    fn sessions_expire(
+        &self,
+        now: u64,
+        timeouts: &SessionTimeouts,
+    ) -> Result<(usize, Vec<AbandonedRun>)> {
+        let mut data = self
+            .data
+            .write()
+            .map_err(|_| anyhow!("Storage lock poisoned"))?;
+
+        let mut removed = 0;
+        let mut abandoned = Vec::new();
+        data.sessions.retain(|pubkey, session| {
+            if !session.is_expired(now, timeouts) {
+                return true;
+            }
+            removed += 1;
+            abandoned.extend(session.abandoned_run(pubkey, now));
+            false
+        });
+        data.abandoned_runs.extend(abandoned.iter().cloned());
+
+        Ok((removed, abandoned))
+    }
+
+    fn session_counts(&self, now: u64, timeouts: &SessionTimeouts) -> Result<SessionCounts> {
+        let data = self
+            .data
+            .read()
+            .map_err(|_| anyhow!("Storage lock poisoned"))?;
+        let mut counts = SessionCounts {
+            abandoned: data.abandoned_runs.len() as u64,
+            ..Default::default()
+        };
+        for session in data.sessions.values() {
+            counts.count(session, now, timeouts);
+        }
+        Ok(counts)
+    }
+
+    fn entropy_register(&self, entropy: &str, _pubkey: &str, _now: u64) -> Result<bool> {
+        let mut data = self
+            .data
+            .write()
+            .map_err(|_| anyhow!("Storage lock poisoned"))?;
+        if data.entropies.contains_key(entropy) {
+            return Ok(false);
+        }
+        data.entropies.insert(entropy.to_string(), false);
+        Ok(true)
+    }
+
+    fn entropy_completed(&self, entropy: &str) -> Result<bool> {
+        let data = self
+            .data
+            .read()
+            .map_err(|_| anyhow!("Storage lock poisoned"))?;
+        Ok(data.entropies.get(entropy).copied().unwrap_or(false))
+    }
+
+    fn run_complete(
+        &self,
+        run: &CompletedRun,
+        replay: &str,
+        reward: Option<&LedgerTransaction>,
+    ) -> Result<bool> {
+        let mut data = self
+            .data
+            .write()
+            .map_err(|_| anyhow!("Storage lock poisoned"))?;
+        match data.entropies.get_mut(&run.entropy) {
+            Some(completed) if !*completed => *completed = true,
+            _ => return Ok(false),
+        }
+        data.replays.insert(run.entropy.clone(), replay.to_string());
+        data.runs.push(run.clone());
+        if let Some(reward) = reward {
+            data.ledger_append(&run.pubkey, reward);
+        }
+        Ok(true)
+    }
+
+    fn leaderboard(
+        &self,
+        filter: &LeaderboardFilter,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<LeaderboardEntry>> {
+        let data = self
+            .data
+            .read()
+            .map_err(|_| anyhow!("Storage lock poisoned"))?;
+        Ok(ranked_runs(&data.runs, filter)
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect())
+    }
+
+    fn leaderboard_rank(
+        &self,
+        filter: &LeaderboardFilter,
+        pubkey: &str,
+    ) -> Result<Option<LeaderboardEntry>> {
+        let data = self
+            .data
+            .read()
+            .map_err(|_| anyhow!("Storage lock poisoned"))?;
+        Ok(ranked_runs(&data.runs, filter)
+            .into_iter()
+            .find(|entry| entry.pubkey == pubkey))
+    }
+
+    fn ledger_balance(&self, pubkey: &str) -> Result<i64> {
+        let data = self
+            .data
+            .read()
+            .map_err(|_| anyhow!("Storage lock poisoned"))?;
+        Ok(data
+            .ledger
+            .iter()
+            .filter(|(owner, _)| owner == pubkey)
+            .map(|(_, transaction)| transaction.amount)
+            .sum())
+    }
+
+    fn ledger_history(
+        &self,
+        pubkey: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<LedgerTransaction>> {
+        let data = self
+            .data
+            .read()
+            .map_err(|_| anyhow!("Storage lock poisoned"))?;
+        Ok(data
+            .ledger
+            .iter()
+            .rev()
+            .filter(|(owner, _)| owner == pubkey)
+            .skip(offset as usize)
+            .take(limit as usize)
+            .map(|(_, transaction)| transaction.clone())
+            .collect())
+    }
+}
+
+/// Same ranking as the SQLite leaderboard query: best valid run per player, ties go to the earlier run
+fn ranked_runs(runs: &[CompletedRun], filter: &LeaderboardFilter) -> Vec<LeaderboardEntry> {
+    let matches = |value: &String, wanted: &Option<String>| {
+        wanted.as_ref().is_none_or(|wanted| wanted == value)
+    };
+    let value = |run: &CompletedRun| match filter.board {
+        LeaderboardKind::Time => run.outcome.ticks as u64,
+        LeaderboardKind::Kills => run.outcome.kills as u64,
+        LeaderboardKind::Score => run.outcome.score(),
+    };
+
+    let mut best: HashMap<&str, &CompletedRun> = HashMap::new();
+    for run in runs.iter().filter(|run| {
+        run.verdict == ReplayVerdict::Valid
+            && matches(&run.map, &filter.map)
+            && matches(&run.hero, &filter.hero)
+            && matches(&run.season, &filter.season)
+    }) {
+        let better = best.get(run.pubkey.as_str()).is_none_or(|current| {
+            (value(run), std::cmp::Reverse(run.completed_at))
+                > (value(current), std::cmp::Reverse(current.completed_at))
+        });
+        if better {
+            best.insert(&run.pubkey, run);
+        }
+    }
+
+    let mut best: Vec<&CompletedRun> = best.into_values().collect();
+    best.sort_by(|a, b| {
+        value(b)
+            .cmp(&value(a))
+            .then(a.completed_at.cmp(&b.completed_at))
+            .then(a.pubkey.cmp(&b.pubkey))
+    });
+
+    best.into_iter()
+        .enumerate()
+        .map(|(idx, run)| LeaderboardEntry {
+            rank: idx as u64 + 1,
+            pubkey: run.pubkey.clone(),
+            hero: run.hero.clone(),
+            map: run.map.clone(),
+            season: run.season.clone(),
+            ticks: run.outcome.ticks,
+            kills: run.outcome.kills,
+            coins: run.outcome.coins,
+            level: run.outcome.level,
+            score: run.outcome.score(),
+            completed_at: run.completed_at,
+        })
+        .collect()
+}
@@ -0,0 +1,707 @@
+use actix_web::web::{self, Data};
+use anyhow::{bail, Error, Result};
+use serde::Serialize;
+use speedrun_survivors_model::{
+    LeaderboardEntry, LeaderboardFilter, LedgerTransaction, ReplayVerdict, RunOutcome,
+};
+use std::str::FromStr;
+
+use crate::config::SessionTimeouts;
+
+mod memory;
+mod sqlite;
+
+pub use memory::MemoryStorage;
+pub use sqlite::SqliteStorage;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SessionStatus {
+    AwaitingSignature,
+    GameStarted,
+}
+
+impl SessionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionStatus::AwaitingSignature => "AwaitingSignature",
+            SessionStatus::GameStarted => "GameStarted",
+        }
+    }
+}
+
+impl FromStr for SessionStatus {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "AwaitingSignature" => Ok(SessionStatus::AwaitingSignature),
+            "GameStarted" => Ok(SessionStatus::GameStarted),
+            _ => bail!("Unknown session status: {value}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Session {
+    pub entropy: String,
+    pub state: SessionStatus,
+    pub unixtime: u64,
+    /// The NFTs equipped for this run; ownership was verified when the game started
+    pub nft_list: Vec<String>,
+    /// Hero and map of the run, locked with the NFTs; empty until the game started
+    pub hero: String,
+    pub map: String,
+}
+
+impl Session {
+    /// The run this session was playing, if it expired during the game
+    fn abandoned_run(&self, pubkey: &str, now: u64) -> Option<AbandonedRun> {
+        (self.state == SessionStatus::GameStarted).then(|| AbandonedRun {
+            pubkey: pubkey.to_string(),
+            entropy: self.entropy.clone(),
+            nft_list: self.nft_list.clone(),
+            started_at: self.unixtime,
+            expired_at: now,
+        })
+    }
+
+    /// Whether the sweeper may evict the session; it lasts as long as `game_start` / `game_complete` accept it
+    pub fn is_expired(&self, now: u64, timeouts: &SessionTimeouts) -> bool {
+        (self.state == SessionStatus::AwaitingSignature && self.unixtime + timeouts.start < now)
+            || self.unixtime + timeouts.game < now
+    }
+
+    /// Whether `session_init` may overwrite the session with a new one
+    pub fn is_replaceable(&self, now: u64, timeouts: &SessionTimeouts) -> bool {
+        // In case of AwaitingSignature we must wait some time for the client to sign, otherwise there might be a DoS opportunity
+        // Waiting until the session expires would lock the wallet out for long after an abandoned `session_init`
+        (self.state == SessionStatus::AwaitingSignature && self.unixtime + timeouts.signature < now)
+            || self.is_expired(now, timeouts)
+    }
+}
+
+/// A session that expired while its game was running; kept for analytics
+#[derive(Clone, Debug)]
+pub struct AbandonedRun {
+    pub pubkey: String,
+    pub entropy: String,
+    pub nft_list: Vec<String>,
+    pub started_at: u64,
+    pub expired_at: u64,
+}
+
+/// Sessions by state; expired sessions are counted until the sweeper removes them
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct SessionCounts {
+    pub active: u64,
+    #[serde(rename = "awaitingSignature")]
+    pub awaiting_signature: u64,
+    pub expired: u64,
+    /// Abandoned runs recorded so far
+    pub abandoned: u64,
+}
+
+impl SessionCounts {
+    fn count(&mut self, session: &Session, now: u64, timeouts: &SessionTimeouts) {
+        match (session.is_expired(now, timeouts), session.state) {
+            (true, _) => self.expired += 1,
+            (false, SessionStatus::AwaitingSignature) => self.awaiting_signature += 1,
+            (false, SessionStatus::GameStarted) => self.active += 1,
+        }
+    }
+}
+
+/// A game that was completed and submitted to the server; only runs with a valid replay count
+#[derive(Clone, Debug)]
+pub struct CompletedRun {
+    pub pubkey: String,
+    pub entropy: String,
+    pub nft_list: Vec<String>,
+    pub hero: String,
+    pub map: String,
+    /// The season that was running when the game was completed
+    pub season: String,
+    pub outcome: RunOutcome,
+    pub verdict: ReplayVerdict,
+    pub started_at: u64,
+    pub completed_at: u64,
+}
+
+/// Everything the API needs to persist; implemented by the SQLite database and an in-memory backend for tests
+pub trait Storage: Send + Sync {
+    fn session_get(&self, pubkey: &str) -> Result<Option<Session>>;
+    fn session_put(&self, pubkey: &str, session: &Session) -> Result<()>;
+    fn session_delete(&self, pubkey: &str) -> Result<()>;
+    /// Remove all expired sessions; the ones that expired during a game are recorded as abandoned runs
+    /// Returns the number of removed sessions and the abandoned runs among them
+    fn sessions_expire(
+        &self,
+        now: u64,
+        timeouts: &SessionTimeouts,
+    ) -> Result<(usize, Vec<AbandonedRun>)>;
+    fn session_counts(&self, now: u64, timeouts: &SessionTimeouts) -> Result<SessionCounts>;
+
+    /// Add newly issued session entropy to the registry of every entropy ever issued
+    /// Returns false if the entropy was issued before and must not be used
+    fn entropy_register(&self, entropy: &str, pubkey: &str, now: u64) -> Result<bool>;
+    /// Whether a game with this entropy was completed
+    fn entropy_completed(&self, entropy: &str) -> Result<bool>;
+
+    /// Record a completed run together with the replay that was submitted for it and credit its reward
+    /// Marks the entropy as completed in the same transaction; returns false without recording anything
+    /// if the entropy is unknown or was completed before
+    fn run_complete(
+        &self,
+        run: &CompletedRun,
+        replay: &str,
+        reward: Option<&LedgerTransaction>,
+    ) -> Result<bool>;
+
+    /// Ranked best runs per player, only counting runs with a valid replay
+    fn leaderboard(
+        &self,
+        filter: &LeaderboardFilter,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<LeaderboardEntry>>;
+    /// The board entry of a single player, if they have a valid run on that board
+    fn leaderboard_rank(
+        &self,
+        filter: &LeaderboardFilter,
+        pubkey: &str,
+    ) -> Result<Option<LeaderboardEntry>>;
+
+    fn ledger_balance(&self, pubkey: &str) -> Result<i64>;
+    /// Transactions of `pubkey`, newest first
+    fn ledger_history(
+        &self,
+        pubkey: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<LedgerTransaction>>;
+}
+
+/// Run `f` on the blocking thread pool; storage calls may wait on the disk and must not stall the
+/// actix worker with every other request it serves
+pub async fn blocking<T, F>(storage: &Data<dyn Storage>, f: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn Storage) -> Result<T> + Send + 'static,
+{
+    let storage = Data::clone(storage);
+    web::block(move || f(storage.as_ref())).await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use speedrun_survivors_model::{LeaderboardKind, LedgerKind};
+
+    fn backends() -> Vec<Box<dyn Storage>> {
+        vec![
+            Box::new(MemoryStorage::default()),
+            Box::new(SqliteStorage::open(":memory:").unwrap()),
+        ]
+    }
+
+    fn run(entropy: &str) -> CompletedRun {
+        CompletedRun {
+            pubkey: "pubkey".to_string(),
+            entropy: entropy.to_string(),
+            nft_list: Vec::new(),
+            hero: "hero".to_string(),
+            map: "map".to_string(),
+            season: "1".to_string(),
+            outcome: RunOutcome {
+                ticks: 600,
+                kills: 3,
+                coins: 40,
+                level: 2,
+            },
+            verdict: ReplayVerdict::Valid,
+            started_at: 10,
+            completed_at: 20,
+        }
+    }
+
+    fn reward(entropy: &str) -> LedgerTransaction {
+        LedgerTransaction {
+            kind: LedgerKind::RunReward,
+            amount: 40,
+            reference: entropy.to_string(),
+            unixtime: 20,
+        }
+    }
+
+    fn session(state: SessionStatus, unixtime: u64) -> Session {
+        Session {
+            entropy: "entropy".to_string(),
+            state,
+            unixtime,
+            nft_list: Vec::new(),
+            hero: String::new(),
+            map: String::new(),
+        }
+    }
+
+    #[test]
+    fn started_session_keeps_its_loadout() {
+        for storage in backends() {
+            let started = Session {
+                nft_list: vec!["a".to_string(), "b".to_string()],
+                hero: "Pepe".to_string(),
+                map: "Map1".to_string(),
+                ..session(SessionStatus::GameStarted, 1000)
+            };
+            storage.session_put("pubkey", &started).unwrap();
+
+            let stored = storage.session_get("pubkey").unwrap().unwrap();
+            assert_eq!(stored.state, SessionStatus::GameStarted);
+            assert_eq!(stored.nft_list, started.nft_list);
+            assert_eq!((stored.hero, stored.map), (started.hero, started.map));
+        }
+    }
+
+    #[test]
+    fn sessions_expire_with_their_phase() {
+        let timeouts = SessionTimeouts::default();
+        let awaiting = session(SessionStatus::AwaitingSignature, 1000);
+        assert!(!awaiting.is_expired(1000 + timeouts.start, &timeouts));
+        assert!(awaiting.is_expired(1001 + timeouts.start, &timeouts));
+
+        let started = session(SessionStatus::GameStarted, 1000);
+        assert!(!started.is_expired(1000 + timeouts.game, &timeouts));
+        assert!(started.is_expired(1001 + timeouts.game, &timeouts));
+    }
+
+    #[test]
+    fn unsigned_sessions_are_replaceable_before_they_expire() {
+        let timeouts = SessionTimeouts::default();
+        let awaiting = session(SessionStatus::AwaitingSignature, 1000);
+        assert!(!awaiting.is_replaceable(1000 + timeouts.signature, &timeouts));
+        assert!(awaiting.is_replaceable(1001 + timeouts.signature, &timeouts));
+        assert!(!awaiting.is_expired(1001 + timeouts.signature, &timeouts));
+
+        let started = session(SessionStatus::GameStarted, 1000);
+        assert!(!started.is_replaceable(1001 + timeouts.start, &timeouts));
+        assert!(started.is_replaceable(1001 + timeouts.game, &timeouts));
+    }
+
+    #[test]
+    fn sweep_keeps_sessions_game_start_accepts() {
+        let timeouts = SessionTimeouts::default();
+        for storage in backends() {
+            let now = 10_000;
+            storage
+                .session_put(
+                    "waiting",
+                    &session(SessionStatus::AwaitingSignature, now - timeouts.start),
+                )
+                .unwrap();
+            storage
+                .session_put(
+                    "stale",
+                    &session(SessionStatus::AwaitingSignature, now - timeouts.start - 1),
+                )
+                .unwrap();
+            storage
+                .session_put(
+                    "playing",
+                    &session(SessionStatus::GameStarted, now - timeouts.start - 1),
+                )
+                .unwrap();
+
+            let (removed, abandoned) = storage.sessions_expire(now, &timeouts).unwrap();
+            assert_eq!(removed, 1);
+            assert!(abandoned.is_empty());
+            assert!(storage.session_get("waiting").unwrap().is_some());
+            assert!(storage.session_get("stale").unwrap().is_none());
+            assert!(storage.session_get("playing").unwrap().is_some());
+        }
+    }
+
+    #[test]
+    fn expired_sessions_are_counted_and_swept() {
+        let timeouts = SessionTimeouts::default();
+        let now = 10_000;
+        for storage in backends() {
+            let sessions = [
+                ("waiting", SessionStatus::AwaitingSignature, now),
+                (
+                    "stale",
+                    SessionStatus::AwaitingSignature,
+                    now - timeouts.start - 1,
+                ),
+                (
+                    "playing",
+                    SessionStatus::GameStarted,
+                    now - timeouts.start - 1,
+                ),
+                ("left", SessionStatus::GameStarted, now - timeouts.game - 1),
+                ("completed", SessionStatus::AwaitingSignature, 0),
+            ];
+            for (pubkey, state, unixtime) in sessions {
+                storage
+                    .session_put(pubkey, &session(state, unixtime))
+                    .unwrap();
+            }
+
+            let counts = storage.session_counts(now, &timeouts).unwrap();
+            assert_eq!(
+                (counts.awaiting_signature, counts.active, counts.expired),
+                (1, 1, 3)
+            );
+
+            let (removed, abandoned) = storage.sessions_expire(now, &timeouts).unwrap();
+            assert_eq!(removed, 3);
+            assert_eq!(abandoned.len(), 1);
+            assert_eq!(abandoned[0].pubkey, "left");
+            assert_eq!(abandoned[0].expired_at, now);
+
+            let counts = storage.session_counts(now, &timeouts).unwrap();
+            assert_eq!(
+                (
+                    counts.awaiting_signature,
+                    counts.active,
+                    counts.expired,
+                    counts.abandoned
+                ),
+                (1, 1, 0, 1)
+            );
+        }
+    }
+
+    #[test]
+    fn runs_complete_once_per_entropy() {
+        for storage in backends() {
+            assert!(storage.entropy_register("entropy", "pubkey", 10).unwrap());
+            assert!(!storage.entropy_completed("entropy").unwrap());
+
+            let reward = reward("entropy");
+            assert!(storage
+                .run_complete(&run("entropy"), "replay", Some(&reward))
+                .unwrap());
+            assert!(storage.entropy_completed("entropy").unwrap());
+            assert_eq!(storage.ledger_balance("pubkey").unwrap(), 40);
+
+            assert!(!storage
+                .run_complete(&run("entropy"), "replay", Some(&reward))
+                .unwrap());
+            assert_eq!(storage.ledger_balance("pubkey").unwrap(), 40);
+            assert_eq!(storage.ledger_history("pubkey", 0, 10).unwrap().len(), 1);
+        }
+    }
+
+    #[test]
+    fn unknown_entropy_records_nothing() {
+        for storage in backends() {
+            assert!(!storage
+                .run_complete(&run("unknown"), "replay", Some(&reward("unknown")))
+                .unwrap());
+            assert!(!storage.entropy_completed("unknown").unwrap());
+            assert_eq!(storage.ledger_balance("pubkey").unwrap(), 0);
+            assert!(storage
+                .leaderboard(&LeaderboardFilter::default(), 0, 10)
+                .unwrap()
+                .is_empty());
+        }
+    }
+
+    #[test]
+    fn rejected_runs_are_recorded_without_reward() {
+        for storage in backends() {
+            storage.entropy_register("entropy", "pubkey", 10).unwrap();
+            let mut rejected = run("entropy");
+            rejected.verdict = ReplayVerdict::Mismatch;
+            assert!(storage.run_complete(&rejected, "replay", None).unwrap());
+            assert!(storage.entropy_completed("entropy").unwrap());
+            assert_eq!(storage.ledger_balance("pubkey").unwrap(), 0);
+        }
+    }
+
+    /// A valid run of `pubkey` on the default board; `entropy` must be unique per test
+    fn ranked_run(pubkey: &str, entropy: &str, outcome: (u32, u32, u64), at: u64) -> CompletedRun {
+        let (ticks, kills, coins) = outcome;
+        CompletedRun {
+            pubkey: pubkey.to_string(),
+            outcome: RunOutcome {
+                ticks,
+                kills,
+                coins,
+                level: 1,
+            },
+            completed_at: at,
+            ..run(entropy)
+        }
+    }
+
+    fn record(storage: &dyn Storage, run: &CompletedRun) {
+        assert!(storage
+            .entropy_register(&run.entropy, &run.pubkey, run.started_at)
+            .unwrap());
+        assert!(storage.run_complete(run, "replay", None).unwrap());
+    }
+
+    /// Rank and pubkey of each entry on a page of the board
+    fn board(
+        storage: &dyn Storage,
+        filter: &LeaderboardFilter,
+        offset: u32,
+        limit: u32,
+    ) -> Vec<(u64, String)> {
+        storage
+            .leaderboard(filter, offset, limit)
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.rank, entry.pubkey))
+            .collect()
+    }
+
+    fn ranks(expected: &[(u64, &str)]) -> Vec<(u64, String)> {
+        expected
+            .iter()
+            .map(|(rank, pubkey)| (*rank, pubkey.to_string()))
+            .collect()
+    }
+
+    fn filter(board: LeaderboardKind) -> LeaderboardFilter {
+        LeaderboardFilter {
+            board,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn each_board_ranks_by_its_key() {
+        for storage in backends() {
+            // Scores: a 110, b 190, c 620
+            record(storage.as_ref(), &ranked_run("a", "a1", (600, 1, 0), 20));
+            record(storage.as_ref(), &ranked_run("b", "b1", (300, 9, 0), 20));
+            record(storage.as_ref(), &ranked_run("c", "c1", (100, 2, 500), 20));
+
+            let expected = [
+                (LeaderboardKind::Time, ["a", "b", "c"]),
+                (LeaderboardKind::Kills, ["b", "c", "a"]),
+                (LeaderboardKind::Score, ["c", "b", "a"]),
+            ];
+            for (kind, order) in expected {
+                let entries = storage.leaderboard(&filter(kind), 0, 10).unwrap();
+                let pubkeys: Vec<&str> =
+                    entries.iter().map(|entry| entry.pubkey.as_str()).collect();
+                assert_eq!(pubkeys, order, "{kind:?}");
+                let ranks: Vec<u64> = entries.iter().map(|entry| entry.rank).collect();
+                assert_eq!(ranks, [1, 2, 3], "{kind:?}");
+            }
+
+            let entry = &storage
+                .leaderboard(&filter(LeaderboardKind::Score), 0, 1)
+                .unwrap()[0];
+            assert_eq!(
+                (
+                    entry.ticks,
+                    entry.kills,
+                    entry.coins,
+                    entry.level,
+                    entry.score
+                ),
+                (100, 2, 500, 1, 620)
+            );
+        }
+    }
+
+    #[test]
+    fn ties_go_to_the_earlier_run() {
+        for storage in backends() {
+            record(
+                storage.as_ref(),
+                &ranked_run("late", "late1", (600, 1, 0), 30),
+            );
+            record(
+                storage.as_ref(),
+                &ranked_run("early", "early1", (600, 1, 0), 20),
+            );
+            assert_eq!(
+                board(storage.as_ref(), &filter(LeaderboardKind::Time), 0, 10),
+                ranks(&[(1, "early"), (2, "late")])
+            );
+        }
+    }
+
+    #[test]
+    fn filters_select_the_board() {
+        for storage in backends() {
+            let runs = [
+                ("a", "map", "hero", "1"),
+                ("b", "other", "hero", "1"),
+                ("c", "map", "other", "1"),
+                ("d", "map", "hero", "2"),
+            ];
+            for (idx, (pubkey, map, hero, season)) in runs.into_iter().enumerate() {
+                let run = CompletedRun {
+                    map: map.to_string(),
+                    hero: hero.to_string(),
+                    season: season.to_string(),
+                    // Earlier players rank higher
+                    ..ranked_run(pubkey, pubkey, (600 - idx as u32, 1, 0), 20)
+                };
+                record(storage.as_ref(), &run);
+            }
+
+            let some = |value: &str| Some(value.to_string());
+            let filters = [
+                (LeaderboardFilter::default(), vec!["a", "b", "c", "d"]),
+                (
+                    LeaderboardFilter {
+                        map: some("map"),
+                        ..Default::default()
+                    },
+                    vec!["a", "c", "d"],
+                ),
+                (
+                    LeaderboardFilter {
+                        hero: some("hero"),
+                        ..Default::default()
+                    },
+                    vec!["a", "b", "d"],
+                ),
+                (
+                    LeaderboardFilter {
+                        season: some("2"),
+                        ..Default::default()
+                    },
+                    vec!["d"],
+                ),
+                (
+                    LeaderboardFilter {
+                        map: some("map"),
+                        hero: some("hero"),
+                        season: some("1"),
+                        ..Default::default()
+                    },
+                    vec!["a"],
+                ),
+                (
+                    LeaderboardFilter {
+                        map: some("unknown"),
+                        ..Default::default()
+                    },
+                    vec![],
+                ),
+            ];
+            for (filter, pubkeys) in filters {
+                let expected: Vec<(u64, &str)> = pubkeys
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, pubkey)| (idx as u64 + 1, pubkey))
+                    .collect();
+                assert_eq!(
+                    board(storage.as_ref(), &filter, 0, 10),
+                    ranks(&expected),
+                    "{filter:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pages_continue_the_ranking() {
+        for storage in backends() {
+            for (idx, pubkey) in ["a", "b", "c", "d", "e"].into_iter().enumerate() {
+                record(
+                    storage.as_ref(),
+                    &ranked_run(pubkey, pubkey, (600 - idx as u32, 1, 0), 20),
+                );
+            }
+
+            let time = filter(LeaderboardKind::Time);
+            let storage = storage.as_ref();
+            assert_eq!(board(storage, &time, 0, 2), ranks(&[(1, "a"), (2, "b")]));
+            assert_eq!(board(storage, &time, 2, 2), ranks(&[(3, "c"), (4, "d")]));
+            assert_eq!(board(storage, &time, 4, 2), ranks(&[(5, "e")]));
+            assert!(board(storage, &time, 5, 2).is_empty());
+            assert_eq!(board(storage, &time, 1, 1), ranks(&[(2, "b")]));
+            assert_eq!(board(storage, &time, 0, 100).len(), 5);
+        }
+    }
+
+    #[test]
+    fn rank_is_the_best_run_of_the_player() {
+        for storage in backends() {
+            record(storage.as_ref(), &ranked_run("a", "a1", (900, 1, 0), 20));
+            record(storage.as_ref(), &ranked_run("b", "b1", (600, 5, 0), 20));
+            record(storage.as_ref(), &ranked_run("b", "b2", (300, 20, 0), 30));
+            record(storage.as_ref(), &ranked_run("b", "b3", (100, 20, 0), 40));
+
+            // One entry per player, ranked by their best run on the board
+            assert_eq!(
+                board(storage.as_ref(), &filter(LeaderboardKind::Time), 0, 10),
+                ranks(&[(1, "a"), (2, "b")])
+            );
+            let entry = storage
+                .leaderboard_rank(&filter(LeaderboardKind::Time), "b")
+                .unwrap()
+                .unwrap();
+            assert_eq!((entry.rank, entry.ticks), (2, 600));
+
+            // Two equally good runs, the earlier one counts
+            let entry = storage
+                .leaderboard_rank(&filter(LeaderboardKind::Kills), "b")
+                .unwrap()
+                .unwrap();
+            assert_eq!((entry.rank, entry.kills, entry.completed_at), (1, 20, 30));
+
+            assert!(storage
+                .leaderboard_rank(&filter(LeaderboardKind::Time), "unknown")
+                .unwrap()
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn rejected_runs_are_not_ranked() {
+        for storage in backends() {
+            record(storage.as_ref(), &ranked_run("a", "a1", (300, 1, 0), 20));
+            record(storage.as_ref(), &ranked_run("b", "b1", (200, 1, 0), 20));
+            for (idx, verdict) in [
+                ReplayVerdict::None,
+                ReplayVerdict::Mismatch,
+                ReplayVerdict::Undecodable,
+                ReplayVerdict::Implausible,
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                // Better than every valid run, by a player that is ranked and one that is not
+                for pubkey in ["b", "cheater"] {
+                    let rejected = CompletedRun {
+                        verdict,
+                        ..ranked_run(pubkey, &format!("{pubkey}-{idx}"), (9000, 90, 900), 10)
+                    };
+                    record(storage.as_ref(), &rejected);
+                }
+            }
+
+            for kind in [
+                LeaderboardKind::Time,
+                LeaderboardKind::Kills,
+                LeaderboardKind::Score,
+            ] {
+                let entries = storage.leaderboard(&filter(kind), 0, 10).unwrap();
+                assert!(entries.iter().all(|entry| entry.ticks <= 300), "{kind:?}");
+                assert!(storage
+                    .leaderboard_rank(&filter(kind), "cheater")
+                    .unwrap()
+                    .is_none());
+            }
+            assert_eq!(
+                board(storage.as_ref(), &filter(LeaderboardKind::Time), 0, 10),
+                ranks(&[(1, "a"), (2, "b")])
+            );
+            let entry = storage
+                .leaderboard_rank(&filter(LeaderboardKind::Time), "b")
+                .unwrap()
+                .unwrap();
+            assert_eq!((entry.rank, entry.ticks), (2, 200));
+        }
+    }
+}
@@ -0,0 +1,507 @@
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use speedrun_survivors_model::{
+    LeaderboardEntry, LeaderboardFilter, LeaderboardKind, LedgerTransaction,
+};
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::{AbandonedRun, CompletedRun, Session, SessionCounts, SessionStatus, Storage};
+use crate::config::SessionTimeouts;
+
+/// Schema migrations; applied in order and tracked through `PRAGMA user_version`
+/// Never edit a migration that has been released, always append a new one
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE sessions (
+        pubkey TEXT PRIMARY KEY NOT NULL,
+        entropy TEXT NOT NULL,
+        state TEXT NOT NULL,
+        unixtime INTEGER NOT NULL
+    );
+
+    CREATE TABLE runs (
+        entropy TEXT PRIMARY KEY NOT NULL,
+        pubkey TEXT NOT NULL,
+        nft_list TEXT NOT NULL,
+        started_at INTEGER NOT NULL,
+        completed_at INTEGER NOT NULL
+    );
+    CREATE INDEX runs_pubkey ON runs (pubkey);
+
+    CREATE TABLE replays (
+        entropy TEXT PRIMARY KEY NOT NULL REFERENCES runs (entropy),
+        data TEXT NOT NULL
+    );
+"#,
+    r#"
+    ALTER TABLE runs ADD COLUMN verdict TEXT NOT NULL DEFAULT 'None';
+    ALTER TABLE runs ADD COLUMN ticks INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE runs ADD COLUMN kills INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE runs ADD COLUMN coins INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE runs ADD COLUMN level INTEGER NOT NULL DEFAULT 0;
+"#,
+    r#"
+    ALTER TABLE sessions ADD COLUMN nft_list TEXT NOT NULL DEFAULT '[]';
+"#,
+    r#"
+    ALTER TABLE runs ADD COLUMN hero TEXT NOT NULL DEFAULT '';
+    ALTER TABLE runs ADD COLUMN map TEXT NOT NULL DEFAULT '';
+    ALTER TABLE runs ADD COLUMN season TEXT NOT NULL DEFAULT '';
+    ALTER TABLE runs ADD COLUMN score INTEGER NOT NULL DEFAULT 0;
+    UPDATE runs SET score = coins + kills * 10 + level * 100;
+    CREATE INDEX runs_board ON runs (verdict, season, map, hero);
+"#,
+    r#"
+    CREATE TABLE ledger (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        pubkey TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        amount INTEGER NOT NULL,
+        reference TEXT NOT NULL,
+        unixtime INTEGER NOT NULL,
+        UNIQUE (kind, reference)
+    );
+    CREATE INDEX ledger_pubkey ON ledger (pubkey);
+"#,
+    r#"
+    CREATE TABLE entropies (
+        entropy TEXT PRIMARY KEY NOT NULL,
+        pubkey TEXT NOT NULL,
+        issued_at INTEGER NOT NULL,
+        completed_at INTEGER
+    );
+    INSERT INTO entropies (entropy, pubkey, issued_at, completed_at)
+        SELECT entropy, pubkey, started_at, completed_at FROM runs;
+    INSERT OR IGNORE INTO entropies (entropy, pubkey, issued_at)
+        SELECT entropy, pubkey, unixtime FROM sessions;
+"#,
+    r#"
+    CREATE TABLE abandoned_runs (
+        entropy TEXT PRIMARY KEY NOT NULL,
+        pubkey TEXT NOT NULL,
+        nft_list TEXT NOT NULL,
+        started_at INTEGER NOT NULL,
+        expired_at INTEGER NOT NULL
+    );
+"#,
+    r#"
+    CREATE INDEX sessions_unixtime ON sessions (unixtime);
+"#,
+    r#"
+    ALTER TABLE sessions ADD COLUMN hero TEXT NOT NULL DEFAULT '';
+    ALTER TABLE sessions ADD COLUMN map TEXT NOT NULL DEFAULT '';
+"#,
+];
+
+/// `Session::is_expired` as SQL; `?1` is bound to the current time, `?2` and `?3` to the start and game timeouts
+const SESSION_EXPIRED: &str =
+    "(state = 'AwaitingSignature' AND unixtime < ?1 - ?2) OR unixtime < ?1 - ?3";
+
+/// Best runs per player on a board, ranked; `{order}` is replaced with the board ordering
+const LEADERBOARD_QUERY: &str = r#"
+    WITH best AS (
+        SELECT pubkey, hero, map, season, ticks, kills, coins, level, score, completed_at,
+            ROW_NUMBER() OVER (PARTITION BY pubkey ORDER BY {order}) AS best
+        FROM runs
+        WHERE verdict = 'Valid'
+            AND (?1 IS NULL OR map = ?1)
+            AND (?2 IS NULL OR hero = ?2)
+            AND (?3 IS NULL OR season = ?3)
+    ), ranked AS (
+        SELECT *, ROW_NUMBER() OVER (ORDER BY {order}, pubkey) AS rank
+        FROM best
+        WHERE best = 1
+    )
+    SELECT rank, pubkey, hero, map, season, ticks, kills, coins, level, score, completed_at
+    FROM ranked
+"#;
+
+/// Embedded SQLite database
+pub struct SqliteStorage {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Runs the leaderboard query with `tail` appended; `?4` and up are bound to `extra`
+    fn query_leaderboard(
+        &self,
+        filter: &LeaderboardFilter,
+        tail: &str,
+        extra: &[&dyn rusqlite::ToSql],
+    ) -> Result<Vec<LeaderboardEntry>> {
+        let order = match filter.board {
+            LeaderboardKind::Time => "ticks DESC, completed_at",
+            LeaderboardKind::Kills => "kills DESC, completed_at",
+            LeaderboardKind::Score => "score DESC, completed_at",
+        };
+        let sql = format!("{}{tail}", LEADERBOARD_QUERY.replace("{order}", order));
+
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&sql)?;
+        let mut bind: Vec<&dyn rusqlite::ToSql> = vec![&filter.map, &filter.hero, &filter.season];
+        bind.extend_from_slice(extra);
+
+        let entries = stmt
+            .query_map(bind.as_slice(), |row| {
+                Ok(LeaderboardEntry {
+                    rank: row.get(0)?,
+                    pubkey: row.get(1)?,
+                    hero: row.get(2)?,
+                    map: row.get(3)?,
+                    season: row.get(4)?,
+                    ticks: row.get(5)?,
+                    kills: row.get(6)?,
+                    coins: row.get(7)?,
+                    level: row.get(8)?,
+                    score: row.get(9)?,
+                    completed_at: row.get(10)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+
+    fn from_connection(mut conn: Connection) -> Result<Self> {
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+        migrate(&mut conn)?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> Result<std::sync::MutexGuard<'_, Connection>> {
+        self.conn
+            .lock()
+            .map_err(|_| anyhow!("Storage lock poisoned"))
+    }
+}
+
+/// The sessions returned by `sql` with their pubkey; it must yield `pubkey, entropy, state, unixtime, nft_list, hero, map`
+fn query_sessions(
+    conn: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+) -> Result<Vec<(String, Session)>> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt
+        .query_map(params, |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, u64>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, String>(6)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    rows.into_iter()
+        .map(|(pubkey, entropy, state, unixtime, nft_list, hero, map)| {
+            Ok((
+                pubkey,
+                Session {
+                    entropy,
+                    state: state.parse()?,
+                    unixtime,
+                    nft_list: serde_json::from_str(&nft_list)?,
+                    hero,
+                    map,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Append a transaction to the ledger of `pubkey`
+/// Returns false without changing anything if a transaction of the same kind and reference exists already
+fn insert_ledger(conn: &Connection, pubkey: &str, transaction: &LedgerTransaction) -> Result<bool> {
+    let inserted = conn.execute(
+        "INSERT INTO ledger (pubkey, kind, amount, reference, unixtime) VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT (kind, reference) DO NOTHING",
+        params![
+            pubkey,
+            serde_json::to_value(transaction.kind)?.as_str(),
+            transaction.amount,
+            transaction.reference,
+            transaction.unixtime
+        ],
+    )?;
+    Ok(inserted == 1)
+}
+
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+    for (idx, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", idx + 1)?;
+        tx.commit()?;
+        log::info!("Applied database migration {}", idx + 1);
+    }
+
+    Ok(())
+}
+
+impl Storage for SqliteStorage {
+    fn session_get(&self, pubkey: &str) -> Result<Option<Session>> {
+        let conn = self.conn()?;
+        let row = conn
+            .query_row(
+                "SELECT entropy, state, unixtime, nft_list, hero, map FROM sessions WHERE pubkey = ?1",
+                params![pubkey],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, u64>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get::<_, String>(5)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        row.map(|(entropy, state, unixtime, nft_list, hero, map)| {
+            Ok(Session {
+                entropy,
+                state: state.parse()?,
+                unixtime,
+                nft_list: serde_json::from_str(&nft_list)?,
+                hero,
+                map,
+            })
+        })
+        .transpose()
+    }
+
+    fn session_put(&self, pubkey: &str, session: &Session) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT INTO sessions (pubkey, entropy, state, unixtime, nft_list, hero, map)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT (pubkey) DO UPDATE
+             SET entropy = ?2, state = ?3, unixtime = ?4, nft_list = ?5, hero = ?6, map = ?7",
+            params![
+                pubkey,
+                session.entropy,
+                session.state.as_str(),
+                session.unixtime,
+                serde_json::to_string(&session.nft_list)?,
+                session.hero,
+                session.map
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn session_delete(&self, pubkey: &str) -> Result<()> {
+        self.conn()?
+            .execute("DELETE FROM sessions WHERE pubkey = ?1", params![pubkey])?;
+        Ok(())
+    }
+
+    fn sessions_expire(
+        &self,
+        now: u64,
+        timeouts: &SessionTimeouts,
+    ) -> Result<(usize, Vec<AbandonedRun>)> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+
+        let expired = query_sessions(
+            &tx,
+            &format!(
+                "DELETE FROM sessions WHERE {SESSION_EXPIRED}
+                 RETURNING pubkey, entropy, state, unixtime, nft_list, hero, map"
+            ),
+            params![now, timeouts.start, timeouts.game],
+        )?;
+
+        let mut abandoned = Vec::new();
+        for (pubkey, session) in &expired {
+            if let Some(run) = session.abandoned_run(pubkey, now) {
+                tx.execute(
+                    "INSERT INTO abandoned_runs (entropy, pubkey, nft_list, started_at, expired_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5) ON CONFLICT (entropy) DO NOTHING",
+                    params![
+                        run.entropy,
+                        run.pubkey,
+                        serde_json::to_string(&run.nft_list)?,
+                        run.started_at,
+                        run.expired_at
+                    ],
+                )?;
+                abandoned.push(run);
+            }
+        }
+
+        tx.commit()?;
+        Ok((expired.len(), abandoned))
+    }
+
+    fn session_counts(&self, now: u64, timeouts: &SessionTimeouts) -> Result<SessionCounts> {
+        let conn = self.conn()?;
+        let mut counts = SessionCounts {
+            abandoned: conn
+                .query_row("SELECT COUNT(*) FROM abandoned_runs", [], |row| row.get(0))?,
+            ..Default::default()
+        };
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT CASE WHEN {SESSION_EXPIRED} THEN 'Expired' ELSE state END AS phase, COUNT(*)
+             FROM sessions GROUP BY phase"
+        ))?;
+        let rows = stmt.query_map(params![now, timeouts.start, timeouts.game], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?))
+        })?;
+        for row in rows {
+            let (phase, count) = row?;
+            match phase.as_str() {
+                "Expired" => counts.expired = count,
+                state => match state.parse()? {
+                    SessionStatus::AwaitingSignature => counts.awaiting_signature = count,
+                    SessionStatus::GameStarted => counts.active = count,
+                },
+            }
+        }
+        Ok(counts)
+    }
+
+    fn entropy_register(&self, entropy: &str, pubkey: &str, now: u64) -> Result<bool> {
+        let inserted = self.conn()?.execute(
+            "INSERT INTO entropies (entropy, pubkey, issued_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (entropy) DO NOTHING",
+            params![entropy, pubkey, now],
+        )?;
+        Ok(inserted == 1)
+    }
+
+    fn entropy_completed(&self, entropy: &str) -> Result<bool> {
+        Ok(self.conn()?.query_row(
+            "SELECT EXISTS (SELECT 1 FROM entropies WHERE entropy = ?1 AND completed_at IS NOT NULL)",
+            params![entropy],
+            |row| row.get(0),
+        )?)
+    }
+
+    fn run_complete(
+        &self,
+        run: &CompletedRun,
+        replay: &str,
+        reward: Option<&LedgerTransaction>,
+    ) -> Result<bool> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        // Dropping the transaction without committing rolls it back
+        let consumed = tx.execute(
+            "UPDATE entropies SET completed_at = ?2 WHERE entropy = ?1 AND completed_at IS NULL",
+            params![run.entropy, run.completed_at],
+        )?;
+        if consumed != 1 {
+            return Ok(false);
+        }
+        tx.execute(
+            "INSERT INTO runs (entropy, pubkey, nft_list, started_at, completed_at, verdict, ticks, kills, coins, level, hero, map, season, score)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                run.entropy,
+                run.pubkey,
+                serde_json::to_string(&run.nft_list)?,
+                run.started_at,
+                run.completed_at,
+                serde_json::to_value(run.verdict)?.as_str(),
+                run.outcome.ticks,
+                run.outcome.kills,
+                run.outcome.coins,
+                run.outcome.level,
+                run.hero,
+                run.map,
+                run.season,
+                run.outcome.score()
+            ],
+        )?;
+        tx.execute(
+            "INSERT INTO replays (entropy, data) VALUES (?1, ?2)",
+            params![run.entropy, replay],
+        )?;
+        if let Some(reward) = reward {
+            insert_ledger(&tx, &run.pubkey, reward)?;
+        }
+        tx.commit()?;
+        Ok(true)
+    }
+
+    fn leaderboard(
+        &self,
+        filter: &LeaderboardFilter,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<LeaderboardEntry>> {
+        self.query_leaderboard(
+            filter,
+            "ORDER BY rank LIMIT ?4 OFFSET ?5",
+            &[&limit, &offset],
+        )
+    }
+
+    fn leaderboard_rank(
+        &self,
+        filter: &LeaderboardFilter,
+        pubkey: &str,
+    ) -> Result<Option<LeaderboardEntry>> {
+        Ok(self
+            .query_leaderboard(filter, "WHERE pubkey = ?4", &[&pubkey])?
+            .pop())
+    }
+
+    fn ledger_balance(&self, pubkey: &str) -> Result<i64> {
+        Ok(self.conn()?.query_row(
+            "SELECT COALESCE(SUM(amount), 0) FROM ledger WHERE pubkey = ?1",
+            params![pubkey],
+            |row| row.get(0),
+        )?)
+    }
+
+    fn ledger_history(
+        &self,
+        pubkey: &str,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<LedgerTransaction>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(
+            "SELECT kind, amount, reference, unixtime FROM ledger WHERE pubkey = ?1
+             ORDER BY id DESC LIMIT ?2 OFFSET ?3",
+        )?;
+        let rows = stmt
+            .query_map(params![pubkey, limit, offset], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, u64>(3)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        rows.into_iter()
+            .map(|(kind, amount, reference, unixtime)| {
+                Ok(LedgerTransaction {
+                    kind: serde_json::from_value(serde_json::Value::String(kind))?,
+                    amount,
+                    reference,
+                    unixtime,
+                })
+            })
+            .collect()
+    }
+}
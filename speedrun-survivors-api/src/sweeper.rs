@@ -0,0 +1,56 @@
+use actix_web::rt::time::interval;
+use actix_web::web::Data;
+use actix_web::web::Json;
+use std::time::Duration;
+
+use crate::config::{Config, SessionTimeouts};
+use crate::error::ApiError;
+use crate::storage::{blocking, SessionCounts, Storage};
+use crate::utils::unixtime;
+
+/// Evict expired sessions on an interval; sessions are otherwise only checked for expiry when their wallet returns
+pub fn spawn_sweeper(storage: Data<dyn Storage>, config: &Config) {
+    let timeouts = config.session;
+    let period = Duration::from_secs(config.sweep_interval.max(1));
+
+    actix_web::rt::spawn(async move {
+        let mut ticker = interval(period);
+        loop {
+            ticker.tick().await;
+            sweep(&storage, timeouts).await;
+        }
+    });
+}
+
+async fn sweep(storage: &Data<dyn Storage>, timeouts: SessionTimeouts) {
+    let expired = blocking(storage, move |storage| {
+        storage.sessions_expire(unixtime(), &timeouts)
+    })
+    .await;
+    match expired {
+        Ok((0, _)) => {}
+        Ok((removed, abandoned)) => {
+            log::info!(
+                "Evicted {removed} expired sessions, {} of them abandoned a run",
+                abandoned.len()
+            );
+            for run in abandoned {
+                log::debug!("Abandoned run {} of {}", run.entropy, run.pubkey);
+            }
+        }
+        Err(e) => log::error!("Failed to evict expired sessions: {e:?}"),
+    }
+}
+
+/// Number of sessions per state and of abandoned runs
+pub async fn session_stats(
+    storage: Data<dyn Storage>,
+    config: Data<Config>,
+) -> Result<Json<SessionCounts>, ApiError> {
+    let timeouts = config.session;
+    let counts = blocking(&storage, move |storage| {
+        storage.session_counts(unixtime(), &timeouts)
+    })
+    .await?;
+    Ok(Json(counts))
+}
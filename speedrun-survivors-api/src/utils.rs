@@ -1,4 +1,5 @@
 use rand::distributions::Distribution;
+use rand::rngs::OsRng;
 use rand::seq::SliceRandom;
 use rand::Rng;
 use std::time::SystemTime;
@@ -12,8 +13,9 @@ impl Distribution<char> for Base64 {
     }
 }
 
-pub fn weak_random_base64_string(len: usize) -> String {
-    rand::thread_rng().sample_iter(&Base64).take(len).collect()
+/// Random base64 characters drawn from the OS CSPRNG; suitable for session entropy
+pub fn secure_random_base64_string(len: usize) -> String {
+    OsRng.sample_iter(&Base64).take(len).collect()
 }
 
 pub fn unixtime() -> u64 {
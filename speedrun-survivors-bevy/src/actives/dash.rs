@@ -1,27 +1,20 @@
 use crate::plugins::gameplay_effects::{
     GameplayEffect, GameplayEffectPluginState, GameplayStat, GameplayTag,
 };
+use crate::plugins::simulation::{SimulationInput, SimulationSet};
 use crate::GameAction;
 use bevy::prelude::*;
-use leafwing_input_manager::action_state::ActionState;
-
-use crate::state::AppState;
 
 pub struct DashPlugin;
 
 impl Plugin for DashPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, on_update.run_if(in_state(AppState::GameRunning)));
+        app.add_systems(FixedUpdate, on_update.in_set(SimulationSet::Input));
     }
 }
 
-fn on_update(
-    actions: Query<&ActionState<GameAction>>,
-    mut gameplay_state: ResMut<GameplayEffectPluginState>,
-) {
-    let action = actions.single();
-
-    if action.pressed(GameAction::Action3)
+fn on_update(input: Res<SimulationInput>, mut gameplay_state: ResMut<GameplayEffectPluginState>) {
+    if input.pressed(GameAction::Action3)
         && gameplay_state.player_tags.add_tag(GameplayTag::Dash, 2.5)
     {
         gameplay_state.player_effects.apply_temporary(
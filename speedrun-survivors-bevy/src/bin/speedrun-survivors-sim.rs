@@ -0,0 +1,319 @@
+//! Headless simulation of a single run; no window, audio or rendering
+//!
+//! `speedrun-survivors-sim --seed <entropy> [--hero <hero>] [--map <map>] [--nfts <key,key>] [--replay <file|->] [--max-ticks <ticks>]`
+//!
+//! Values can also be attached as `--hero=<hero>`, then they are never taken for an option.
+//!
+//! Prints a JSON summary of the run to stdout. Without a replay the hero stands still until it dies.
+//! Exits with 2 if the replay can not be decoded, 1 on any other error.
+
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use leafwing_input_manager::prelude::*;
+use serde::Serialize;
+use speedrun_survivors_bevy::actives::dash::DashPlugin;
+use speedrun_survivors_bevy::data::enemy::EnemyRegistry;
+use speedrun_survivors_bevy::data::hero::HeroRegistry;
+use speedrun_survivors_bevy::data::item::ItemType;
+use speedrun_survivors_bevy::data::map::MapId;
+use speedrun_survivors_bevy::data::wave::WaveRegistry;
+use speedrun_survivors_bevy::enemy::enemy_spawner::SpawnEnemiesPlugin;
+use speedrun_survivors_bevy::enemy::EnemyPlugin;
+use speedrun_survivors_bevy::menu::MenuGameConfig;
+use speedrun_survivors_bevy::passives::orca_chopper::OrcaChopperPlugin;
+use speedrun_survivors_bevy::passives::rug_pull::RugPullPlugin;
+use speedrun_survivors_bevy::passives::shitcoin::ShitcoinClusterPlugin;
+use speedrun_survivors_bevy::passives::whale::WhaleDumpPlugin;
+use speedrun_survivors_bevy::player::{PlayerPlugin, PlayerState};
+use speedrun_survivors_bevy::plugins::assets::AssetsPlugin;
+use speedrun_survivors_bevy::plugins::camera_shake::CameraImpact;
+use speedrun_survivors_bevy::plugins::coin_rewards::{CoinAccumulator, CoinRewardsPlugin};
+use speedrun_survivors_bevy::plugins::game_rng::GameRngPlugin;
+use speedrun_survivors_bevy::plugins::gameplay_effects::{
+    GameplayEffectPluginState, GameplayEffectsPlugin,
+};
+use speedrun_survivors_bevy::plugins::health::HealthPlugin;
+use speedrun_survivors_bevy::plugins::hud::HudRedraw;
+use speedrun_survivors_bevy::plugins::pickup::PickupPlugin;
+use speedrun_survivors_bevy::plugins::sfx_manager::PlaySFX;
+use speedrun_survivors_bevy::plugins::simulation::SimulationPlugin;
+use speedrun_survivors_bevy::plugins::status_effect::StatusEffectPlugin;
+use speedrun_survivors_bevy::plugins::vfx_manager::PlayVFX;
+use speedrun_survivors_bevy::replay::playback::{ReplayPlayback, ReplayPlaybackPlugin};
+use speedrun_survivors_bevy::replay::recorder::{ReplayRecorder, ReplayRecorderPlugin};
+use speedrun_survivors_bevy::state::{AppState, StatesPlugin};
+use speedrun_survivors_bevy::weapon::WeaponPlugin;
+use speedrun_survivors_bevy::GameAction;
+use speedrun_survivors_replay::{Replay, TICKS_PER_SECOND};
+
+/// The API treats this exit code as an undecodable replay
+const EXIT_UNDECODABLE: u8 = 2;
+
+/// Runs without a replay end after an hour of game time at the latest
+const DEFAULT_MAX_TICKS: u32 = 60 * 60 * TICKS_PER_SECOND;
+
+/// Give up if the simulation stops advancing, i.e. stuck outside of the running state
+const MAX_STALLED_FRAMES: u32 = 600;
+
+/// Give up if the game data is not loaded by then
+const ASSET_LOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Args {
+    seed: Option<String>,
+    /// Id of a hero in the roster; the first one if not given
+    hero: Option<String>,
+    map: MapId,
+    /// Keys of the equipped NFTs, as locked when the game started
+    nfts: Vec<String>,
+    replay: Option<String>,
+    max_ticks: u32,
+}
+
+impl Args {
+    fn parse() -> Result<Self> {
+        let args: Vec<String> = std::env::args().collect();
+        let arg = |name: &str| {
+            let attached = format!("{name}=");
+            args.iter()
+                .find_map(|arg| arg.strip_prefix(&attached))
+                .or_else(|| {
+                    args.iter()
+                        .position(|arg| arg == name)
+                        .and_then(|idx| args.get(idx + 1))
+                        .map(String::as_str)
+                })
+        };
+
+        Ok(Self {
+            seed: arg("--seed").map(String::from),
+            hero: arg("--hero").map(String::from),
+            map: arg("--map")
+                .map(|map| map.parse())
+                .transpose()
+                .context("Unknown map")?
+                .unwrap_or_default(),
+            nfts: arg("--nfts")
+                .map(|nfts| {
+                    nfts.split(',')
+                        .filter(|key| !key.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            replay: arg("--replay").map(String::from),
+            max_ticks: arg("--max-ticks")
+                .map(|ticks| ticks.parse())
+                .transpose()
+                .context("Invalid tick count")?
+                .unwrap_or(DEFAULT_MAX_TICKS),
+        })
+    }
+}
+
+/// The outcome of a run; field names match what the API expects
+#[derive(Serialize)]
+struct RunSummary {
+    ticks: u32,
+    /// Game time in seconds
+    time: f32,
+    kills: u64,
+    coins: u64,
+    level: u64,
+    /// The enemy that hit the hero last, if the run ended with its death
+    death_cause: Option<String>,
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e:?}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let replay = match &args.replay {
+        Some(path) => {
+            let data = match ReplayPlayback::read(path) {
+                Ok(data) => data,
+                Err(e) => {
+                    eprintln!("Failed to read replay {path}: {e:?}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            match ReplayPlayback::load(&data) {
+                Ok(replay) => Some(replay),
+                Err(e) => {
+                    eprintln!("Failed to decode replay {path}: {e:?}");
+                    return ExitCode::from(EXIT_UNDECODABLE);
+                }
+            }
+        }
+        None => None,
+    };
+
+    let summary = simulate(&args, replay)
+        .and_then(|summary| serde_json::to_string(&summary).map_err(anyhow::Error::from));
+    match summary {
+        Ok(summary) => {
+            println!("{summary}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{e:?}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run the game until the hero dies or the replay / tick limit ends
+fn simulate(args: &Args, replay: Option<Replay>) -> Result<RunSummary> {
+    let end_tick = replay
+        .as_ref()
+        .map_or(args.max_ticks, |replay| replay.end_tick);
+
+    let mut playback = ReplayPlayback::default();
+    playback.replay = replay;
+    playback.entropy = args.seed.clone().unwrap_or_default();
+
+    let mut app = App::new();
+    app.add_state::<AppState>()
+        .add_plugins((MinimalPlugins, AssetPlugin::default()))
+        .add_asset::<TextureAtlas>()
+        // Sound, particles, camera and hud are not simulated, but the gameplay still sends to them
+        .add_event::<PlaySFX>()
+        .add_event::<PlayVFX>()
+        .add_event::<CameraImpact>()
+        .add_event::<HudRedraw>()
+        .add_plugins((
+            StatesPlugin,
+            SimulationPlugin,
+            GameRngPlugin,
+            AssetsPlugin,
+            PlayerPlugin,
+            EnemyPlugin,
+            SpawnEnemiesPlugin,
+            WeaponPlugin,
+            HealthPlugin,
+            StatusEffectPlugin,
+            DashPlugin,
+            CoinRewardsPlugin,
+            GameplayEffectsPlugin,
+            PickupPlugin,
+        ))
+        .add_plugins((
+            OrcaChopperPlugin,
+            WhaleDumpPlugin,
+            ShitcoinClusterPlugin,
+            RugPullPlugin,
+            ReplayRecorderPlugin,
+            ReplayPlaybackPlugin,
+        ))
+        .add_systems(OnEnter(AppState::GameInitializing), start_run)
+        .insert_resource(MenuGameConfig {
+            map: args.map,
+            entropy: args.seed.clone(),
+            ..default()
+        })
+        .insert_resource(playback);
+
+    // Every frame advances the game by exactly one tick
+    let period = app.world.resource::<FixedTime>().period;
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(period));
+    app.world.spawn(ActionState::<GameAction>::default());
+
+    app.finish();
+    app.cleanup();
+
+    // Heroes, enemies and waves are game data, wait for them like the splash screen does
+    let started = Instant::now();
+    while !app.world.resource::<HeroRegistry>().is_loaded()
+        || !app.world.resource::<EnemyRegistry>().is_loaded()
+        || !app.world.resource::<WaveRegistry>().is_loaded()
+    {
+        if started.elapsed() > ASSET_LOAD_TIMEOUT {
+            bail!("Failed to load the game data");
+        }
+        app.update();
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    let heroes = app.world.resource::<HeroRegistry>();
+    let hero = match &args.hero {
+        Some(id) => heroes
+            .get(id)
+            .with_context(|| format!("Unknown hero {id}"))?,
+        None => heroes.default_hero().context("No heroes")?,
+    };
+    let hero_id = hero.id.clone();
+    let hero_effects = hero.get_gameplay_effects();
+
+    // Same choices as made in the menu
+    app.world.resource_mut::<MenuGameConfig>().hero = hero_id;
+    let mut gameplay_state = app.world.resource_mut::<GameplayEffectPluginState>();
+    gameplay_state.player_effects.equip_hero(hero_effects);
+    gameplay_state
+        .player_effects
+        .equip_map(args.map.get_gameplay_effects());
+    for key in &args.nfts {
+        gameplay_state
+            .player_effects
+            .equip_nft(key, ItemType::for_nft(key).get_gameplay_effects());
+    }
+
+    app.world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::GameInitializing);
+
+    let mut last_tick = 0;
+    let mut stalled_frames = 0;
+    loop {
+        app.update();
+
+        let tick = app.world.resource::<ReplayRecorder>().tick;
+        if *app.world.resource::<State<AppState>>().get() == AppState::GameOver || tick >= end_tick
+        {
+            break;
+        }
+
+        stalled_frames = if tick == last_tick {
+            stalled_frames + 1
+        } else {
+            0
+        };
+        if stalled_frames > MAX_STALLED_FRAMES {
+            bail!(
+                "Simulation stalled at tick {tick} in {:?}",
+                app.world.resource::<State<AppState>>().get()
+            );
+        }
+        last_tick = tick;
+    }
+
+    // The hero may have died on the very last tick, before the state changed
+    let died = *app.world.resource::<State<AppState>>().get() == AppState::GameOver
+        || app.world.resource::<NextState<AppState>>().0 == Some(AppState::GameOver);
+
+    let ticks = app.world.resource::<ReplayRecorder>().tick;
+    let player_state = app.world.resource::<PlayerState>();
+    Ok(RunSummary {
+        ticks,
+        time: ticks as f32 / TICKS_PER_SECOND as f32,
+        kills: player_state.total_kills,
+        coins: app.world.resource::<CoinAccumulator>().total_coin,
+        level: player_state.level.0,
+        death_cause: player_state
+            .last_hit_by
+            .filter(|_| died)
+            .map(|enemy| format!("{enemy:?}")),
+    })
+}
+
+/// There is no menu to start the run from
+fn start_run(mut next_state: ResMut<NextState<AppState>>) {
+    next_state.set(AppState::GameRunning);
+}
@@ -0,0 +1,241 @@
+use anyhow::{bail, Context};
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::BoxedFuture;
+use rand::Rng;
+use serde::Deserialize;
+use strum::IntoEnumIterator;
+
+use crate::enemy::enemy_type::EnemyType;
+use crate::enemy::Enemy;
+
+/// Stats, art and drops of every enemy type are defined in this asset
+pub const BESTIARY_PATH: &str = "data/bestiary.enemies.ron";
+
+/// The enemy archetypes as loaded from `BESTIARY_PATH`
+#[derive(Debug, Deserialize, TypeUuid, TypePath)]
+#[uuid = "a4c1d7e2-6f35-4b0e-8d2a-91b7c3e5f604"]
+pub struct Bestiary {
+    pub enemies: Vec<EnemyDefinition>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct EnemyDefinition {
+    pub kind: EnemyType,
+    /// Sprite sheet with one row of walk frames
+    pub sprite: String,
+    pub atlas: EnemyAtlas,
+    pub scale: f32,
+    pub health: f32,
+    pub speed: f32,
+    /// Damage dealt to the player per tick of contact
+    pub attack: f32,
+    /// Dropped on death
+    pub coins: u64,
+    pub exp: u64,
+    /// Relative chance to be picked by the spawner; 0 never spawns
+    pub spawn_weight: u32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct EnemyAtlas {
+    pub tile_size: (f32, f32),
+    pub columns: usize,
+}
+
+impl Bestiary {
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let bestiary: Bestiary = ron::de::from_bytes(bytes)?;
+        for (idx, enemy) in bestiary.enemies.iter().enumerate() {
+            if bestiary.enemies[..idx]
+                .iter()
+                .any(|other| other.kind == enemy.kind)
+            {
+                bail!("Enemy {:?} is defined twice", enemy.kind);
+            }
+            enemy
+                .validate()
+                .with_context(|| format!("Invalid enemy {:?}", enemy.kind))?;
+        }
+        // Enemies are referenced by type all over the game, each needs its data
+        if let Some(kind) =
+            EnemyType::iter().find(|kind| !bestiary.enemies.iter().any(|e| e.kind == *kind))
+        {
+            bail!("Missing enemy {kind:?}");
+        }
+        if bestiary.enemies.iter().all(|enemy| enemy.spawn_weight == 0) {
+            bail!("No enemy has a spawn weight");
+        }
+        Ok(bestiary)
+    }
+}
+
+impl EnemyDefinition {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.atlas.columns == 0 {
+            bail!("The atlas needs at least one column");
+        }
+        if self.health <= 0. {
+            bail!("Health must be positive");
+        }
+        if self.scale <= 0. {
+            bail!("Scale must be positive");
+        }
+        Ok(())
+    }
+
+    pub fn enemy(&self) -> Enemy {
+        Enemy {
+            speed: self.speed,
+            attack: self.attack,
+            kind: self.kind,
+        }
+    }
+
+    pub fn scale(&self) -> Vec3 {
+        Vec3::splat(self.scale)
+    }
+
+    pub fn texture_atlas(&self, texture_handle: Handle<Image>) -> TextureAtlas {
+        TextureAtlas::from_grid(
+            texture_handle,
+            Vec2::new(self.atlas.tile_size.0, self.atlas.tile_size.1),
+            self.atlas.columns,
+            1,
+            Some(Vec2::new(1., 1.)),
+            None,
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct BestiaryLoader;
+
+impl AssetLoader for BestiaryLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let bestiary = Bestiary::from_bytes(bytes)
+                .with_context(|| format!("Invalid bestiary {:?}", load_context.path()))?;
+            load_context.set_default_asset(LoadedAsset::new(bestiary));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["enemies.ron"]
+    }
+}
+
+/// The loaded enemy archetypes; empty until the bestiary asset is loaded
+#[derive(Resource, Default)]
+pub struct EnemyRegistry {
+    pub handle: Handle<Bestiary>,
+    enemies: Vec<EnemyDefinition>,
+}
+
+impl EnemyRegistry {
+    pub fn is_loaded(&self) -> bool {
+        !self.enemies.is_empty()
+    }
+
+    pub fn set_enemies(&mut self, enemies: Vec<EnemyDefinition>) {
+        self.enemies = enemies;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &EnemyDefinition> {
+        self.enemies.iter()
+    }
+
+    pub fn get(&self, kind: EnemyType) -> Option<&EnemyDefinition> {
+        self.enemies.iter().find(|enemy| enemy.kind == kind)
+    }
+
+    /// Pick an enemy by spawn weight
+    pub fn random(&self, rng: &mut impl Rng) -> Option<&EnemyDefinition> {
+        weighted_pick(&self.enemies, |enemy| enemy.spawn_weight, rng)
+    }
+}
+
+/// Pick one of `items` by weight; draws exactly one number so replays stay in sync
+pub fn weighted_pick<'a, T>(
+    items: &'a [T],
+    weight: impl Fn(&T) -> u32,
+    rng: &mut impl Rng,
+) -> Option<&'a T> {
+    let total: u32 = items.iter().map(&weight).sum();
+    if total == 0 {
+        return None;
+    }
+
+    let mut roll = rng.gen_range(0..total);
+    for item in items {
+        if roll < weight(item) {
+            return Some(item);
+        }
+        roll -= weight(item);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    const BESTIARY: &str = include_str!("../../assets/data/bestiary.enemies.ron");
+
+    #[test]
+    fn shipped_bestiary_has_every_enemy() {
+        let bestiary = Bestiary::from_bytes(BESTIARY.as_bytes()).unwrap();
+        assert_eq!(bestiary.enemies.len(), EnemyType::iter().count());
+    }
+
+    #[test]
+    fn enemy_without_coins_is_rejected() {
+        let bestiary = BESTIARY.replacen("coins: 100,", "", 1);
+        assert!(Bestiary::from_bytes(bestiary.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn missing_enemy_is_rejected() {
+        let spider = BESTIARY.find("(\n            kind: Spider").unwrap();
+        let snake = BESTIARY.find("(\n            kind: Snake").unwrap();
+        let bestiary = format!("{}{}", &BESTIARY[..spider], &BESTIARY[snake..]);
+
+        let error = Bestiary::from_bytes(bestiary.as_bytes()).unwrap_err();
+        assert!(error.to_string().contains("Missing enemy Spider"));
+    }
+
+    #[test]
+    fn picks_follow_the_weights() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let items = [('a', 3), ('b', 0), ('c', 1)];
+
+        let mut picks = [0; 3];
+        for _ in 0..4000 {
+            let (item, _) = weighted_pick(&items, |(_, weight)| *weight, &mut rng).unwrap();
+            picks[items.iter().position(|(other, _)| other == item).unwrap()] += 1;
+        }
+        assert_eq!(picks[1], 0);
+        assert!((2850..3150).contains(&picks[0]), "{picks:?}");
+    }
+
+    #[test]
+    fn pick_draws_one_number() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let mut expected = rng.clone();
+        let items = [('a', 3), ('b', 5)];
+
+        weighted_pick(&items, |(_, weight)| *weight, &mut rng);
+        expected.gen_range(0..8u32);
+        assert_eq!(rng.gen::<u64>(), expected.gen::<u64>());
+
+        assert_eq!(weighted_pick(&items, |_| 0, &mut rng), None);
+    }
+}
@@ -1,137 +1,232 @@
+use anyhow::{bail, Context};
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
 use bevy::prelude::*;
-use strum::EnumIter;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::{BoxedFuture, HashMap};
+use serde::Deserialize;
+use strum::IntoEnumIterator;
 
+use crate::animation::Animation;
 use crate::plugins::gameplay_effects::{GameplayEffect, GameplayStat};
-use crate::{animation::Animation, plugins::assets::GameAssets, weapon::weapon_type::WeaponType};
-
-#[derive(Clone, Copy, Debug, Eq, Hash, Default, PartialEq, EnumIter)]
-pub enum HeroType {
-    Pepe,
-    #[default]
-    BonkInu,
-    Orca,
-    MadLad,
-    MysteryHero1,
-    MysteryHero2,
+use crate::weapon::weapon_type::WeaponType;
+
+/// All heroes are defined in this asset; adding a hero needs art and an entry here, no code
+pub const HERO_ROSTER_PATH: &str = "data/roster.heroes.ron";
+
+/// Each hero needs its own complete set of ABS type stats
+const REQUIRED_STATS: [GameplayStat; 6] = [
+    GameplayStat::HealthCap,
+    GameplayStat::HealthRegen,
+    GameplayStat::Damage,
+    GameplayStat::AttackRate,
+    GameplayStat::MovementSpeed,
+    GameplayStat::PickupDistance,
+];
+
+/// The heroes as loaded from `HERO_ROSTER_PATH`, in menu order
+#[derive(Debug, Deserialize, TypeUuid, TypePath)]
+#[uuid = "5e0f0c6a-3b8e-4a52-9a57-3f1f0e8f2b41"]
+pub struct HeroRoster {
+    pub heroes: Vec<HeroDefinition>,
 }
 
-impl HeroType {
-    pub fn walk_animation(&self) -> Animation {
-        match self {
-            HeroType::Pepe => Animation {
-                start: 1,
-                end: 4,
-                looping: true,
-                cooldown: 0.1,
-            },
-            HeroType::BonkInu => Animation {
-                start: 1,
-                end: 5,
-                looping: true,
-                cooldown: 0.1,
-            },
-            _ => Animation {
-                start: 1,
-                end: 4,
-                looping: true,
-                cooldown: 0.1,
-            },
+#[derive(Clone, Debug, Deserialize)]
+pub struct HeroDefinition {
+    /// Identifies the hero in the menu, replays and the API, i.e. `Pepe`
+    pub id: String,
+    /// Sprite sheet with one row of walk frames
+    pub sprite: String,
+    pub ui_image: String,
+    pub atlas: HeroAtlas,
+    pub walk_animation: HeroAnimation,
+    pub splat_scale: f32,
+    pub weapon_offsets: HashMap<WeaponType, (f32, f32)>,
+    /// Base stats, applied as ABS effects in this order
+    pub stats: Vec<(GameplayStat, f64)>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct HeroAtlas {
+    pub tile_size: (f32, f32),
+    pub columns: usize,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct HeroAnimation {
+    pub start: usize,
+    pub end: usize,
+    pub cooldown: f32,
+}
+
+impl HeroRoster {
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let roster: HeroRoster = ron::de::from_bytes(bytes)?;
+        if roster.heroes.is_empty() {
+            bail!("The roster has no heroes");
         }
+        for (idx, hero) in roster.heroes.iter().enumerate() {
+            if roster.heroes[..idx].iter().any(|other| other.id == hero.id) {
+                bail!("Hero {} is defined twice", hero.id);
+            }
+            hero.validate()
+                .with_context(|| format!("Invalid hero {}", hero.id))?;
+        }
+        Ok(roster)
     }
+}
 
-    pub fn weapon_offset(&self, weapon_type: WeaponType) -> Vec2 {
-        match self {
-            HeroType::Pepe => match weapon_type {
-                WeaponType::Hammer => Vec2::new(50., 30.),
-                WeaponType::Sword => Vec2::new(35., 15.),
-                WeaponType::Gun => Vec2::new(0., 0.),
-                WeaponType::FlameThrower => Vec2::new(50., 20.),
-            },
-            HeroType::BonkInu => match weapon_type {
-                WeaponType::Hammer => Vec2::new(50., 30.),
-                WeaponType::Sword => Vec2::new(45., 15.),
-                WeaponType::Gun => Vec2::new(0., 0.),
-                WeaponType::FlameThrower => Vec2::new(50., -10.),
-            },
-            _ => match weapon_type {
-                WeaponType::Hammer => Vec2::new(0., 0.),
-                WeaponType::Sword => Vec2::new(0., 0.),
-                WeaponType::Gun => Vec2::new(0., 0.),
-                WeaponType::FlameThrower => Vec2::new(0., 0.),
-            },
+impl HeroDefinition {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.id.is_empty() {
+            bail!("Missing id");
         }
+        if self.atlas.columns == 0 {
+            bail!("The atlas needs at least one column");
+        }
+        let anim = self.walk_animation;
+        if anim.start > anim.end || anim.end > self.atlas.columns {
+            bail!(
+                "Walk animation {}..{} does not fit into {} columns",
+                anim.start,
+                anim.end,
+                self.atlas.columns
+            );
+        }
+        if let Some(weapon) =
+            WeaponType::iter().find(|weapon| !self.weapon_offsets.contains_key(weapon))
+        {
+            bail!("Missing weapon offset for {weapon:?}");
+        }
+        if let Some(stat) = REQUIRED_STATS
+            .iter()
+            .find(|stat| !self.stats.iter().any(|(other, _)| other == *stat))
+        {
+            bail!("Missing stat {stat:?}");
+        }
+        Ok(())
     }
 
-    pub fn splat_scale(&self) -> f32 {
-        match self {
-            HeroType::Pepe => 2.,
-            HeroType::BonkInu => 1.4,
-            _ => 3.5,
+    pub fn walk_animation(&self) -> Animation {
+        Animation {
+            start: self.walk_animation.start,
+            end: self.walk_animation.end,
+            looping: true,
+            cooldown: self.walk_animation.cooldown,
         }
     }
 
-    pub fn texture_atlas(&self, game_assets: &Res<GameAssets>) -> TextureAtlas {
-        let texture_handle = game_assets.heroes.get(self).unwrap().clone();
-
-        let (size, frames) = match self {
-            HeroType::Pepe => (Vec2::new(32., 56.), 4),
-            HeroType::BonkInu => (Vec2::new(57., 64.), 5),
-            _ => (Vec2::new(32., 56.), 4),
-        };
+    pub fn weapon_offset(&self, weapon_type: WeaponType) -> Vec2 {
+        self.weapon_offsets
+            .get(&weapon_type)
+            .map_or(Vec2::ZERO, |(x, y)| Vec2::new(*x, *y))
+    }
 
-        let texture_atlas = TextureAtlas::from_grid(
+    pub fn texture_atlas(&self, texture_handle: Handle<Image>) -> TextureAtlas {
+        TextureAtlas::from_grid(
             texture_handle,
-            size,
-            frames,
+            Vec2::new(self.atlas.tile_size.0, self.atlas.tile_size.1),
+            self.atlas.columns,
             1,
             Some(Vec2::new(1., 1.)),
             None,
-        );
+        )
+    }
 
-        texture_atlas
+    pub fn get_gameplay_effects(&self) -> Vec<GameplayEffect> {
+        self.stats
+            .iter()
+            .map(|(stat, val)| GameplayEffect::new_abs(*stat, *val))
+            .collect()
     }
+}
 
-    pub fn get_sprite_name(&self) -> &str {
-        match self {
-            HeroType::Pepe => "sprites/player/pepe.png",
-            HeroType::BonkInu => "sprites/player/bonk-walking.png",
-            _ => "sprites/player/pepe.png",
-        }
+#[derive(Default)]
+pub struct HeroRosterLoader;
+
+impl AssetLoader for HeroRosterLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let roster = HeroRoster::from_bytes(bytes)
+                .with_context(|| format!("Invalid hero roster {:?}", load_context.path()))?;
+            load_context.set_default_asset(LoadedAsset::new(roster));
+            Ok(())
+        })
     }
 
-    pub fn get_ui_image_name(&self) -> &str {
-        match self {
-            HeroType::Pepe => "ui/heroes/pepe.png",
-            HeroType::BonkInu => "ui/heroes/bonk_inu.png",
-            HeroType::Orca => "ui/heroes/orca.png",
-            HeroType::MadLad => "ui/heroes/madlad.png",
-            _ => "ui/heroes/mystery.png",
-        }
+    fn extensions(&self) -> &[&str] {
+        &["heroes.ron"]
     }
+}
 
-    /// Each hero needs its own complete set of ABS type stats
-    pub fn get_gameplay_effects(&self) -> Vec<GameplayEffect> {
-        match self {
-            HeroType::Pepe => {
-                vec![
-                    GameplayEffect::new_abs(GameplayStat::HealthCap, 100.0),
-                    GameplayEffect::new_abs(GameplayStat::HealthRegen, 10.0),
-                    GameplayEffect::new_abs(GameplayStat::Damage, 5.0),
-                    GameplayEffect::new_abs(GameplayStat::AttackRate, 5.0),
-                    GameplayEffect::new_abs(GameplayStat::MovementSpeed, 100.0),
-                    GameplayEffect::new_abs(GameplayStat::PickupDistance, 40.0),
-                ]
-            }
-            _ => {
-                vec![
-                    GameplayEffect::new_abs(GameplayStat::HealthCap, 120.0),
-                    GameplayEffect::new_abs(GameplayStat::HealthRegen, 5.0),
-                    GameplayEffect::new_abs(GameplayStat::Damage, 5.0),
-                    GameplayEffect::new_abs(GameplayStat::AttackRate, 5.0),
-                    GameplayEffect::new_abs(GameplayStat::MovementSpeed, 120.0),
-                    GameplayEffect::new_abs(GameplayStat::PickupDistance, 40.0),
-                ]
-            }
-        }
+/// The loaded heroes; empty until the roster asset is loaded
+#[derive(Resource, Default)]
+pub struct HeroRegistry {
+    pub handle: Handle<HeroRoster>,
+    heroes: Vec<HeroDefinition>,
+}
+
+impl HeroRegistry {
+    pub fn is_loaded(&self) -> bool {
+        !self.heroes.is_empty()
+    }
+
+    pub fn set_heroes(&mut self, heroes: Vec<HeroDefinition>) {
+        self.heroes = heroes;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &HeroDefinition> {
+        self.heroes.iter()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&HeroDefinition> {
+        self.heroes.iter().find(|hero| hero.id == id)
+    }
+
+    /// The first hero of the roster is picked when none was chosen
+    pub fn default_hero(&self) -> Option<&HeroDefinition> {
+        self.heroes.first()
+    }
+
+    /// The hero with this id, or the default one if it is unknown
+    pub fn get_or_default(&self, id: &str) -> Option<&HeroDefinition> {
+        self.get(id).or_else(|| self.default_hero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROSTER: &str = include_str!("../../assets/data/roster.heroes.ron");
+
+    #[test]
+    fn shipped_roster_is_valid() {
+        let roster = HeroRoster::from_bytes(ROSTER.as_bytes()).unwrap();
+        // The default hero
+        assert_eq!(roster.heroes[0].id, "Pepe");
+    }
+
+    #[test]
+    fn hero_without_splat_scale_is_rejected() {
+        let roster = ROSTER.replacen("splat_scale: 2.0,", "", 1);
+        assert!(HeroRoster::from_bytes(roster.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn hero_without_a_required_stat_is_rejected() {
+        let roster = ROSTER.replacen("(PickupDistance, 40.0),", "", 1);
+        let error = HeroRoster::from_bytes(roster.as_bytes()).unwrap_err();
+        assert!(format!("{error:#}").contains("Missing stat PickupDistance"));
+    }
+
+    #[test]
+    fn heroes_must_be_unique() {
+        let roster = ROSTER.replacen(r#"id: "BonkInu""#, r#"id: "Pepe""#, 1);
+        let error = HeroRoster::from_bytes(roster.as_bytes()).unwrap_err();
+        assert!(error.to_string().contains("Pepe is defined twice"));
     }
 }
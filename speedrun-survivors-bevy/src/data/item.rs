@@ -10,6 +10,12 @@ pub enum ItemType {
 }
 
 impl ItemType {
+    /// The item an equipped NFT grants; the menu and the simulator must agree on it
+    pub fn for_nft(_key: &str) -> Self {
+        //TODO depends on NFT
+        ItemType::BonkInuBattleBracers
+    }
+
     /// The path to the ui image for this item
     pub fn get_ui_image_name(&self) -> &str {
         match self {
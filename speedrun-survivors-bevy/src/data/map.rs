@@ -1,8 +1,8 @@
 use crate::plugins::gameplay_effects::{GameplayEffect, GameplayStat};
 use bevy::prelude::*;
-use strum::EnumIter;
+use strum::{EnumIter, EnumString};
 
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, EnumIter)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, EnumIter, EnumString)]
 pub enum MapId {
     #[default]
     Map1,
@@ -27,6 +27,14 @@ impl MapId {
         }
     }
 
+    /// Returns the path of the wave timeline, i.e. what spawns when
+    pub fn get_waves_path(&self) -> &str {
+        match self {
+            MapId::Map1 => "data/waves/map_1.waves.ron",
+            _ => "data/waves/default.waves.ron",
+        }
+    }
+
     /// Returns the dimensions of the imported map
     pub fn get_map_dimensions(&self) -> (f32, f32) {
         match self {
@@ -50,18 +58,20 @@ impl MapId {
         (map_witdh, map_height)
     }
     pub fn is_at_border(&self, tr: Transform) -> bool {
+        !self.get_playable_area().contains(tr.translation.truncate())
+    }
+
+    /// The area within the walls, where the player and enemies can be
+    pub fn get_playable_area(&self) -> Rect {
         let (map_width, map_height) = self.get_scaled_map_dimensions();
         let border_offset = self.get_scaled_border_offset();
 
-        let x = tr.translation.x;
-        let y = tr.translation.y;
-
-        let x_min = -map_width / 2. + border_offset.x;
-        let x_max = map_width / 2. - border_offset.x;
-        let y_min = -map_height / 2. + border_offset.y;
-        let y_max = map_height / 2. - border_offset.y;
-
-        x < x_min || x > x_max || y < y_min || y > y_max
+        Rect::new(
+            -map_width / 2. + border_offset.x,
+            -map_height / 2. + border_offset.y,
+            map_width / 2. - border_offset.x,
+            map_height / 2. - border_offset.y,
+        )
     }
 
     pub fn get_border_offset(&self) -> Vec2 {
@@ -107,3 +117,19 @@ impl MapId {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use speedrun_survivors_model::MAP_IDS;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn server_knows_every_map() {
+        let names: Vec<String> = MapId::iter().map(|map| format!("{map:?}")).collect();
+        assert_eq!(names, MAP_IDS);
+        for id in MAP_IDS {
+            assert!(id.parse::<MapId>().is_ok());
+        }
+    }
+}
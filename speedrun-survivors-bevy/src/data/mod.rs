@@ -1,5 +1,29 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::data::enemy::EnemyRegistry;
+use crate::data::hero::HeroRegistry;
+use crate::data::wave::WaveRegistry;
+
 pub mod abilities;
+pub mod enemy;
 pub mod hero;
 pub mod item;
 pub mod level;
 pub mod map;
+pub mod wave;
+
+/// The registries of the game data in `assets/data`
+#[derive(SystemParam)]
+pub struct GameData<'w> {
+    pub heroes: Res<'w, HeroRegistry>,
+    pub enemies: Res<'w, EnemyRegistry>,
+    pub waves: Res<'w, WaveRegistry>,
+}
+
+impl GameData<'_> {
+    /// A run can only start once all of it is loaded
+    pub fn is_loaded(&self) -> bool {
+        self.heroes.is_loaded() && self.enemies.is_loaded() && self.waves.is_loaded()
+    }
+}
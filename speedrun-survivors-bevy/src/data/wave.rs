@@ -0,0 +1,238 @@
+use anyhow::{bail, Context};
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::{BoxedFuture, HashMap};
+use rand::Rng;
+use serde::Deserialize;
+use strum::IntoEnumIterator;
+
+use crate::data::enemy::weighted_pick;
+use crate::data::map::MapId;
+use crate::enemy::enemy_type::EnemyType;
+
+/// What spawns when on one map, see `MapId::get_waves_path`
+/// All times are in minutes since the run started
+#[derive(Clone, Debug, Deserialize, TypeUuid, TypePath)]
+#[uuid = "3f9b2c7d-1e4a-4d68-b5c0-7a2e9d1f8c35"]
+pub struct WaveTimeline {
+    /// Each phase lasts until the next one starts, the last one until the run ends
+    pub phases: Vec<WavePhase>,
+    #[serde(default)]
+    pub bursts: Vec<WaveBurst>,
+    #[serde(default)]
+    pub bosses: Vec<BossSpawn>,
+    #[serde(default)]
+    pub ring: SpawnRing,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WavePhase {
+    pub minute: f32,
+    /// Seconds between two spawns at a `SpawnRate` of 1
+    pub interval: f32,
+    /// Enemies per spawn
+    pub count: u32,
+    /// Enemy types and their weights; empty uses the spawn weights of the bestiary
+    #[serde(default)]
+    pub mix: Vec<(EnemyType, u32)>,
+}
+
+/// A group of enemies spawned at once
+#[derive(Clone, Debug, Deserialize)]
+pub struct WaveBurst {
+    pub minute: f32,
+    /// Enemies at a `SpawnRate` of 1
+    pub count: u32,
+    #[serde(default)]
+    pub mix: Vec<(EnemyType, u32)>,
+}
+
+/// A single, stronger enemy of the given type
+#[derive(Clone, Debug, Deserialize)]
+pub struct BossSpawn {
+    pub minute: f32,
+    pub kind: EnemyType,
+    /// Multiply the health, scale and drops of the archetype
+    pub health: f32,
+    pub scale: f32,
+    pub rewards: u64,
+}
+
+/// Enemies appear in a ring just outside the viewport around the player
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct SpawnRing {
+    /// Distance between the viewport edge and the inner edge of the ring
+    pub margin: f32,
+    pub width: f32,
+    /// Chance that an enemy comes from the half the player is heading to, otherwise from anywhere
+    pub ahead: f32,
+}
+
+impl Default for SpawnRing {
+    fn default() -> Self {
+        Self {
+            margin: 64.,
+            width: 128.,
+            ahead: 0.6,
+        }
+    }
+}
+
+impl WaveTimeline {
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let timeline: WaveTimeline = ron::de::from_bytes(bytes)?;
+        if timeline.phases.first().map(|phase| phase.minute) != Some(0.) {
+            bail!("The first phase must start at minute 0");
+        }
+        // The director walks through the timeline front to back
+        if !is_sorted(timeline.phases.iter().map(|phase| phase.minute))
+            || !is_sorted(timeline.bursts.iter().map(|burst| burst.minute))
+            || !is_sorted(timeline.bosses.iter().map(|boss| boss.minute))
+        {
+            bail!("Phases, bursts and bosses must be sorted by minute");
+        }
+        for phase in &timeline.phases {
+            if phase.interval <= 0. || phase.count == 0 {
+                bail!("Phase at minute {} never spawns", phase.minute);
+            }
+            validate_mix(&phase.mix)
+                .with_context(|| format!("Invalid phase at minute {}", phase.minute))?;
+        }
+        for burst in &timeline.bursts {
+            validate_mix(&burst.mix)
+                .with_context(|| format!("Invalid burst at minute {}", burst.minute))?;
+        }
+        let ring = timeline.ring;
+        if ring.margin < 0. || ring.width < 0. || !(0. ..=1.).contains(&ring.ahead) {
+            bail!("The spawn ring needs a positive size and a chance between 0 and 1");
+        }
+        for boss in &timeline.bosses {
+            if boss.health <= 0. || boss.scale <= 0. {
+                bail!("Boss at minute {} needs positive multipliers", boss.minute);
+            }
+        }
+        Ok(timeline)
+    }
+
+    /// The phase running at `minute`
+    pub fn phase_at(&self, minute: f32) -> Option<&WavePhase> {
+        self.phases
+            .iter()
+            .rev()
+            .find(|phase| phase.minute <= minute)
+    }
+}
+
+fn is_sorted(mut minutes: impl Iterator<Item = f32>) -> bool {
+    let Some(mut last) = minutes.next() else {
+        return true;
+    };
+    minutes.all(|minute| {
+        let sorted = last <= minute;
+        last = minute;
+        sorted
+    })
+}
+
+fn validate_mix(mix: &[(EnemyType, u32)]) -> anyhow::Result<()> {
+    if !mix.is_empty() && mix.iter().all(|(_, weight)| *weight == 0) {
+        bail!("No enemy of the mix has a weight");
+    }
+    Ok(())
+}
+
+/// Pick an enemy type of `mix` by weight; `None` if the mix is empty
+pub fn pick_from_mix(mix: &[(EnemyType, u32)], rng: &mut impl Rng) -> Option<EnemyType> {
+    weighted_pick(mix, |(_, weight)| *weight, rng).map(|(kind, _)| *kind)
+}
+
+#[derive(Default)]
+pub struct WaveTimelineLoader;
+
+impl AssetLoader for WaveTimelineLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let timeline = WaveTimeline::from_bytes(bytes)
+                .with_context(|| format!("Invalid wave timeline {:?}", load_context.path()))?;
+            load_context.set_default_asset(LoadedAsset::new(timeline));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["waves.ron"]
+    }
+}
+
+/// The loaded wave timelines; several maps may share one asset
+#[derive(Resource, Default)]
+pub struct WaveRegistry {
+    pub handles: HashMap<MapId, Handle<WaveTimeline>>,
+    timelines: HashMap<MapId, WaveTimeline>,
+}
+
+impl WaveRegistry {
+    /// Every map has its timeline
+    pub fn is_loaded(&self) -> bool {
+        MapId::iter().all(|map| self.timelines.contains_key(&map))
+    }
+
+    /// Take over `timeline` for every map that uses the asset behind `handle`
+    pub fn set_timeline(&mut self, handle: &Handle<WaveTimeline>, timeline: &WaveTimeline) {
+        for (map, map_handle) in &self.handles {
+            if map_handle == handle {
+                self.timelines.insert(*map, timeline.clone());
+            }
+        }
+    }
+
+    pub fn get(&self, map: MapId) -> Option<&WaveTimeline> {
+        self.timelines.get(&map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP_1: &str = include_str!("../../assets/data/waves/map_1.waves.ron");
+    const DEFAULT: &str = include_str!("../../assets/data/waves/default.waves.ron");
+
+    #[test]
+    fn shipped_timelines_are_valid() {
+        for timeline in [MAP_1, DEFAULT] {
+            WaveTimeline::from_bytes(timeline.as_bytes()).unwrap();
+        }
+    }
+
+    #[test]
+    fn phase_without_interval_is_rejected() {
+        let timeline = MAP_1.replacen("interval: 1.0, ", "", 1);
+        assert!(WaveTimeline::from_bytes(timeline.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn unsorted_bursts_are_rejected() {
+        let timeline = MAP_1.replacen("minute: 1.5, count: 12", "minute: 4.5, count: 12", 1);
+        let error = WaveTimeline::from_bytes(timeline.as_bytes()).unwrap_err();
+        assert!(error.to_string().contains("sorted by minute"));
+    }
+
+    #[test]
+    fn phase_lasts_until_the_next_one() {
+        let timeline = WaveTimeline::from_bytes(MAP_1.as_bytes()).unwrap();
+        let phase_minute = |minute| timeline.phase_at(minute).map(|phase| phase.minute);
+
+        assert_eq!(phase_minute(0.), Some(0.));
+        assert_eq!(phase_minute(0.49), Some(0.));
+        assert_eq!(phase_minute(0.5), Some(0.5));
+        assert_eq!(phase_minute(7.99), Some(5.));
+        assert_eq!(phase_minute(60.), Some(8.));
+        assert_eq!(phase_minute(-1.), None);
+    }
+}
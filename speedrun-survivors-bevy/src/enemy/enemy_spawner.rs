@@ -1,46 +1,93 @@
 use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI};
 use std::time::Duration;
 
-use bevy::{prelude::*, window::PrimaryWindow};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
 use rand::Rng;
 
+use crate::animation::{self, Animator};
+use crate::data::enemy::EnemyDefinition;
+use crate::data::wave::{pick_from_mix, BossSpawn, SpawnRing, WaveTimeline};
+use crate::data::GameData;
+use crate::enemy::enemy_type::EnemyType;
+use crate::enemy::Boss;
+use crate::menu::MenuGameConfig;
+use crate::player::Player;
 use crate::plugins::assets::GameAssets;
+use crate::plugins::game_rng::{GameRng, RngStream};
+use crate::plugins::gameplay_effects::{GameplayEffectPluginState, GameplayStat};
+use crate::plugins::health::Health;
+use crate::plugins::simulation::{Interpolated, SimulationSet, SIMULATED_VIEWPORT};
 use crate::plugins::status_effect::StatusEffectController;
 use crate::state::{for_game_states, AppState};
-use crate::{
-    animation::{self, Animator},
-    enemy::Enemy,
-};
-
-use super::enemy_type::EnemyType;
 
 pub struct SpawnEnemiesPlugin;
 
 impl Plugin for SpawnEnemiesPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(AppState::GameInitializing), on_enter_game_init)
-            .add_systems(
-                Update,
-                (update_spawning).run_if(in_state(AppState::GameRunning)),
-            )
-            .insert_resource(EnemySpawnerState::default());
+            .add_systems(FixedUpdate, update_spawning.in_set(SimulationSet::Movement))
+            .init_resource::<WaveDirectorState>();
     }
 }
 
-fn on_enter_game_init(mut spawner: ResMut<EnemySpawnerState>) {
-    *spawner = EnemySpawnerState::default();
+fn on_enter_game_init(mut director: ResMut<WaveDirectorState>) {
+    *director = WaveDirectorState::default();
 }
 
-#[derive(Resource)]
-pub struct EnemySpawnerState {
-    pub timer: Timer,
+/// Progress of the wave director through the timeline of the current map
+#[derive(Resource, Default)]
+pub struct WaveDirectorState {
+    /// Game time since the run started
+    pub elapsed: Duration,
+    /// Spawns owed by the current phase; one is due whenever this reaches 1
+    progress: f32,
+    next_burst: usize,
+    next_boss: usize,
 }
 
-impl Default for EnemySpawnerState {
-    fn default() -> Self {
-        Self {
-            timer: Timer::from_seconds(1., TimerMode::Repeating),
+impl WaveDirectorState {
+    fn minute(&self) -> f32 {
+        self.elapsed.as_secs_f32() / 60.
+    }
+
+    /// Move the timeline on by one tick; the enemies due, `None` picks by the bestiary weights
+    fn advance(
+        &mut self,
+        timeline: &WaveTimeline,
+        period: Duration,
+        spawn_rate: f32,
+        rng: &mut impl Rng,
+    ) -> Vec<Option<EnemyType>> {
+        self.elapsed += period;
+        let minute = self.minute();
+        let mut spawns = Vec::new();
+
+        // The current phase spawns at a steady rate
+        if let Some(phase) = timeline.phase_at(minute) {
+            self.progress += period.as_secs_f32() * spawn_rate / phase.interval;
+            while self.progress >= 1. {
+                self.progress -= 1.;
+                for _ in 0..phase.count {
+                    spawns.push(pick_from_mix(&phase.mix, rng));
+                }
+            }
         }
+
+        // Bursts happen once, when their minute is reached
+        while let Some(burst) = timeline
+            .bursts
+            .get(self.next_burst)
+            .filter(|burst| burst.minute <= minute)
+        {
+            self.next_burst += 1;
+            let count = (burst.count as f32 * spawn_rate).round() as u32;
+            for _ in 0..count {
+                spawns.push(pick_from_mix(&burst.mix, rng));
+            }
+        }
+        spawns
     }
 }
 
@@ -70,97 +117,292 @@ pub fn create_enemy_anim_hashmap(walk_frames: usize) -> HashMap<String, animatio
     return hash_map;
 }
 
+/// What the spawning is read from: the map, the game data and the effects on the spawn rate
+#[derive(SystemParam)]
+pub struct SpawnContext<'w> {
+    game_assets: Res<'w, GameAssets>,
+    game_config: Res<'w, MenuGameConfig>,
+    gameplay_state: Res<'w, GameplayEffectPluginState>,
+    game_data: GameData<'w>,
+}
+
+/// Spawn enemies as the timeline of the map says, faster with a higher `SpawnRate`
 pub fn update_spawning(
-    primary_query: Query<&Window, With<PrimaryWindow>>,
-    mut spawner: ResMut<EnemySpawnerState>,
-    time: Res<Time>,
+    mut director: ResMut<WaveDirectorState>,
+    time: Res<FixedTime>,
     mut commands: Commands,
-    game_assets: Res<GameAssets>,
+    context: SpawnContext,
+    mut game_rng: ResMut<GameRng>,
+    player_query: Query<(&Transform, &Player)>,
 ) {
-    spawner.timer.tick(time.delta());
-
-    if !spawner.timer.finished() {
+    let SpawnContext {
+        game_assets,
+        game_config,
+        gameplay_state,
+        game_data,
+    } = context;
+    let Some(timeline) = game_data.waves.get(game_config.map) else {
         return;
-    }
-
-    let Ok(primary) = primary_query.get_single() else {
+    };
+    let Ok((player_transform, player)) = player_query.get_single() else {
         return;
     };
 
-    // Reduce timer period for every monster spawned
-    // TODO find good logic for this, this is very self limiting xD
-    //  should also use GameplayEffectPluginState spawn rate modifiers
-    let current_duration = spawner.timer.duration().clone();
-    if current_duration > Duration::from_millis(500) {
-        spawner
-            .timer
-            .set_duration(current_duration - Duration::from_millis(10));
-    } else if current_duration > Duration::from_millis(120) {
-        spawner
-            .timer
-            .set_duration(current_duration - Duration::from_millis(1));
+    // Runs started without a map have no spawn rate, they follow the timeline as is
+    let spawn_rate = match gameplay_state
+        .player_effects
+        .get_stat(GameplayStat::SpawnRate)
+    {
+        rate if rate > 0. => rate as f32,
+        _ => 1.,
+    };
+
+    let rng = game_rng.stream(RngStream::Spawning);
+    let spawns = director.advance(timeline, time.period, spawn_rate, rng);
+    let minute = director.minute();
+
+    // The viewport follows the player; the camera lags behind, but the simulation must not depend on it
+    let center = player_transform.translation.truncate();
+    let area = game_assets.map.0.get_playable_area();
+    for kind in spawns {
+        // An empty mix falls back to the spawn weights of the bestiary
+        let archetype = match kind {
+            Some(kind) => game_data.enemies.get(kind),
+            None => game_data.enemies.random(rng),
+        };
+        let Some(archetype) = archetype else {
+            continue;
+        };
+        // Only a map that fits on screen has no room for the ring
+        let Some(translation) = ring_position(rng, &timeline.ring, center, player.heading, area)
+        else {
+            continue;
+        };
+        spawn_enemy(&mut commands, &game_assets, archetype, translation, None);
     }
 
-    // eprintln!("SPAWN TIMER NOW: {current_duration:?}");
-
-    let mut rng = rand::thread_rng();
-
-    let enemy_type = EnemyType::random();
-    let texture_atlas_handle = game_assets.enemies.get(&enemy_type).unwrap().clone();
-    let mut spawn_transform = Transform::from_scale(enemy_type.scale());
-
-    if rng.gen_range(0..2) == 1 {
-        if rng.gen_range(0..2) == 1 {
-            spawn_transform.translation = Vec3::new(
-                primary.width() / 2.,
-                rng.gen_range(-primary.height() / 2.0..primary.height() / 2.0),
-                0.,
-            );
-        } else {
-            spawn_transform.translation = Vec3::new(
-                -primary.width() / 2.,
-                rng.gen_range(-primary.height() / 2.0..primary.height() / 2.0),
-                0.,
-            );
-        }
+    // A boss must not be missed, it waits for a tick with room in the ring
+    while let Some(boss) = timeline
+        .bosses
+        .get(director.next_boss)
+        .filter(|boss| boss.minute <= minute)
+    {
+        let Some(archetype) = game_data.enemies.get(boss.kind) else {
+            director.next_boss += 1;
+            continue;
+        };
+        let Some(translation) = ring_position(rng, &timeline.ring, center, player.heading, area)
+        else {
+            break;
+        };
+        director.next_boss += 1;
+        spawn_enemy(
+            &mut commands,
+            &game_assets,
+            archetype,
+            translation,
+            Some(boss),
+        );
+    }
+}
+
+/// A random point in the spawn ring, inside the walls of the map
+/// The ring is laid around `SIMULATED_VIEWPORT` rather than the projection of the camera: the
+/// camera never shows more than it, and replays are verified without a camera at all
+/// A point behind the walls is clamped to the closest spot inside that is still off screen;
+/// `None` only if the walls leave no such spot
+fn ring_position(
+    rng: &mut impl Rng,
+    ring: &SpawnRing,
+    center: Vec2,
+    heading: Vec2,
+    area: Rect,
+) -> Option<Vec3> {
+    let angle = if heading != Vec2::ZERO && rng.gen_bool(ring.ahead as f64) {
+        heading.y.atan2(heading.x) + rng.gen_range(-FRAC_PI_2..FRAC_PI_2)
     } else {
-        if rng.gen_range(0..2) == 1 {
-            spawn_transform.translation = Vec3::new(
-                rng.gen_range(-primary.width() / 2.0..primary.width() / 2.0),
-                primary.height() / 2.,
-                0.,
-            );
-        } else {
-            spawn_transform.translation = Vec3::new(
-                rng.gen_range(-primary.width() / 2.0..primary.width() / 2.0),
-                -primary.height() / 2.,
-                0.,
-            );
-        }
+        rng.gen_range(-PI..PI)
+    };
+    let direction = Vec2::from_angle(angle);
+
+    // Distance from the center to the viewport edge in this direction
+    let half_viewport = SIMULATED_VIEWPORT / 2.;
+    let to_edge = (half_viewport.x / direction.x.abs()).min(half_viewport.y / direction.y.abs());
+    let distance = to_edge + ring.margin + rng.gen_range(0.0..=ring.width);
+    let point = (center + direction * distance).clamp(area.min, area.max);
+
+    let viewport = Rect::from_center_size(center, SIMULATED_VIEWPORT);
+    if !viewport.contains(point) {
+        return Some(point.extend(0.));
     }
 
-    commands
-        .spawn((
-            SpriteSheetBundle {
-                texture_atlas: texture_atlas_handle,
-                transform: spawn_transform,
-                ..default()
-            },
-            for_game_states(),
-        ))
+    // The walls pulled it on screen; move it past the closest viewport edge that has room
+    let outside = viewport.inset(ring.margin);
+    [
+        Vec2::new(outside.min.x, point.y),
+        Vec2::new(outside.max.x, point.y),
+        Vec2::new(point.x, outside.min.y),
+        Vec2::new(point.x, outside.max.y),
+    ]
+    .into_iter()
+    .filter(|spot| area.contains(*spot))
+    .min_by(|a, b| {
+        a.distance_squared(point)
+            .total_cmp(&b.distance_squared(point))
+    })
+    .map(|spot| spot.extend(0.))
+}
+
+fn spawn_enemy(
+    commands: &mut Commands,
+    game_assets: &GameAssets,
+    archetype: &EnemyDefinition,
+    translation: Vec3,
+    boss: Option<&BossSpawn>,
+) {
+    let Some(texture_atlas_handle) = game_assets.enemies.get(&archetype.kind).cloned() else {
+        return;
+    };
+
+    let mut spawn_transform = Transform::from_translation(translation);
+    spawn_transform.scale = archetype.scale() * boss.map_or(1., |boss| boss.scale);
+    let health = archetype.health * boss.map_or(1., |boss| boss.health);
+
+    let mut enemy = commands.spawn((
+        SpriteSheetBundle {
+            texture_atlas: texture_atlas_handle,
+            transform: spawn_transform,
+            ..default()
+        },
+        for_game_states(),
+    ));
+    enemy
         .insert(Animator {
-            animation_bank: create_enemy_anim_hashmap(enemy_type.frames()),
+            animation_bank: create_enemy_anim_hashmap(archetype.atlas.columns),
             timer: 0.,
             cooldown: 0.05,
             last_animation: "Walk".to_string(),
             current_animation: "Walk".to_string(),
             destroy_on_end: false,
         })
-        .insert(Enemy {
-            speed: 100.,
-            attack: 1.,
-            kind: enemy_type,
-        })
+        .insert(archetype.enemy())
         .insert(StatusEffectController { effects: vec![] })
-        .insert(enemy_type.health());
+        .insert(Interpolated::default())
+        .insert(Health::new(health, health, 0., None));
+    if let Some(boss) = boss {
+        enemy.insert(Boss {
+            rewards: boss.rewards,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::map::MapId;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use speedrun_survivors_replay::TICKS_PER_SECOND;
+
+    #[test]
+    fn ring_is_off_screen_and_inside_the_walls() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let ring = SpawnRing::default();
+        // The playable area of this map is exactly as wide as the viewport
+        let area = MapId::Map1.get_playable_area();
+
+        for center in [Vec2::ZERO, Vec2::new(0., 600.), area.max - 100.] {
+            let viewport = Rect::from_center_size(center, SIMULATED_VIEWPORT);
+            for heading in [Vec2::X, Vec2::NEG_Y, Vec2::ZERO] {
+                for _ in 0..500 {
+                    let point = ring_position(&mut rng, &ring, center, heading, area)
+                        .unwrap()
+                        .truncate();
+                    assert!(area.contains(point), "{point} is behind the walls");
+                    assert!(
+                        !viewport.contains(point),
+                        "{point} is on screen at {center}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn enemies_mostly_come_from_ahead() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let ring = SpawnRing::default();
+        let area = Rect::from_center_size(Vec2::ZERO, Vec2::splat(10_000.));
+
+        let ahead = (0..1000)
+            .filter_map(|_| ring_position(&mut rng, &ring, Vec2::ZERO, Vec2::Y, area))
+            .filter(|point| point.y > 0.)
+            .count();
+        // Ahead by chance, or by the half of the remaining spawns that land there anyway
+        let expected = (ring.ahead + (1. - ring.ahead) / 2.) * 1000.;
+        assert!(
+            (ahead as f32 - expected).abs() < 50.,
+            "{ahead} of 1000 ahead"
+        );
+    }
+
+    /// Spawns of the shipped timeline of the first map until `minute`, one list per tick
+    fn director_spawns(spawn_rate: f32, minute: u64) -> Vec<Vec<Option<EnemyType>>> {
+        let timeline =
+            WaveTimeline::from_bytes(include_bytes!("../../assets/data/waves/map_1.waves.ron"))
+                .unwrap();
+        let mut director = WaveDirectorState::default();
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let period = Duration::from_secs(1) / TICKS_PER_SECOND;
+
+        (0..minute * 60 * TICKS_PER_SECOND as u64)
+            .map(|_| director.advance(&timeline, period, spawn_rate, &mut rng))
+            .collect()
+    }
+
+    fn largest_tick(ticks: &[Vec<Option<EnemyType>>]) -> usize {
+        (0..ticks.len())
+            .max_by_key(|tick| ticks[*tick].len())
+            .unwrap()
+    }
+
+    #[test]
+    fn director_follows_the_timeline() {
+        let ticks = director_spawns(1., 2);
+        // One spider a second for the first half minute
+        let first: Vec<_> = ticks[..30 * TICKS_PER_SECOND as usize].concat();
+        assert!((29..=30).contains(&first.len()), "{}", first.len());
+        assert!(first.iter().all(|kind| *kind == Some(EnemyType::Spider)));
+
+        // The burst of 12 slimes comes once minute 1.5 is reached
+        let burst = largest_tick(&ticks);
+        assert!(burst >= 90 * TICKS_PER_SECOND as usize - 1);
+        let slimes = ticks[burst]
+            .iter()
+            .filter(|kind| **kind == Some(EnemyType::Slime))
+            .count();
+        assert!(slimes >= 12);
+    }
+
+    #[test]
+    fn spawn_rate_scales_phases_and_bursts() {
+        let normal: usize = director_spawns(1., 2).iter().map(Vec::len).sum();
+        let doubled: usize = director_spawns(2., 2).iter().map(Vec::len).sum();
+        assert!(
+            doubled.abs_diff(normal * 2) <= 2,
+            "{doubled} spawns at twice the rate of {normal}"
+        );
+
+        let ticks = director_spawns(2., 2);
+        assert!((24..=26).contains(&ticks[largest_tick(&ticks)].len()));
+    }
+
+    #[test]
+    fn map_on_screen_has_no_ring() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let area = Rect::from_center_size(Vec2::ZERO, SIMULATED_VIEWPORT / 2.);
+
+        let point = ring_position(&mut rng, &SpawnRing::default(), Vec2::ZERO, Vec2::X, area);
+        assert_eq!(point, None);
+    }
 }
@@ -1,9 +1,10 @@
+use crate::data::enemy::EnemyRegistry;
 use crate::enemy::enemy_type::EnemyType;
 use crate::passives::rug_pull::RugPulled;
-use crate::player::Player;
+use crate::player::{Player, PlayerState};
 use crate::plugins::health::{self, Health};
 use crate::plugins::pickup::PickupEvent;
-use crate::state::AppState;
+use crate::plugins::simulation::{SimulationEventApp, SimulationSet};
 use bevy::prelude::*;
 
 pub mod enemy_spawner;
@@ -14,11 +15,14 @@ pub struct EnemyPlugin;
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
-            Update,
-            (process_events, move_enemies, update_enemy_hits)
-                .run_if(in_state(AppState::GameRunning)),
+            FixedUpdate,
+            (
+                move_enemies.in_set(SimulationSet::Movement),
+                update_enemy_hits.in_set(SimulationSet::Attacks),
+                process_events.in_set(SimulationSet::Death),
+            ),
         )
-        .add_event::<EnemyEvent>();
+        .add_simulation_event::<EnemyEvent>();
     }
 }
 
@@ -29,6 +33,13 @@ pub struct Enemy {
     pub kind: EnemyType,
 }
 
+/// Spawned by the wave director at a set time
+#[derive(Component)]
+pub struct Boss {
+    /// Multiplies the drops of the archetype
+    pub rewards: u64,
+}
+
 #[derive(Debug, Event)]
 pub enum EnemyEvent {
     Spawned(EnemyType),
@@ -41,20 +52,24 @@ pub fn process_events(
     mut commands: Commands,
     mut rx_enemy: EventReader<EnemyEvent>,
     mut tx_pickup: EventWriter<PickupEvent>,
-    query_tf: Query<&Transform, With<Enemy>>,
+    query_tf: Query<(&Transform, Option<&Boss>), With<Enemy>>,
+    enemies: Res<EnemyRegistry>,
 ) {
     for ev in rx_enemy.iter() {
         match ev {
             EnemyEvent::Died(entity, kind) => {
-                let Ok(tf) = query_tf.get(*entity) else {
+                let Ok((tf, boss)) = query_tf.get(*entity) else {
                     continue;
                 };
 
-                tx_pickup.send(PickupEvent::new(
-                    kind.get_coin_reward(),
-                    kind.get_exp_reward(),
-                    tf.translation,
-                ));
+                if let Some(archetype) = enemies.get(*kind) {
+                    let rewards = boss.map_or(1, |boss| boss.rewards);
+                    tx_pickup.send(PickupEvent::new(
+                        archetype.coins * rewards,
+                        archetype.exp * rewards,
+                        tf.translation,
+                    ));
+                }
                 commands
                     .get_entity(*entity)
                     .and_then(|entity| Some(entity.despawn_recursive()));
@@ -67,7 +82,7 @@ pub fn process_events(
 }
 
 pub fn move_enemies(
-    time: Res<Time>,
+    time: Res<FixedTime>,
     mut enemy_query: Query<
         (&Enemy, &mut Transform, Entity, &Health),
         (Without<Player>, Without<RugPulled>),
@@ -81,7 +96,7 @@ pub fn move_enemies(
     for (enemy, mut transform, entity, health) in enemy_query.iter_mut() {
         let moving = Vec3::normalize(player_transform.translation - transform.translation)
             * enemy.speed
-            * time.delta_seconds();
+            * time.period.as_secs_f32();
 
         transform.translation += moving;
         transform.scale.x = moving.x.signum() * -f32::abs(transform.scale.x);
@@ -92,12 +107,14 @@ pub struct EnemyInfo {
     pub translation: Vec2,
     pub entity: Entity,
     pub attack: f32,
+    pub kind: EnemyType,
 }
 
 pub fn update_enemy_hits(
     enemy_query: Query<(&Transform, Entity, &Enemy), (With<Enemy>, Without<Player>)>,
     mut player_query: Query<(&mut Transform, &mut Health, Entity), Without<Enemy>>,
     mut tx_health: EventWriter<health::HealthUpdateEvent>,
+    mut player_state: ResMut<PlayerState>,
 ) {
     let mut enemy_list = Vec::new();
     for (transform, entity, enemy) in enemy_query.iter() {
@@ -105,6 +122,7 @@ pub fn update_enemy_hits(
             translation: Vec2::new(transform.translation.x, transform.translation.y),
             entity,
             attack: enemy.attack,
+            kind: enemy.kind,
         });
     }
 
@@ -116,6 +134,7 @@ pub fn update_enemy_hits(
                     health_change: -enemy.attack,
                     target_type: health::TargetType::Player,
                 });
+                player_state.last_hit_by = Some(enemy.kind);
             }
         }
     }
@@ -0,0 +1,43 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+pub mod actives;
+pub mod animation;
+pub mod data;
+pub mod enemy;
+pub mod menu;
+pub mod online;
+pub mod passives;
+pub mod player;
+pub mod plugins;
+pub mod replay;
+pub mod state;
+pub mod wallet;
+pub mod weapon;
+
+pub const GAME_NAME: &str = "Speedrun Survivors";
+
+pub const COLOR_SOL_SURGE: Color = Color::rgb(0.0, 1.0, 0.63921568627);
+pub const COLOR_SOL_OCEAN: Color = Color::rgb(0.01176470588, 0.88235294118, 1.0);
+pub const COLOR_SOL_DINO: Color = Color::rgb(0.86274509804, 0.12156862745, 1.0);
+
+#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect)]
+pub enum GameAction {
+    MoveUp,
+    MoveLeft,
+    MoveDown,
+    MoveRight,
+    Slot1,
+    Slot2,
+    Slot3,
+    Slot4,
+    Slot5,
+    Slot6,
+    Action1,
+    Action2,
+    Action3,
+    Cancel,
+    Confirm,
+    Pause,
+    HackLevelUp,
+}
@@ -1,69 +1,39 @@
-use crate::enemy::enemy_spawner::SpawnEnemiesPlugin;
-use crate::enemy::EnemyPlugin;
-use crate::menu::MenuPlugin;
-use crate::passives::orca_chopper::OrcaChopperPlugin;
-use crate::passives::rug_pull::RugPullPlugin;
-use crate::passives::shitcoin::ShitcoinClusterPlugin;
-use crate::passives::whale::WhaleDumpPlugin;
-use crate::player::PlayerPlugin;
-use crate::plugins::assets::AssetsPlugin;
-use crate::plugins::camera_shake::CameraShakePlugin;
-use crate::plugins::coin_rewards::CoinRewardsPlugin;
-use crate::plugins::gameplay_effects::GameplayEffectsPlugin;
-use crate::plugins::hud::HudPlugin;
-use crate::plugins::pickup::PickupPlugin;
-use crate::plugins::sfx_manager::SFXManagerPlugin;
-use crate::plugins::vfx_manager::VFXManagerPlugin;
-use crate::state::{for_game_states, AppState, StatesPlugin};
-use actives::dash::DashPlugin;
 use bevy::audio::VolumeLevel;
 use bevy::prelude::*;
+use bevy::render::camera::ScalingMode;
 use bevy_ecs_ldtk::prelude::*;
 use leafwing_input_manager::prelude::*;
 use leafwing_input_manager::user_input::InputKind;
-use plugins::assets::GameAssets;
-use plugins::combat_text::CombatTextPlugin;
-use plugins::health::HealthPlugin;
-use plugins::status_effect::StatusEffectPlugin;
-use weapon::WeaponPlugin;
-
-mod actives;
-mod animation;
-mod data;
-mod enemy;
-mod menu;
-mod passives;
-mod player;
-mod plugins;
-mod state;
-mod weapon;
-
-const GAME_NAME: &str = "Speedrun Survivors";
-
-const COLOR_SOL_SURGE: Color = Color::rgb(0.0, 1.0, 0.63921568627);
-const COLOR_SOL_OCEAN: Color = Color::rgb(0.01176470588, 0.88235294118, 1.0);
-const COLOR_SOL_DINO: Color = Color::rgb(0.86274509804, 0.12156862745, 1.0);
-
-#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect)]
-enum GameAction {
-    MoveUp,
-    MoveLeft,
-    MoveDown,
-    MoveRight,
-    Slot1,
-    Slot2,
-    Slot3,
-    Slot4,
-    Slot5,
-    Slot6,
-    Action1,
-    Action2,
-    Action3,
-    Cancel,
-    Confirm,
-    Pause,
-    HackLevelUp,
-}
+use speedrun_survivors_bevy::actives::dash::DashPlugin;
+use speedrun_survivors_bevy::enemy::enemy_spawner::SpawnEnemiesPlugin;
+use speedrun_survivors_bevy::enemy::EnemyPlugin;
+use speedrun_survivors_bevy::menu::MenuPlugin;
+use speedrun_survivors_bevy::online::OnlineSessionPlugin;
+use speedrun_survivors_bevy::passives::orca_chopper::OrcaChopperPlugin;
+use speedrun_survivors_bevy::passives::rug_pull::RugPullPlugin;
+use speedrun_survivors_bevy::passives::shitcoin::ShitcoinClusterPlugin;
+use speedrun_survivors_bevy::passives::whale::WhaleDumpPlugin;
+use speedrun_survivors_bevy::player::PlayerPlugin;
+use speedrun_survivors_bevy::plugins::assets::AssetsPlugin;
+use speedrun_survivors_bevy::plugins::assets::GameAssets;
+use speedrun_survivors_bevy::plugins::camera_shake::CameraShakePlugin;
+use speedrun_survivors_bevy::plugins::coin_rewards::CoinRewardsPlugin;
+use speedrun_survivors_bevy::plugins::combat_text::CombatTextPlugin;
+use speedrun_survivors_bevy::plugins::game_rng::GameRngPlugin;
+use speedrun_survivors_bevy::plugins::gameplay_effects::GameplayEffectsPlugin;
+use speedrun_survivors_bevy::plugins::health::HealthPlugin;
+use speedrun_survivors_bevy::plugins::hud::HudPlugin;
+use speedrun_survivors_bevy::plugins::pickup::PickupPlugin;
+use speedrun_survivors_bevy::plugins::sfx_manager::SFXManagerPlugin;
+use speedrun_survivors_bevy::plugins::simulation::{SimulationPlugin, SIMULATED_VIEWPORT};
+use speedrun_survivors_bevy::plugins::status_effect::StatusEffectPlugin;
+use speedrun_survivors_bevy::plugins::vfx_manager::VFXManagerPlugin;
+use speedrun_survivors_bevy::replay::playback::ReplayPlaybackPlugin;
+use speedrun_survivors_bevy::replay::recorder::ReplayRecorderPlugin;
+use speedrun_survivors_bevy::state::{for_game_states, AppState, StatesPlugin};
+use speedrun_survivors_bevy::wallet::WalletPlugin;
+use speedrun_survivors_bevy::weapon::WeaponPlugin;
+use speedrun_survivors_bevy::{animation, GameAction, GAME_NAME};
 
 fn main() {
     App::new()
@@ -100,6 +70,8 @@ fn main() {
             StatusEffectPlugin,
         ))
         .add_plugins((
+            SimulationPlugin,
+            GameRngPlugin,
             GameplayEffectsPlugin,
             OrcaChopperPlugin,
             WhaleDumpPlugin,
@@ -107,6 +79,10 @@ fn main() {
             RugPullPlugin,
             PickupPlugin,
             VFXManagerPlugin,
+            ReplayRecorderPlugin,
+            ReplayPlaybackPlugin,
+            WalletPlugin,
+            OnlineSessionPlugin,
         ))
         .add_systems(Startup, (setup_camera, setup_key_bindings))
         .add_systems(
@@ -152,7 +128,17 @@ fn spawn_ldtk_map(game_assets: Res<GameAssets>, mut commands: Commands) {
 }
 
 fn setup_camera(mut commands: Commands) {
-    commands.spawn(Camera2dBundle::default());
+    // Show no more than the viewport the simulation assumes, or enemies would be seen spawning
+    commands.spawn(Camera2dBundle {
+        projection: OrthographicProjection {
+            scaling_mode: ScalingMode::AutoMax {
+                max_width: SIMULATED_VIEWPORT.x,
+                max_height: SIMULATED_VIEWPORT.y,
+            },
+            ..Camera2dBundle::default().projection
+        },
+        ..default()
+    });
     // commands.spawn((
     //     Camera2dBundle {
     //         camera: Camera {
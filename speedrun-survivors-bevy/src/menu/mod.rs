@@ -3,9 +3,10 @@ mod level_up;
 mod pause;
 mod splash_screen;
 
-use crate::data::hero::HeroType;
+use crate::data::hero::HeroRegistry;
 use crate::data::item::ItemType;
 use crate::data::map::MapId;
+use crate::data::GameData;
 use crate::menu::game_over::menu_game_over;
 use crate::menu::level_up::{menu_level_up, on_level_up_menu_button_action};
 use crate::menu::pause::menu_pause;
@@ -13,6 +14,7 @@ use crate::menu::splash_screen::menu_splash_screen;
 use crate::plugins::assets::UiAssets;
 use crate::plugins::gameplay_effects::GameplayEffectEvent;
 use crate::state::{AppState, ForState};
+use crate::wallet::Wallet;
 use crate::GameAction;
 use bevy::a11y::accesskit::{NodeBuilder, Role};
 use bevy::a11y::AccessibilityNode;
@@ -32,6 +34,10 @@ const MAX_NUM_NFT: usize = 6;
 #[derive(Component)]
 pub struct DrawBlinkTimer(pub Timer);
 
+/// The player wants to start a run; the online session decides when it begins
+#[derive(Event)]
+pub struct PlayRequested;
+
 pub struct MenuPlugin;
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
@@ -63,15 +69,19 @@ impl Plugin for MenuPlugin {
                 Update,
                 on_level_up_menu_button_action.run_if(in_state(AppState::GameLevelUp)),
             )
+            .add_event::<PlayRequested>()
             .insert_resource(MenuGameConfig::default());
     }
 }
 
 #[derive(Resource, Debug, Default)]
 pub struct MenuGameConfig {
-    pub hero: HeroType,
+    /// Id of the hero in the roster
+    pub hero: String,
     pub map: MapId,
     pub nft_list: Vec<String>,
+    /// Seeds the RNG of the next run; a random one is picked when starting without
+    pub entropy: Option<String>,
 }
 
 #[derive(Component)]
@@ -90,7 +100,7 @@ impl CheckBox {
 
 #[derive(Component)]
 struct HeroSelectButton {
-    hero_type: HeroType,
+    hero_id: String,
 }
 
 #[derive(Component)]
@@ -115,7 +125,7 @@ fn on_main_menu_button_action(
         ),
         (Changed<Interaction>, Without<SelectedElement>),
     >,
-    mut next_state: ResMut<NextState<AppState>>,
+    mut tx_play: EventWriter<PlayRequested>,
     mut tx_exit: EventWriter<AppExit>,
     mut state: ResMut<MenuGameConfig>,
     mut selected_hero: Query<
@@ -140,7 +150,7 @@ fn on_main_menu_button_action(
     for (interaction, mut action) in query_action_button.iter_mut() {
         match *interaction {
             Interaction::Pressed => match *action {
-                MenuButtonAction::Play => next_state.set(AppState::GameInitializing),
+                MenuButtonAction::Play => tx_play.send(PlayRequested),
                 MenuButtonAction::Quit => tx_exit.send(AppExit),
             },
             _ => {}
@@ -164,8 +174,8 @@ fn on_main_menu_button_action(
                         border.0 = BTN_BORDER_DEFAULT;
                         commands.entity(entity).remove::<SelectedElement>();
                     }
-                    state.hero = hero.hero_type.clone();
-                    tx_gameplay.send(GameplayEffectEvent::HeroSelected(hero.hero_type.clone()));
+                    state.hero = hero.hero_id.clone();
+                    tx_gameplay.send(GameplayEffectEvent::HeroSelected(hero.hero_id.clone()));
                     commands.entity(entity).insert(SelectedElement {});
                 }
                 if let Some(mut checkbox) = checkbox {
@@ -181,7 +191,7 @@ fn on_main_menu_button_action(
                         if checkbox.checked {
                             tx_gameplay.send(GameplayEffectEvent::NFTEquipped(
                                 checkbox.nft_id.clone(),
-                                ItemType::BonkInuBattleBracers, //TODO depends on NFT
+                                ItemType::for_nft(&checkbox.nft_id),
                             ));
                             state.nft_list.push(checkbox.nft_id.clone());
                         } else {
@@ -223,10 +233,15 @@ struct ScrollingList {
 fn menu_game_create(
     mut commands: Commands,
     assets: Res<UiAssets>,
+    wallet: Res<Wallet>,
+    heroes: Res<HeroRegistry>,
     mut state: ResMut<MenuGameConfig>,
 ) {
     // Reset state
-    state.hero = HeroType::Pepe;
+    state.hero = heroes
+        .default_hero()
+        .map(|hero| hero.id.clone())
+        .unwrap_or_default();
     state.map = MapId::Map1;
     state.nft_list = vec![];
 
@@ -259,7 +274,7 @@ fn menu_game_create(
                     },
                     ..Default::default()
                 })
-                .with_children(|parent| wrapper_content(parent, &assets));
+                .with_children(|parent| wrapper_content(parent, &assets, &heroes));
 
             // Wrapper for the footer
             parent
@@ -273,7 +288,7 @@ fn menu_game_create(
                     background_color: Color::OLIVE.into(),
                     ..Default::default()
                 })
-                .with_children(|parent| wrapper_footer(parent, &assets));
+                .with_children(|parent| wrapper_footer(parent, &assets, wallet.pubkey()));
         });
 }
 
@@ -298,7 +313,7 @@ fn get_equipped_nfts(nft_list: &Query<&CheckBox>) -> Vec<String> {
 }
 
 /// Wrapper for the game menu content, this is split into two sides, on the left the hero and map are selected and on the right the NFTs can be equipped
-fn wrapper_content(parent: &mut ChildBuilder, assets: &UiAssets) {
+fn wrapper_content(parent: &mut ChildBuilder, assets: &UiAssets, heroes: &HeroRegistry) {
     // Wrapper for the left side
     parent
         .spawn(NodeBundle {
@@ -313,7 +328,7 @@ fn wrapper_content(parent: &mut ChildBuilder, assets: &UiAssets) {
         })
         .with_children(|parent| {
             // Wrapper for hero selection
-            wrapper_hero_selector(parent, assets);
+            wrapper_hero_selector(parent, assets, heroes);
 
             // Wrapper for map selection
             wrapper_map_selector(parent, assets);
@@ -338,7 +353,7 @@ fn wrapper_content(parent: &mut ChildBuilder, assets: &UiAssets) {
 }
 
 /// This section is about choosing a hero
-fn wrapper_hero_selector(parent: &mut ChildBuilder, assets: &UiAssets) {
+fn wrapper_hero_selector(parent: &mut ChildBuilder, assets: &UiAssets, heroes: &HeroRegistry) {
     parent
         .spawn(NodeBundle {
             style: Style {
@@ -379,12 +394,14 @@ fn wrapper_hero_selector(parent: &mut ChildBuilder, assets: &UiAssets) {
                     ..Default::default()
                 })
                 .with_children(|parent| {
-                    for hero in HeroType::iter() {
-                        let ui_img = assets.heroes.get(&hero).unwrap();
+                    for hero in heroes.iter() {
+                        let ui_img = assets.heroes.get(&hero.id).cloned().unwrap_or_default();
                         spawn_bordered_button_with_bundle(
                             parent,
-                            ui_img.clone(),
-                            HeroSelectButton { hero_type: hero },
+                            ui_img,
+                            HeroSelectButton {
+                                hero_id: hero.id.clone(),
+                            },
                         );
                     }
                 });
@@ -643,8 +660,8 @@ fn list_item_selectable(parent: &mut ChildBuilder, assets: &UiAssets, id: &str,
         });
 }
 
-/// This wrapper contains the quit and play buttons
-fn wrapper_footer(parent: &mut ChildBuilder, assets: &UiAssets) {
+/// This wrapper contains the quit and play buttons and shows the wallet in between
+fn wrapper_footer(parent: &mut ChildBuilder, assets: &UiAssets, pubkey: Option<String>) {
     // Common style for all buttons on the screen
     let button_style = Style {
         width: Val::Px(250.0),
@@ -672,7 +689,7 @@ fn wrapper_footer(parent: &mut ChildBuilder, assets: &UiAssets) {
     parent
         .spawn(NodeBundle {
             style: Style {
-                width: Val::Percent(50.),
+                width: Val::Percent(30.),
                 height: Val::Percent(100.),
                 ..Default::default()
             },
@@ -699,10 +716,37 @@ fn wrapper_footer(parent: &mut ChildBuilder, assets: &UiAssets) {
                 });
         });
 
+    // The pubkey the runs are signed with
     parent
         .spawn(NodeBundle {
             style: Style {
-                width: Val::Percent(50.),
+                width: Val::Percent(40.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            let text = match pubkey {
+                Some(pubkey) => format!("Wallet: {pubkey}"),
+                None => "No wallet - playing offline".to_string(),
+            };
+            parent.spawn(TextBundle::from_section(
+                text,
+                TextStyle {
+                    font: assets.font_primary.clone(),
+                    font_size: 20.0,
+                    color: TEXT_COLOR,
+                },
+            ));
+        });
+
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(30.),
                 height: Val::Percent(100.),
                 justify_content: JustifyContent::End,
                 ..Default::default()
@@ -810,7 +854,9 @@ fn menu_input_system(
     state: ResMut<State<AppState>>,
     mut next_state: ResMut<NextState<AppState>>,
     mut tx_exit: EventWriter<AppExit>,
+    mut tx_play: EventWriter<PlayRequested>,
     actions: Query<&ActionState<GameAction>>,
+    game_data: GameData,
 ) {
     let action = actions.single();
 
@@ -819,7 +865,8 @@ fn menu_input_system(
     } else {
         match state.get() {
             AppState::SplashScreen => {
-                if action.just_pressed(GameAction::Confirm) {
+                // The menu lists the heroes and a run spawns enemies, so all must be loaded first
+                if action.just_pressed(GameAction::Confirm) && game_data.is_loaded() {
                     next_state.set(AppState::GameMenuMain);
                 }
                 if action.just_pressed(GameAction::Cancel) {
@@ -828,7 +875,7 @@ fn menu_input_system(
             }
             AppState::GameMenuMain => {
                 if action.just_pressed(GameAction::Confirm) {
-                    next_state.set(AppState::GameInitializing);
+                    tx_play.send(PlayRequested);
                 }
             }
             AppState::GameInitializing => {
@@ -838,7 +885,8 @@ fn menu_input_system(
                 if action.just_pressed(GameAction::Pause) {
                     next_state.set(AppState::GamePaused);
                 }
-                if action.just_pressed(GameAction::HackLevelUp) {
+                // Cheat for testing; the simulator knows nothing of it, so a recorded run would not verify
+                if cfg!(feature = "dev") && action.just_pressed(GameAction::HackLevelUp) {
                     next_state.set(AppState::GameLevelUp);
                 }
             }
@@ -0,0 +1,188 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use speedrun_survivors_model::*;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Most calls are answered right away
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// `game_complete` is only answered once the server re-simulated the whole run, which it may have to
+/// queue first; this outlasts the default simulator timeout of the server
+const GAME_COMPLETE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Blocking client of the game API; only call it from a task, never from a system
+#[derive(Clone)]
+pub struct ApiClient {
+    base_url: String,
+    /// Shared by every clone, so the version is only asked for once
+    version_checked: Arc<AtomicBool>,
+    #[cfg(not(target_arch = "wasm32"))]
+    agent: ureq::Agent,
+}
+
+#[derive(Debug)]
+pub enum ApiCallError {
+    /// The server could not be reached, was rate limiting or failed; the call can be retried
+    Transient(String),
+    /// The server refused the request; retrying it will not help
+    Rejected(ApiErrorBody),
+    /// The wallet did not sign a message
+    Signer(String),
+    /// The server speaks another `API_VERSION`; nothing will succeed until the game is updated
+    Incompatible(u32),
+}
+
+impl fmt::Display for ApiCallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiCallError::Transient(reason) => write!(f, "{reason}"),
+            ApiCallError::Rejected(body) => write!(f, "{} ({})", body.message, body.error),
+            ApiCallError::Signer(reason) => write!(f, "Signing failed: {reason}"),
+            ApiCallError::Incompatible(version) => write!(
+                f,
+                "The server speaks API version {version}, this game version {API_VERSION}"
+            ),
+        }
+    }
+}
+
+impl ApiCallError {
+    /// The server refused the request with the given error code
+    pub fn is_rejected_with(&self, code: ApiErrorCode) -> bool {
+        matches!(self, ApiCallError::Rejected(body) if body.error == code.as_str())
+    }
+}
+
+impl ApiClient {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            version_checked: Arc::new(AtomicBool::new(false)),
+            #[cfg(not(target_arch = "wasm32"))]
+            agent: ureq::AgentBuilder::new().build(),
+        }
+    }
+
+    /// Make sure the server speaks the `API_VERSION` of the game; asks only until it did once
+    pub fn check_version(&self) -> Result<(), ApiCallError> {
+        if self.version_checked.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let response: VersionResponse = self
+            .get("/", REQUEST_TIMEOUT)
+            .and_then(|response| parse_json(&response))?;
+        if response.api_version != API_VERSION {
+            return Err(ApiCallError::Incompatible(response.api_version));
+        }
+        self.version_checked.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn session_get(&self, req: &SessionGetRequest) -> Result<SessionGetResponse, ApiCallError> {
+        self.post("/play/session_get", req, REQUEST_TIMEOUT)
+            .and_then(|response| parse_json(&response))
+    }
+
+    pub fn session_init(
+        &self,
+        req: &SessionInitRequest,
+    ) -> Result<SessionInitResponse, ApiCallError> {
+        self.post("/play/session_init", req, REQUEST_TIMEOUT)
+            .and_then(|response| parse_json(&response))
+    }
+
+    pub fn session_cancel(&self, req: &SessionCancelRequest) -> Result<(), ApiCallError> {
+        self.post("/play/session_cancel", req, REQUEST_TIMEOUT)
+            .map(|_| ())
+    }
+
+    pub fn game_start(&self, req: &GameStartRequest) -> Result<(), ApiCallError> {
+        self.post("/play/game_start", req, REQUEST_TIMEOUT)
+            .map(|_| ())
+    }
+
+    /// Replay verification can take a while, the server re-simulates the whole run
+    pub fn game_complete(
+        &self,
+        req: &GameCompleteRequest,
+    ) -> Result<GameCompleteResponse, ApiCallError> {
+        self.post("/play/game_complete", req, GAME_COMPLETE_TIMEOUT)
+            .and_then(|response| parse_json(&response))
+    }
+
+    /// GET a route and return the body of a successful response
+    #[cfg(not(target_arch = "wasm32"))]
+    fn get(&self, route: &str, timeout: Duration) -> Result<String, ApiCallError> {
+        let url = format!("{}{route}", self.base_url);
+        read_response(route, self.agent.get(&url).timeout(timeout).call())
+    }
+
+    /// POST a JSON body and return the body of a successful response
+    #[cfg(not(target_arch = "wasm32"))]
+    fn post<T: Serialize>(
+        &self,
+        route: &str,
+        req: &T,
+        timeout: Duration,
+    ) -> Result<String, ApiCallError> {
+        let url = format!("{}{route}", self.base_url);
+        read_response(route, self.agent.post(&url).timeout(timeout).send_json(req))
+    }
+
+    /// There is no HTTP backend for browser builds yet, they play offline
+    #[cfg(target_arch = "wasm32")]
+    fn get(&self, route: &str, _timeout: Duration) -> Result<String, ApiCallError> {
+        Err(ApiCallError::Transient(format!(
+            "{}{route}: not supported in the browser",
+            self.base_url
+        )))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn post<T: Serialize>(
+        &self,
+        route: &str,
+        _req: &T,
+        timeout: Duration,
+    ) -> Result<String, ApiCallError> {
+        self.get(route, timeout)
+    }
+}
+
+/// The body of a successful response, or the error the server answered with
+#[cfg(not(target_arch = "wasm32"))]
+fn read_response(
+    route: &str,
+    result: Result<ureq::Response, ureq::Error>,
+) -> Result<String, ApiCallError> {
+    match result {
+        Ok(response) => response
+            .into_string()
+            .map_err(|e| ApiCallError::Transient(format!("{route}: {e}"))),
+        // Too many requests and server side failures may succeed later
+        Err(ureq::Error::Status(status, response)) if status == 429 || status >= 500 => {
+            Err(ApiCallError::Transient(format!(
+                "{route}: status {status} {}",
+                response.status_text()
+            )))
+        }
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            Err(ApiCallError::Rejected(
+                serde_json::from_str(&body).unwrap_or_else(|_| ApiErrorBody {
+                    error: format!("Status{status}"),
+                    message: body,
+                }),
+            ))
+        }
+        Err(ureq::Error::Transport(e)) => Err(ApiCallError::Transient(format!("{route}: {e}"))),
+    }
+}
+
+fn parse_json<T: DeserializeOwned>(body: &str) -> Result<T, ApiCallError> {
+    serde_json::from_str(body)
+        .map_err(|e| ApiCallError::Transient(format!("Invalid response: {e}")))
+}
@@ -0,0 +1,803 @@
+pub mod api_client;
+
+use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use futures_lite::future;
+use speedrun_survivors_model::*;
+use std::sync::Arc;
+
+use crate::menu::{MenuGameConfig, PlayRequested};
+use crate::online::api_client::{ApiCallError, ApiClient};
+use crate::player::PlayerState;
+use crate::plugins::coin_rewards::CoinAccumulator;
+use crate::replay::playback::ReplayPlayback;
+use crate::replay::recorder::ReplayRecorder;
+use crate::state::AppState;
+use crate::wallet::{Signer, Wallet};
+
+/// Env variable with the base URL of the game API; `--api <url>` takes precedence
+const API_URL_ENV: &str = "SPEEDRUN_API_URL";
+
+/// Submitting a completed run is retried with exponential backoff
+const COMPLETE_ATTEMPTS: u32 = 8;
+const COMPLETE_RETRY_DELAY: f32 = 2.;
+const COMPLETE_MAX_RETRY_DELAY: f32 = 60.;
+
+/// Runs the session lifecycle against the game API, so runs can be verified and ranked
+/// Without an API URL, a wallet or a reachable server the game falls back to offline play
+/// Signs with the `Wallet`, so the `WalletPlugin` is required
+pub struct OnlineSessionPlugin;
+
+impl Plugin for OnlineSessionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<OnlineState>()
+            .add_systems(Startup, setup_online_session)
+            .add_systems(OnEnter(AppState::GameMenuMain), on_enter_game_menu)
+            .add_systems(
+                Update,
+                (connect_session, on_play_requested, start_requested_game)
+                    .chain()
+                    .run_if(in_state(AppState::GameMenuMain)),
+            )
+            .add_systems(
+                Update,
+                queue_game_complete.run_if(in_state(AppState::GameOver)),
+            )
+            .add_systems(
+                Update,
+                (
+                    poll_connect,
+                    poll_game_start,
+                    submit_game_complete,
+                    poll_game_complete,
+                ),
+            )
+            .insert_resource(OnlineSession::default());
+    }
+}
+
+#[derive(States, Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum OnlineState {
+    /// Waiting for a fresh session from the server
+    #[default]
+    Connecting,
+    /// A session is ready; the next run is started on the server and can be ranked
+    Online,
+    /// Runs are played unranked; connecting is tried again when returning to the menu, unless the
+    /// server speaks another API version
+    Offline,
+}
+
+#[derive(Resource, Default)]
+pub struct OnlineSession {
+    client: Option<ApiClient>,
+    /// Entropy of the session the next run is started with
+    entropy: Option<String>,
+    /// Play was pressed while still connecting
+    play_requested: bool,
+    /// The run that was started on the server and still needs to be completed
+    run: Option<OnlineRun>,
+    pending: Option<PendingCompletion>,
+    connect_task: Option<Task<Result<String, ApiCallError>>>,
+    start_task: Option<Task<Result<OnlineRun, ApiCallError>>>,
+    complete_task: Option<Task<Result<GameCompleteResponse, ApiCallError>>>,
+}
+
+impl OnlineSession {
+    /// A completed run is still being submitted; a new session would cancel it
+    fn completing(&self) -> bool {
+        self.pending.is_some() || self.complete_task.is_some()
+    }
+}
+
+struct OnlineRun {
+    pubkey: String,
+    entropy: String,
+    nft_list: Vec<String>,
+    /// Locked by the server along with the NFTs
+    hero: String,
+    map: String,
+}
+
+struct PendingCompletion {
+    request: GameCompleteRequest,
+    attempts: u32,
+    /// Elapsed app time at which the next attempt is made
+    retry_at: f32,
+}
+
+/// Take the API URL from `--api <url>` or the environment
+fn setup_online_session(mut session: ResMut<OnlineSession>) {
+    let args: Vec<String> = std::env::args().collect();
+    let url = args
+        .iter()
+        .position(|arg| arg == "--api")
+        .and_then(|idx| args.get(idx + 1).cloned())
+        .or_else(|| std::env::var(API_URL_ENV).ok());
+
+    match url {
+        Some(url) => {
+            info!("Using game API {url}");
+            session.client = Some(ApiClient::new(&url));
+        }
+        None => info!("No game API configured, playing offline"),
+    }
+}
+
+/// Try to connect again whenever the menu is entered
+fn on_enter_game_menu(
+    mut session: ResMut<OnlineSession>,
+    mut next_state: ResMut<NextState<OnlineState>>,
+) {
+    // A run that did not reach the game over screen was abandoned
+    session.run = None;
+    session.play_requested = false;
+
+    if session.entropy.is_none() && session.client.is_some() {
+        next_state.set(OnlineState::Connecting);
+    }
+}
+
+/// Get a fresh session: check the API version, `session_get`, cancel a leftover one, then `session_init`
+fn connect_session(
+    mut session: ResMut<OnlineSession>,
+    wallet: Res<Wallet>,
+    state: Res<State<OnlineState>>,
+    mut next_state: ResMut<NextState<OnlineState>>,
+) {
+    if state.get() != &OnlineState::Connecting
+        || session.connect_task.is_some()
+        || session.completing()
+    {
+        return;
+    }
+
+    if session.entropy.is_some() {
+        next_state.set(OnlineState::Online);
+        return;
+    }
+
+    let (Some(client), Some(signer)) = (session.client.clone(), wallet.signer()) else {
+        next_state.set(OnlineState::Offline);
+        return;
+    };
+
+    session.connect_task =
+        Some(AsyncComputeTaskPool::get().spawn(async move { request_session(&client, signer) }));
+}
+
+fn request_session(client: &ApiClient, signer: Arc<dyn Signer>) -> Result<String, ApiCallError> {
+    client.check_version()?;
+    let pubkey = signer.pubkey();
+
+    let current = client.session_get(&SessionGetRequest {
+        pubkey: pubkey.clone(),
+    })?;
+    if current.state == SessionStateClient::Active {
+        // Left over from a run that was never completed, e.g. the game was closed
+        let entropy = current.entropy.unwrap_or_default();
+        let signature = signer
+            .sign(&cancel_game_message(&pubkey, &entropy))
+            .map_err(|e| ApiCallError::Signer(e.to_string()))?;
+        client.session_cancel(&SessionCancelRequest {
+            pubkey: pubkey.clone(),
+            signature,
+        })?;
+    }
+
+    Ok(client.session_init(&SessionInitRequest { pubkey })?.entropy)
+}
+
+fn poll_connect(
+    mut session: ResMut<OnlineSession>,
+    mut next_state: ResMut<NextState<OnlineState>>,
+) {
+    let Some(result) = poll_task(&mut session.connect_task) else {
+        return;
+    };
+
+    match result {
+        Ok(entropy) => {
+            info!("Session ready");
+            session.entropy = Some(entropy);
+            next_state.set(OnlineState::Online);
+        }
+        Err(e @ ApiCallError::Incompatible(_)) => {
+            error!("{e}; update the game to play online, playing offline until then");
+            // Connecting again would only fail the same way
+            session.client = None;
+            next_state.set(OnlineState::Offline);
+        }
+        Err(e) => {
+            warn!("Failed to get a session, playing offline: {e}");
+            next_state.set(OnlineState::Offline);
+        }
+    }
+}
+
+fn on_play_requested(mut rx_play: EventReader<PlayRequested>, mut session: ResMut<OnlineSession>) {
+    if rx_play.iter().count() > 0 {
+        session.play_requested = true;
+    }
+}
+
+/// Start the run on the server once connecting is done; offline, start it right away
+fn start_requested_game(
+    mut session: ResMut<OnlineSession>,
+    wallet: Res<Wallet>,
+    playback: Res<ReplayPlayback>,
+    game_config: Res<MenuGameConfig>,
+    state: Res<State<OnlineState>>,
+    mut next_state: ResMut<NextState<OnlineState>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+    // Connecting waits for a submission in flight, so don't wait for it in turn
+    let connecting = state.get() == &OnlineState::Connecting && !session.completing();
+    if !session.play_requested || connecting || session.start_task.is_some() {
+        return;
+    }
+    session.play_requested = false;
+
+    // Replays are played back with their own entropy
+    let entropy = match (state.get(), &playback.replay) {
+        (OnlineState::Online, None) => session.entropy.take(),
+        _ => None,
+    };
+    let (Some(entropy), Some(client), Some(signer)) =
+        (entropy, session.client.clone(), wallet.signer())
+    else {
+        next_app_state.set(AppState::GameInitializing);
+        return;
+    };
+
+    let pubkey = signer.pubkey();
+    let nft_list = game_config.nft_list.clone();
+    let hero = game_config.hero.clone();
+    let map = format!("{:?}", game_config.map);
+    let signature = match signer.sign(&start_game_message(
+        &pubkey, &entropy, &nft_list, &hero, &map,
+    )) {
+        Ok(signature) => signature,
+        Err(e) => {
+            warn!("Failed to sign the game start, playing offline: {e:?}");
+            next_state.set(OnlineState::Offline);
+            next_app_state.set(AppState::GameInitializing);
+            return;
+        }
+    };
+
+    let req = GameStartRequest {
+        pubkey: pubkey.clone(),
+        entropy: entropy.clone(),
+        nft_list: Some(nft_list.clone()),
+        hero: hero.clone(),
+        map: map.clone(),
+        signature,
+    };
+    session.start_task = Some(AsyncComputeTaskPool::get().spawn(async move {
+        client.game_start(&req).map(|_| OnlineRun {
+            pubkey,
+            entropy,
+            nft_list,
+            hero,
+            map,
+        })
+    }));
+}
+
+fn poll_game_start(
+    mut session: ResMut<OnlineSession>,
+    mut game_config: ResMut<MenuGameConfig>,
+    mut next_state: ResMut<NextState<OnlineState>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+    let Some(result) = poll_task(&mut session.start_task) else {
+        return;
+    };
+
+    match result {
+        Ok(run) => {
+            info!("Game started online");
+            game_config.entropy = Some(run.entropy.clone());
+            session.run = Some(run);
+        }
+        Err(e) => {
+            warn!("Failed to start the game online, playing offline: {e}");
+            next_state.set(OnlineState::Offline);
+        }
+    }
+    next_app_state.set(AppState::GameInitializing);
+}
+
+/// Once the replay is sealed, sign the outcome and queue it for submission
+fn queue_game_complete(
+    mut session: ResMut<OnlineSession>,
+    wallet: Res<Wallet>,
+    recorder: Res<ReplayRecorder>,
+    player_state: Res<PlayerState>,
+    coin_accumulator: Res<CoinAccumulator>,
+) {
+    let Some(replay) = recorder.exported.clone() else {
+        return;
+    };
+    let Some(run) = session.run.take() else {
+        return;
+    };
+    let Some(signer) = wallet
+        .signer()
+        .filter(|signer| signer.pubkey() == run.pubkey)
+    else {
+        warn!("The wallet changed during the run, it can not be submitted");
+        return;
+    };
+
+    let signature = match signer.sign(&complete_game_message(
+        &run.pubkey,
+        &run.entropy,
+        &run.nft_list,
+        &run.hero,
+        &run.map,
+    )) {
+        Ok(signature) => signature,
+        Err(e) => {
+            error!("Failed to sign the game completion: {e:?}");
+            return;
+        }
+    };
+
+    let request = GameCompleteRequest {
+        pubkey: run.pubkey,
+        entropy: run.entropy,
+        nft_list: Some(run.nft_list),
+        hero: run.hero,
+        map: run.map,
+        replay,
+        outcome: RunOutcome {
+            ticks: recorder.replay.end_tick,
+            kills: player_state.total_kills as u32,
+            coins: coin_accumulator.total_coin,
+            level: player_state.level.0 as u32,
+        },
+        signature,
+    };
+    session.pending = Some(PendingCompletion {
+        request,
+        attempts: 0,
+        retry_at: 0.,
+    });
+}
+
+fn submit_game_complete(mut session: ResMut<OnlineSession>, time: Res<Time>) {
+    if session.complete_task.is_some() {
+        return;
+    }
+    let Some(client) = session.client.clone() else {
+        return;
+    };
+    let Some(pending) = session
+        .pending
+        .as_mut()
+        .filter(|pending| pending.retry_at <= time.elapsed_seconds())
+    else {
+        return;
+    };
+
+    pending.attempts += 1;
+    let req = pending.request.clone();
+    session.complete_task =
+        Some(AsyncComputeTaskPool::get().spawn(async move { client.game_complete(&req) }));
+}
+
+fn poll_game_complete(mut session: ResMut<OnlineSession>, time: Res<Time>) {
+    let Some(result) = poll_task(&mut session.complete_task) else {
+        return;
+    };
+    let Some(mut pending) = session.pending.take() else {
+        return;
+    };
+
+    match completion_step(result, pending.attempts) {
+        CompletionStep::Done(response) => info!(
+            "Run verified ({:?}), {} coins credited",
+            response.replay, response.reward
+        ),
+        CompletionStep::AlreadySubmitted => {
+            info!("Run was submitted by an earlier attempt whose response got lost")
+        }
+        CompletionStep::Retry(delay, reason) => {
+            warn!(
+                "Failed to submit the run (attempt {}), retrying in {delay}s: {reason}",
+                pending.attempts
+            );
+            pending.retry_at = time.elapsed_seconds() + delay;
+            session.pending = Some(pending);
+        }
+        CompletionStep::Failed(e) => error!("The run was not accepted: {e}"),
+    }
+}
+
+/// What to do after an attempt to submit a completed run
+enum CompletionStep {
+    Done(GameCompleteResponse),
+    /// An earlier attempt got through, e.g. it timed out on the client while the server verified it
+    AlreadySubmitted,
+    /// Seconds until the next attempt and why this one failed
+    Retry(f32, String),
+    Failed(ApiCallError),
+}
+
+/// `attempts` counts the attempts made so far, including the one that returned `result`
+fn completion_step(
+    result: Result<GameCompleteResponse, ApiCallError>,
+    attempts: u32,
+) -> CompletionStep {
+    match result {
+        Ok(response) => CompletionStep::Done(response),
+        Err(e) if attempts > 1 && e.is_rejected_with(ApiErrorCode::EntropyUsed) => {
+            CompletionStep::AlreadySubmitted
+        }
+        Err(ApiCallError::Transient(reason)) if attempts < COMPLETE_ATTEMPTS => {
+            CompletionStep::Retry(retry_delay(attempts), reason)
+        }
+        Err(e) => CompletionStep::Failed(e),
+    }
+}
+
+/// Exponential backoff, starting at `COMPLETE_RETRY_DELAY` after the first attempt
+fn retry_delay(attempts: u32) -> f32 {
+    (COMPLETE_RETRY_DELAY * 2f32.powi(attempts.max(1) as i32 - 1)).min(COMPLETE_MAX_RETRY_DELAY)
+}
+
+/// Take the result of a finished task without blocking the frame
+fn poll_task<T>(task: &mut Option<Task<T>>) -> Option<T> {
+    let result = future::block_on(future::poll_once(task.as_mut()?))?;
+    *task = None;
+    Some(result)
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use speedrun_survivors_replay::{Replay, ReplayAction, ReplayInput};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+    use std::thread;
+
+    /// Stand-in for the game API: answers each connection with the next scripted response and
+    /// records the requested paths
+    struct StandIn {
+        url: String,
+        paths: Arc<Mutex<Vec<String>>>,
+        server: thread::JoinHandle<()>,
+    }
+
+    impl StandIn {
+        fn serve(responses: Vec<(u16, String)>) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let paths = Arc::new(Mutex::new(Vec::new()));
+            let recorded = Arc::clone(&paths);
+            let server = thread::spawn(move || {
+                for (status, body) in responses {
+                    let (stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream);
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    recorded
+                        .lock()
+                        .unwrap()
+                        .push(line.split(' ').nth(1).unwrap_or_default().to_string());
+
+                    let mut length = 0;
+                    loop {
+                        line.clear();
+                        reader.read_line(&mut line).unwrap();
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = line.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                length = value.trim().parse().unwrap();
+                            }
+                        }
+                    }
+                    reader.read_exact(&mut vec![0; length]).unwrap();
+
+                    write!(
+                        reader.get_mut(),
+                        "HTTP/1.1 {status} Scripted\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    )
+                    .unwrap();
+                }
+            });
+            Self { url, paths, server }
+        }
+
+        /// The paths requested, once every scripted response was sent
+        fn finish(self) -> Vec<String> {
+            self.server.join().unwrap();
+            Arc::try_unwrap(self.paths).unwrap().into_inner().unwrap()
+        }
+    }
+
+    fn ok(body: &str) -> (u16, String) {
+        (200, body.to_string())
+    }
+
+    /// The answer of the version route
+    fn version(api_version: u32) -> (u16, String) {
+        let body = VersionResponse {
+            app: "speedrun-survivors-api".to_string(),
+            version: "0.1.0".to_string(),
+            api_version,
+        };
+        (200, serde_json::to_string(&body).unwrap())
+    }
+
+    /// An error response the way the server sends it
+    fn rejection(code: ApiErrorCode) -> (u16, String) {
+        let body = ApiErrorBody::new(code, "scripted");
+        (code.status(), serde_json::to_string(&body).unwrap())
+    }
+
+    struct TestSigner;
+
+    impl Signer for TestSigner {
+        fn pubkey(&self) -> String {
+            "pubkey".to_string()
+        }
+
+        fn sign(&self, message: &str) -> anyhow::Result<String> {
+            Ok(format!("signed {message}"))
+        }
+    }
+
+    fn complete_request() -> GameCompleteRequest {
+        GameCompleteRequest {
+            pubkey: "pubkey".to_string(),
+            entropy: "entropy".to_string(),
+            nft_list: None,
+            hero: "hero".to_string(),
+            map: "map".to_string(),
+            replay: String::new(),
+            outcome: RunOutcome {
+                ticks: 120,
+                kills: 3,
+                coins: 40,
+                level: 2,
+            },
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn active_session_is_cancelled_before_init() {
+        let server = StandIn::serve(vec![
+            version(API_VERSION),
+            ok(r#"{"state":"Active","entropy":"old"}"#),
+            ok("{}"),
+            ok(r#"{"entropy":"new"}"#),
+        ]);
+        let client = ApiClient::new(&server.url);
+
+        let entropy = request_session(&client, Arc::new(TestSigner)).unwrap();
+        assert_eq!(entropy, "new");
+        assert_eq!(
+            server.finish(),
+            [
+                "/",
+                "/play/session_get",
+                "/play/session_cancel",
+                "/play/session_init"
+            ]
+        );
+    }
+
+    #[test]
+    fn fresh_session_is_initialised_right_away() {
+        let server = StandIn::serve(vec![
+            version(API_VERSION),
+            ok(r#"{"state":"None","entropy":null}"#),
+            ok(r#"{"entropy":"new"}"#),
+        ]);
+        let client = ApiClient::new(&server.url);
+
+        let entropy = request_session(&client, Arc::new(TestSigner)).unwrap();
+        assert_eq!(entropy, "new");
+        assert_eq!(
+            server.finish(),
+            ["/", "/play/session_get", "/play/session_init"]
+        );
+    }
+
+    #[test]
+    fn version_is_only_checked_once() {
+        let server = StandIn::serve(vec![
+            version(API_VERSION),
+            ok(r#"{"state":"None","entropy":null}"#),
+            ok(r#"{"entropy":"first"}"#),
+            ok(r#"{"state":"None","entropy":null}"#),
+            ok(r#"{"entropy":"second"}"#),
+        ]);
+        let client = ApiClient::new(&server.url);
+
+        request_session(&client, Arc::new(TestSigner)).unwrap();
+        let entropy = request_session(&client.clone(), Arc::new(TestSigner)).unwrap();
+        assert_eq!(entropy, "second");
+        assert_eq!(
+            server.finish().iter().filter(|path| *path == "/").count(),
+            1
+        );
+    }
+
+    #[test]
+    fn other_api_version_stops_the_connect() {
+        let server = StandIn::serve(vec![version(API_VERSION - 1)]);
+        let client = ApiClient::new(&server.url);
+
+        let result = request_session(&client, Arc::new(TestSigner));
+        assert!(matches!(result, Err(ApiCallError::Incompatible(v)) if v == API_VERSION - 1));
+        assert_eq!(server.finish(), ["/"]);
+    }
+
+    #[test]
+    fn incompatible_server_is_not_connected_again() {
+        let task = AsyncComputeTaskPool::init(Default::default)
+            .spawn(async { Err(ApiCallError::Incompatible(API_VERSION + 1)) });
+        while !task.is_finished() {
+            thread::yield_now();
+        }
+
+        let mut world = World::new();
+        world.insert_resource(OnlineSession {
+            client: Some(ApiClient::new("http://127.0.0.1:1")),
+            connect_task: Some(task),
+            ..default()
+        });
+        world.init_resource::<NextState<OnlineState>>();
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems(poll_connect);
+        schedule.run(&mut world);
+
+        assert_eq!(
+            world.resource::<NextState<OnlineState>>().0,
+            Some(OnlineState::Offline)
+        );
+        assert!(world.resource::<OnlineSession>().client.is_none());
+    }
+
+    #[test]
+    fn failed_cancel_stops_the_connect() {
+        let server = StandIn::serve(vec![
+            version(API_VERSION),
+            ok(r#"{"state":"Active","entropy":"old"}"#),
+            rejection(ApiErrorCode::SignatureInvalid),
+        ]);
+        let client = ApiClient::new(&server.url);
+
+        let result = request_session(&client, Arc::new(TestSigner));
+        assert!(matches!(result, Err(e) if e.is_rejected_with(ApiErrorCode::SignatureInvalid)));
+        assert_eq!(
+            server.finish(),
+            ["/", "/play/session_get", "/play/session_cancel"]
+        );
+    }
+
+    #[test]
+    fn completion_is_retried_until_it_is_accepted() {
+        let server = StandIn::serve(vec![
+            rejection(ApiErrorCode::Busy),
+            (429, String::new()),
+            ok(r#"{"replay":"Valid","reward":40}"#),
+        ]);
+        let client = ApiClient::new(&server.url);
+
+        let mut delays = Vec::new();
+        for attempts in 1.. {
+            match completion_step(client.game_complete(&complete_request()), attempts) {
+                CompletionStep::Retry(delay, _) => delays.push(delay),
+                CompletionStep::Done(response) => {
+                    assert_eq!(response.replay, ReplayVerdict::Valid);
+                    assert_eq!(response.reward, 40);
+                    break;
+                }
+                _ => panic!("Unexpected step after {attempts} attempts"),
+            }
+        }
+        assert_eq!(delays, [COMPLETE_RETRY_DELAY, COMPLETE_RETRY_DELAY * 2.]);
+        assert_eq!(server.finish().len(), 3);
+    }
+
+    #[test]
+    fn entropy_used_on_a_retry_was_already_submitted() {
+        let server = StandIn::serve(vec![
+            (502, String::new()),
+            rejection(ApiErrorCode::EntropyUsed),
+            rejection(ApiErrorCode::EntropyUsed),
+        ]);
+        let client = ApiClient::new(&server.url);
+
+        assert!(matches!(
+            completion_step(client.game_complete(&complete_request()), 1),
+            CompletionStep::Retry(..)
+        ));
+        assert!(matches!(
+            completion_step(client.game_complete(&complete_request()), 2),
+            CompletionStep::AlreadySubmitted
+        ));
+        // On the first attempt nothing could have been submitted before
+        assert!(matches!(
+            completion_step(client.game_complete(&complete_request()), 1),
+            CompletionStep::Failed(e) if e.is_rejected_with(ApiErrorCode::EntropyUsed)
+        ));
+        server.finish();
+    }
+
+    #[test]
+    fn game_over_submits_the_recorded_replay() {
+        let mut world = World::new();
+        let mut recorder = ReplayRecorder::default();
+        recorder
+            .replay
+            .push(3, ReplayInput::Press(ReplayAction::MoveUp));
+        recorder
+            .replay
+            .push(9, ReplayInput::Release(ReplayAction::MoveUp));
+        recorder.tick = 120;
+        recorder.seal();
+        let recorded = recorder.replay.clone();
+
+        world.insert_resource(recorder);
+        world.insert_resource(OnlineSession {
+            run: Some(OnlineRun {
+                pubkey: "pubkey".to_string(),
+                entropy: "entropy".to_string(),
+                nft_list: Vec::new(),
+                hero: "Pepe".to_string(),
+                map: "Map1".to_string(),
+            }),
+            ..default()
+        });
+        world.insert_resource(Wallet::new(Arc::new(TestSigner)));
+        world.init_resource::<PlayerState>();
+        world.init_resource::<CoinAccumulator>();
+
+        let mut schedule = Schedule::new();
+        schedule.add_systems(queue_game_complete);
+        schedule.run(&mut world);
+
+        let session = world.resource::<OnlineSession>();
+        let request = &session.pending.as_ref().unwrap().request;
+        assert_eq!(Replay::from_base64(&request.replay).unwrap(), recorded);
+        assert_eq!(request.outcome.ticks, 120);
+        assert_eq!(
+            (request.hero.as_str(), request.map.as_str()),
+            ("Pepe", "Map1")
+        );
+        assert!(session.run.is_none());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let delays: Vec<f32> = (1..=COMPLETE_ATTEMPTS).map(retry_delay).collect();
+        assert_eq!(delays[..3], [2., 4., 8.]);
+        assert!(delays.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(delays.last(), Some(&COMPLETE_MAX_RETRY_DELAY));
+    }
+
+    #[test]
+    fn completion_gives_up_after_the_last_attempt() {
+        let transient = || Err(ApiCallError::Transient("down".to_string()));
+        assert!(matches!(
+            completion_step(transient(), COMPLETE_ATTEMPTS - 1),
+            CompletionStep::Retry(..)
+        ));
+        assert!(matches!(
+            completion_step(transient(), COMPLETE_ATTEMPTS),
+            CompletionStep::Failed(ApiCallError::Transient(_))
+        ));
+    }
+}
@@ -1,10 +1,12 @@
 use crate::enemy::Enemy;
 use crate::player::Player;
 use crate::plugins::assets::GameAssets;
+use crate::plugins::game_rng::{GameRng, RngStream};
 use crate::plugins::gameplay_effects::{
     GameplayEffectPluginState, GameplayStat, GameplayStatsRecalculatedEvent,
 };
 use crate::plugins::health::{HealthUpdateEvent, TargetType};
+use crate::plugins::simulation::{Interpolated, SimulationSet, SIMULATED_VIEWPORT};
 use crate::state::{for_game_states, AppState};
 use bevy::prelude::*;
 use rand::Rng;
@@ -17,12 +19,14 @@ impl Plugin for OrcaChopperPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(AppState::GameInitializing), on_enter_game_init)
             .add_systems(
-                Update,
-                (orca_move, orca_attack).run_if(in_state(AppState::GameRunning)),
-            )
-            .add_systems(
-                Update,
-                on_stats_recalculated.run_if(on_event::<GameplayStatsRecalculatedEvent>()),
+                FixedUpdate,
+                (
+                    on_stats_recalculated
+                        .run_if(on_event::<GameplayStatsRecalculatedEvent>())
+                        .in_set(SimulationSet::Input),
+                    orca_move.in_set(SimulationSet::Movement),
+                    orca_attack.in_set(SimulationSet::Attacks),
+                ),
             )
             .insert_resource(OrcaChopperPluginState::default());
     }
@@ -39,6 +43,7 @@ fn on_stats_recalculated(
     player: Query<&Transform, With<Player>>,
     game_assets: Res<GameAssets>,
     gameplay_state: Res<GameplayEffectPluginState>,
+    mut game_rng: ResMut<GameRng>,
 ) {
     // Make sure we got a player
     let Ok(player_location) = player.get_single() else {
@@ -59,61 +64,56 @@ fn on_stats_recalculated(
         .get_stat(GameplayStat::OrcaDamage) as f32;
 
     // No need to spawn none
-    if expected <= 0 {
+    if expected == 0 {
         return;
     }
 
     // Spawn in the required number of orcas
     for _ in orca_state.total_spawned..expected {
         orca_state.total_spawned += 1;
-        spawn_orca_chopper(&mut commands, &player_location.translation, &game_assets);
+        spawn_orca_chopper(
+            &mut commands,
+            game_rng.stream(RngStream::Passives),
+            &player_location.translation,
+            &game_assets,
+        );
     }
 }
 
 /// Move and rotate each orca chopper
 fn orca_move(
-    time: Res<Time>,
-    camera_query: Query<(&Camera, &GlobalTransform)>,
+    time: Res<FixedTime>,
+    player: Query<&Transform, (With<Player>, Without<OrcaChopper>)>,
     mut orcas: Query<(&mut OrcaChopper, &mut Transform)>,
     orca_state: Res<OrcaChopperPluginState>,
 ) {
-    // Project camera viewport to world space
-    let Ok((camera, camera_transform)) = camera_query.get_single() else {
-        return;
-    };
-    let Some(viewport) = camera.logical_viewport_rect() else {
-        return;
-    };
-    let Some(top_left) = camera.viewport_to_world_2d(camera_transform, Vec2::default()) else {
-        return;
-    };
-    let Some(bottom_right) = camera.viewport_to_world_2d(camera_transform, viewport.max) else {
+    // The simulated viewport is centered on the player
+    let Ok(player) = player.get_single() else {
         return;
     };
+    let center = player.translation;
+    let top_left = center.truncate() + Vec2::new(-SIMULATED_VIEWPORT.x, SIMULATED_VIEWPORT.y) / 2.;
+    let bottom_right =
+        center.truncate() + Vec2::new(SIMULATED_VIEWPORT.x, -SIMULATED_VIEWPORT.y) / 2.;
 
     // Get the orca speed from the gameplay system
-    let move_by = orca_state.speed * time.delta_seconds();
+    let move_by = orca_state.speed * time.period.as_secs_f32();
 
     for (mut orca, mut transform) in orcas.iter_mut() {
-        // Distance to camera location
-        let distance_before = transform
-            .translation
-            .distance(camera_transform.translation());
+        // Distance to the viewport center
+        let distance_before = transform.translation.distance(center);
 
         // Move & rotate each orca
         transform.translation.x += orca.heading.cos() * move_by;
         transform.translation.y += orca.heading.sin() * move_by;
-        transform.rotate(Quat::from_rotation_z(30.0 * time.delta_seconds()));
+        transform.rotate(Quat::from_rotation_z(30.0 * time.period.as_secs_f32()));
 
         // The orca might leave the screen by such a distance,
         // that every future tick would only flip flop the heading around
         // So we need to make sure it is getting closer
         // TODO this is not fully foolproof - might get stuck bouncing up and down with a permanent X location
         //  might have to add some angle towards center of screen
-        let moving_away = distance_before
-            < transform
-                .translation
-                .distance(camera_transform.translation());
+        let moving_away = distance_before < transform.translation.distance(center);
 
         // Bounce off of the screen edge
         if moving_away {
@@ -157,11 +157,10 @@ fn orca_attack(
 /// Spawn a new orca chopper on top of the player
 fn spawn_orca_chopper(
     commands: &mut Commands,
+    rng: &mut impl Rng,
     player_location: &Vec3,
     game_assets: &Res<GameAssets>,
 ) {
-    let mut rng = rand::thread_rng();
-
     let spawn_transform = Transform::from_translation(player_location.clone());
 
     commands
@@ -173,7 +172,8 @@ fn spawn_orca_chopper(
             },
             for_game_states(),
         ))
-        .insert(OrcaChopper::new(rng.gen_range(0.0..std::f32::consts::TAU)));
+        .insert(OrcaChopper::new(rng.gen_range(0.0..std::f32::consts::TAU)))
+        .insert(Interpolated::default());
 }
 
 #[derive(Default, Resource)]
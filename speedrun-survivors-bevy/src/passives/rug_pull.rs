@@ -1,12 +1,14 @@
 use crate::enemy::Enemy;
 use crate::player::Player;
 use crate::plugins::assets::GameAssets;
+use crate::plugins::game_rng::{GameRng, RngStream};
 use crate::plugins::gameplay_effects::{
     GameplayEffectPluginState, GameplayStat, GameplayStatsRecalculatedEvent,
 };
+use crate::plugins::simulation::{Interpolated, SimulationSet};
 use crate::state::{for_game_states, AppState};
 use bevy::prelude::*;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 /// The maximum distance between a rug and an enemy that will get pulled
 const RUG_GRAB_RANGE: f32 = 50.0;
@@ -17,12 +19,14 @@ impl Plugin for RugPullPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(AppState::GameInitializing), on_enter_game_init)
             .add_systems(
-                Update,
-                (on_update, rug_move, rug_pull_enemies).run_if(in_state(AppState::GameRunning)),
-            )
-            .add_systems(
-                Update,
-                on_stats_recalculated.run_if(on_event::<GameplayStatsRecalculatedEvent>()),
+                FixedUpdate,
+                (
+                    on_stats_recalculated
+                        .run_if(on_event::<GameplayStatsRecalculatedEvent>())
+                        .in_set(SimulationSet::Input),
+                    (rug_move, rug_pull_enemies).in_set(SimulationSet::Movement),
+                    on_update.in_set(SimulationSet::Attacks),
+                ),
             )
             .insert_resource(RugPullPluginState::default());
     }
@@ -52,11 +56,12 @@ fn on_stats_recalculated(
 
 /// Update stats when required, spawn rugs
 fn on_update(
-    time: Res<Time>,
+    time: Res<FixedTime>,
     mut commands: Commands,
     mut rug_state: ResMut<RugPullPluginState>,
     game_assets: Res<GameAssets>,
     player: Query<&Transform, With<Player>>,
+    mut game_rng: ResMut<GameRng>,
 ) {
     // Make sure we got a player
     let Ok(player_loc) = player.get_single().map(|tf| tf.translation.truncate()) else {
@@ -64,7 +69,7 @@ fn on_update(
     };
 
     // Update the time since last spawn
-    rug_state.time_last_spawn += time.delta_seconds();
+    rug_state.time_last_spawn += time.period.as_secs_f32();
 
     // Spawn a new rug
     if rug_state.interval > 0.0 && rug_state.time_last_spawn > rug_state.interval {
@@ -72,6 +77,7 @@ fn on_update(
         rug_state.rug_count += 1;
         spawn_rug(
             &mut commands,
+            game_rng.stream(RngStream::Passives),
             player_loc,
             &game_assets,
             rug_state.rug_count,
@@ -83,12 +89,12 @@ fn on_update(
 /// Spawn a rug heading into a random direction
 fn spawn_rug(
     commands: &mut Commands,
+    rng: &mut impl Rng,
     player_loc: Vec2,
     game_assets: &Res<GameAssets>,
     id: u64,
     max_ttl: f32,
 ) {
-    let mut rng = thread_rng();
     let mut spawn_transform = Transform::from_translation((player_loc, 1.0).into());
     let heading = rng.gen_range(0.0..std::f32::consts::TAU);
     let ttl = rng.gen_range(max_ttl / 2.0..max_ttl);
@@ -103,18 +109,19 @@ fn spawn_rug(
             },
             for_game_states(),
         ))
-        .insert(Rug { id, ttl, heading });
+        .insert(Rug { id, ttl, heading })
+        .insert(Interpolated::default());
 }
 
 fn rug_move(
-    time: Res<Time>,
+    time: Res<FixedTime>,
     mut commands: Commands,
     mut rugs: Query<(Entity, &mut Rug, &mut Transform), Without<Enemy>>,
     rug_state: Res<RugPullPluginState>,
     mut enemies: Query<(Entity, &Enemy, &Transform), (Without<Rug>, Without<RugPulled>)>,
     mut rug_pulled: Query<(Entity, &Enemy, &RugPulled), (Without<Rug>, With<RugPulled>)>,
 ) {
-    let delta = time.delta_seconds();
+    let delta = time.period.as_secs_f32();
     let move_by = rug_state.speed * delta;
 
     for (entity, mut rug, mut rug_tf) in rugs.iter_mut() {
@@ -159,12 +166,12 @@ fn rug_move(
 
 /// Move enemies affected by the rug pull ability
 fn rug_pull_enemies(
-    time: Res<Time>,
+    time: Res<FixedTime>,
     rug_state: Res<RugPullPluginState>,
     mut enemies: Query<(&mut Transform, &RugPulled), (With<Enemy>, Without<Rug>)>,
     // mut tx_health: EventWriter<HealthUpdateEvent>,
 ) {
-    let delta = time.delta_seconds();
+    let delta = time.period.as_secs_f32();
     let move_by = rug_state.speed * delta;
     // let delta_damage = delta * rug_state.damage;
 
@@ -1,14 +1,16 @@
 use crate::enemy::Enemy;
 use crate::player::Player;
 use crate::plugins::assets::GameAssets;
+use crate::plugins::game_rng::{GameRng, RngStream};
 use crate::plugins::gameplay_effects::{
     GameplayEffectPluginState, GameplayStat, GameplayStatsRecalculatedEvent,
 };
 use crate::plugins::health::{HealthUpdateEvent, TargetType};
+use crate::plugins::simulation::{Interpolated, SimulationSet};
 use crate::plugins::vfx_manager::{PlayVFX, VFX};
 use crate::state::{for_game_states, AppState};
 use bevy::prelude::*;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 /// The speed with which the cluster bomb flies
 const CLUSTER_BOMB_MOVE_SPEED: f32 = 700.0;
@@ -36,13 +38,14 @@ impl Plugin for ShitcoinClusterPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(AppState::GameInitializing), on_enter_game_init)
             .add_systems(
-                Update,
-                (on_update, cluster_move, sub_munition_move)
-                    .run_if(in_state(AppState::GameRunning)),
-            )
-            .add_systems(
-                Update,
-                on_stats_recalculated.run_if(on_event::<GameplayStatsRecalculatedEvent>()),
+                FixedUpdate,
+                (
+                    on_stats_recalculated
+                        .run_if(on_event::<GameplayStatsRecalculatedEvent>())
+                        .in_set(SimulationSet::Input),
+                    (cluster_move, sub_munition_move).in_set(SimulationSet::Movement),
+                    on_update.in_set(SimulationSet::Attacks),
+                ),
             )
             .insert_resource(ShitcoinClusterPluginState::default());
     }
@@ -71,11 +74,12 @@ fn on_stats_recalculated(
 
 /// Update stats when required, spawn shitcoin cluster bombs
 fn on_update(
-    time: Res<Time>,
+    time: Res<FixedTime>,
     mut commands: Commands,
     mut shitcoin_state: ResMut<ShitcoinClusterPluginState>,
     game_assets: Res<GameAssets>,
     player: Query<&Transform, With<Player>>,
+    mut game_rng: ResMut<GameRng>,
 ) {
     // Make sure we got a player
     let Ok(player_loc) = player.get_single().map(|tf| tf.translation.truncate()) else {
@@ -83,18 +87,27 @@ fn on_update(
     };
 
     // Update the time since last spawn
-    shitcoin_state.time_last_spawn += time.delta_seconds();
+    shitcoin_state.time_last_spawn += time.period.as_secs_f32();
 
     // Spawn a new cluster bomb
     if shitcoin_state.interval > 0.0 && shitcoin_state.time_last_spawn > shitcoin_state.interval {
         shitcoin_state.time_last_spawn = 0.0;
-        spawn_cluster_bomb(&mut commands, player_loc, &game_assets);
+        spawn_cluster_bomb(
+            &mut commands,
+            game_rng.stream(RngStream::Passives),
+            player_loc,
+            &game_assets,
+        );
     }
 }
 
 /// Spawn a shitcoin cluster bomb
-fn spawn_cluster_bomb(commands: &mut Commands, player_loc: Vec2, game_assets: &Res<GameAssets>) {
-    let mut rng = thread_rng();
+fn spawn_cluster_bomb(
+    commands: &mut Commands,
+    rng: &mut impl Rng,
+    player_loc: Vec2,
+    game_assets: &Res<GameAssets>,
+) {
     let spawn_transform = Transform::from_translation((player_loc, 5.0).into());
 
     commands
@@ -109,12 +122,13 @@ fn spawn_cluster_bomb(commands: &mut Commands, player_loc: Vec2, game_assets: &R
         .insert(ShitcoinClusterBomb {
             heading: rng.gen_range(0.0..std::f32::consts::TAU),
             time_till_boom: rng.gen_range(CLUSTER_MIN_TIME_TO_BOOM..CLUSTER_MAX_TIME_TO_BOOM),
-        });
+        })
+        .insert(Interpolated::default());
 }
 
 /// Move the shitcoin cluster bombs in an arch, update time until boom
 fn cluster_move(
-    time: Res<Time>,
+    time: Res<FixedTime>,
     mut commands: Commands,
     mut cluster_bombs: Query<
         (Entity, &mut ShitcoinClusterBomb, &mut Transform),
@@ -123,8 +137,9 @@ fn cluster_move(
     game_assets: Res<GameAssets>,
     shitcoin_state: Res<ShitcoinClusterPluginState>,
     mut tx_vfx: EventWriter<PlayVFX>,
+    mut game_rng: ResMut<GameRng>,
 ) {
-    let delta = time.delta_seconds();
+    let delta = time.period.as_secs_f32();
     let move_by = CLUSTER_BOMB_MOVE_SPEED * delta;
     let target_dir = 1.5 * std::f32::consts::PI;
     let gravity_delta = delta * CLUSTER_BOMB_GRAVITY_MULTIPLIER;
@@ -139,6 +154,7 @@ fn cluster_move(
             // Spawn sub-munitions
             spawn_sub_munitions(
                 &mut commands,
+                game_rng.stream(RngStream::Passives),
                 transform.translation.truncate(),
                 &game_assets,
                 &shitcoin_state,
@@ -173,11 +189,11 @@ fn cluster_move(
 /// Spawn a sub-munitions
 fn spawn_sub_munitions(
     commands: &mut Commands,
+    rng: &mut impl Rng,
     cluster_loc: Vec2,
     game_assets: &Res<GameAssets>,
     shitcoin_state: &Res<ShitcoinClusterPluginState>,
 ) {
-    let mut rng = thread_rng();
     let mut spawn_transform = Transform::from_translation((cluster_loc, 5.0).into());
     spawn_transform.scale *= SUB_MUNITION_SCALE;
 
@@ -195,13 +211,14 @@ fn spawn_sub_munitions(
                 heading: rng.gen_range(0.0..std::f32::consts::TAU),
                 time_till_boom: rng
                     .gen_range(SUB_MUNITION_MIN_TIME_TO_BOOM..SUB_MUNITION_MAX_TIME_TO_BOOM),
-            });
+            })
+            .insert(Interpolated::default());
     }
 }
 
 /// Move the sub-munitions in a straight line and update time until boom
 fn sub_munition_move(
-    time: Res<Time>,
+    time: Res<FixedTime>,
     mut commands: Commands,
     mut sub_munitions: Query<
         (Entity, &mut ShitcoinSubMunition, &mut Transform),
@@ -215,7 +232,7 @@ fn sub_munition_move(
     mut tx_health: EventWriter<HealthUpdateEvent>,
     mut tx_vfx: EventWriter<PlayVFX>,
 ) {
-    let delta = time.delta_seconds();
+    let delta = time.period.as_secs_f32();
     let move_by = SUB_MUNITION_MOVE_SPEED * delta;
 
     for (entity, mut munition, mut munition_tf) in sub_munitions.iter_mut() {
@@ -2,13 +2,15 @@ use crate::enemy::Enemy;
 use crate::player::Player;
 use crate::plugins::assets::GameAssets;
 use crate::plugins::camera_shake::{CameraImpact, CameraImpactStrength};
+use crate::plugins::game_rng::{GameRng, RngStream};
 use crate::plugins::gameplay_effects::{
     GameplayEffectPluginState, GameplayStat, GameplayStatsRecalculatedEvent,
 };
 use crate::plugins::health::{HealthUpdateEvent, TargetType};
+use crate::plugins::simulation::{Interpolated, SimulationSet, SIMULATED_VIEWPORT};
 use crate::state::{for_game_states, AppState};
 use bevy::prelude::*;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 /// Pushes the whale above the screen when spawned, so that it appears to drop in
 const WHALE_Y_OFFSET: f32 = 50.0;
@@ -27,12 +29,14 @@ impl Plugin for WhaleDumpPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(AppState::GameInitializing), on_enter_game_init)
             .add_systems(
-                Update,
-                (on_update, whale_move, whale_impact).run_if(in_state(AppState::GameRunning)),
-            )
-            .add_systems(
-                Update,
-                on_stats_recalculated.run_if(on_event::<GameplayStatsRecalculatedEvent>()),
+                FixedUpdate,
+                (
+                    on_stats_recalculated
+                        .run_if(on_event::<GameplayStatsRecalculatedEvent>())
+                        .in_set(SimulationSet::Input),
+                    whale_move.in_set(SimulationSet::Movement),
+                    (on_update, whale_impact).in_set(SimulationSet::Attacks),
+                ),
             )
             .insert_resource(WhaleDumpPluginState::default());
     }
@@ -61,50 +65,50 @@ fn on_stats_recalculated(
 
 /// Update stats when required, spawn whales
 fn on_update(
-    time: Res<Time>,
+    time: Res<FixedTime>,
     mut commands: Commands,
     mut whale_state: ResMut<WhaleDumpPluginState>,
     game_assets: Res<GameAssets>,
-    camera_query: Query<(&Camera, &GlobalTransform)>,
+    player: Query<&Transform, With<Player>>,
+    mut game_rng: ResMut<GameRng>,
 ) {
     // Update the time since last spawn
-    whale_state.time_last_spawn += time.delta_seconds();
+    whale_state.time_last_spawn += time.period.as_secs_f32();
 
     // Spawn if necessary
     if whale_state.interval > 0.0 && whale_state.time_last_spawn > whale_state.interval {
         whale_state.time_last_spawn = 0.0;
-        spawn_whale(&mut commands, spawn_location(camera_query), &game_assets);
+        let rng = game_rng.stream(RngStream::Passives);
+        let location = spawn_location(rng, player);
+        spawn_whale(&mut commands, rng, location, &game_assets);
     }
 }
 
-/// Returns a random location to spawn the whale
-fn spawn_location(camera_query: Query<(&Camera, &GlobalTransform)>) -> Option<Vec2> {
-    // Project camera viewport to world space
-    let Ok((camera, camera_transform)) = camera_query.get_single() else {
+/// Returns a random location to spawn the whale, along the top of the simulated viewport
+fn spawn_location(rng: &mut impl Rng, player: Query<&Transform, With<Player>>) -> Option<Vec2> {
+    let Ok(player) = player.get_single() else {
         return None;
     };
-    let Some(screen_width) = camera.logical_viewport_rect().map(|rect| rect.max.x) else {
-        return None;
-    };
-    let Some(top_left) = camera.viewport_to_world_2d(camera_transform, Vec2::default()) else {
-        return None;
-    };
-
-    let mut rng = thread_rng();
+    let top_left =
+        player.translation.truncate() + Vec2::new(-SIMULATED_VIEWPORT.x, SIMULATED_VIEWPORT.y) / 2.;
 
     Some(Vec2::new(
-        top_left.x + rng.gen_range(0.0..=screen_width),
+        top_left.x + rng.gen_range(0.0..=SIMULATED_VIEWPORT.x),
         top_left.y - WHALE_Y_OFFSET,
     ))
 }
 
 /// Spawn a whale at the given location
-fn spawn_whale(commands: &mut Commands, location: Option<Vec2>, game_assets: &Res<GameAssets>) {
+fn spawn_whale(
+    commands: &mut Commands,
+    rng: &mut impl Rng,
+    location: Option<Vec2>,
+    game_assets: &Res<GameAssets>,
+) {
     let Some(location) = location else {
         return;
     };
 
-    let mut rng = thread_rng();
     let spawn_transform = Transform::from_translation((location, 5.0).into());
 
     commands
@@ -118,16 +122,17 @@ fn spawn_whale(commands: &mut Commands, location: Option<Vec2>, game_assets: &Re
         ))
         .insert(Whale {
             time_till_boom: rng.gen_range(WHALE_MIN_TIME_TO_BOOM..WHALE_MAX_TIME_TO_BOOM),
-        });
+        })
+        .insert(Interpolated::default());
 }
 
 /// Let the whales fall
 fn whale_move(
-    time: Res<Time>,
+    time: Res<FixedTime>,
     mut commands: Commands,
     mut whales: Query<(Entity, &mut Whale, &mut Transform), Without<WhaleFlattens>>,
 ) {
-    let delta = time.delta_seconds();
+    let delta = time.period.as_secs_f32();
     let move_by = WHALE_MOVE_SPEED * delta;
 
     for (entity, mut whale, mut transform) in whales.iter_mut() {
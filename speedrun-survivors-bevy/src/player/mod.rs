@@ -1,17 +1,20 @@
 use std::collections::HashMap;
 
 use bevy::prelude::*;
-use leafwing_input_manager::action_state::ActionState;
 
 use crate::data::abilities::AbilityType;
-use crate::data::hero::HeroType;
+use crate::data::hero::{HeroDefinition, HeroRegistry};
 use crate::data::level::Level;
+use crate::enemy::enemy_type::EnemyType;
 use crate::menu::MenuGameConfig;
 use crate::plugins::assets::GameAssets;
 use crate::plugins::gameplay_effects::{
     GameplayEffectEvent, GameplayEffectPluginState, GameplayStat, GameplayStatsRecalculatedEvent,
 };
 use crate::plugins::health::{add_health_bar, Health};
+use crate::plugins::simulation::{
+    interpolate_translation, Interpolated, SimulationEventApp, SimulationInput, SimulationSet,
+};
 use crate::plugins::status_effect::{StatusEffect, StatusEffectController, StatusEffectType};
 use crate::state::{for_game_states, AppState};
 use crate::weapon::hammer::HammerStomp;
@@ -32,17 +35,25 @@ pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(AppState::GameInitializing), on_enter_game_init)
+            .add_systems(
+                FixedUpdate,
+                (
+                    (move_player, player_attach::attach_objects)
+                        .chain()
+                        .in_set(SimulationSet::Movement),
+                    process_events.in_set(SimulationSet::Death),
+                ),
+            )
             .add_systems(
                 Update,
                 (
-                    process_events,
-                    move_player,
                     player_attach::attach_objects,
                     player_camera::sync_player_camera,
                 )
+                    .after(interpolate_translation)
                     .run_if(in_state(AppState::GameRunning)),
             )
-            .add_event::<PlayerEvent>()
+            .add_simulation_event::<PlayerEvent>()
             .insert_resource(PlayerState::default());
     }
 }
@@ -166,6 +177,8 @@ pub struct PlayerState {
     pub total_kills: u64,
     pub abilities: HashMap<AbilityType, u8>,
     pub ability_order: Vec<AbilityType>,
+    /// The kind of enemy that hit the player last
+    pub last_hit_by: Option<EnemyType>,
 }
 
 impl Default for PlayerState {
@@ -177,14 +190,18 @@ impl Default for PlayerState {
             total_kills: 0,
             abilities: HashMap::new(),
             ability_order: Vec::new(),
+            last_hit_by: None,
         }
     }
 }
 
-#[derive(Component)]
-pub struct Player {}
+#[derive(Component, Default)]
+pub struct Player {
+    /// Direction of the movement input, zero while standing still
+    pub heading: Vec2,
+}
 
-fn create_player_anim_hashmap(hero_type: HeroType) -> HashMap<String, animation::Animation> {
+fn create_player_anim_hashmap(hero: &HeroDefinition) -> HashMap<String, animation::Animation> {
     let mut hash_map = HashMap::new();
     hash_map.insert(
         "Idle".to_string(),
@@ -195,7 +212,7 @@ fn create_player_anim_hashmap(hero_type: HeroType) -> HashMap<String, animation:
             cooldown: 0.1,
         },
     );
-    hash_map.insert("Walk".to_string(), hero_type.walk_animation());
+    hash_map.insert("Walk".to_string(), hero.walk_animation());
     hash_map
 }
 
@@ -205,13 +222,22 @@ pub fn on_enter_game_init(
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     game_config: Res<MenuGameConfig>,
     game_assets: Res<GameAssets>,
+    heroes: Res<HeroRegistry>,
 ) {
     // Reset player state
     *player_state = PlayerState::default();
 
-    let hero_type = game_config.hero.clone();
+    let Some(hero) = heroes.get_or_default(&game_config.hero) else {
+        error!("No heroes are loaded");
+        return;
+    };
 
-    let texture_atlas = hero_type.texture_atlas(&game_assets);
+    let texture_handle = game_assets
+        .heroes
+        .get(&hero.id)
+        .cloned()
+        .unwrap_or_default();
+    let texture_atlas = hero.texture_atlas(texture_handle);
     let texture_atlas_handle = texture_atlases.add(texture_atlas);
 
     let health_bar = add_health_bar(&mut commands, Vec3::default(), 5.);
@@ -220,7 +246,7 @@ pub fn on_enter_game_init(
         .spawn((
             SpriteSheetBundle {
                 texture_atlas: texture_atlas_handle,
-                transform: Transform::from_scale(Vec3::splat(hero_type.splat_scale())),
+                transform: Transform::from_scale(Vec3::splat(hero.splat_scale)),
                 ..Default::default()
             },
             for_game_states(),
@@ -230,18 +256,19 @@ pub fn on_enter_game_init(
             cooldown: 0.05,
             last_animation: "Walk".to_string(),
             current_animation: "Walk".to_string(),
-            animation_bank: create_player_anim_hashmap(hero_type),
+            animation_bank: create_player_anim_hashmap(hero),
             destroy_on_end: false,
         })
-        .insert(Player {})
+        .insert(Player::default())
+        .insert(Interpolated::default())
         .insert(Health::new(200., 200., 10.0, Some(health_bar)))
         .insert(StatusEffectController { effects: vec![] });
 }
 
 pub fn move_player(
-    time: Res<Time>,
-    actions: Query<&ActionState<GameAction>>,
-    mut query: Query<(&mut Transform, &mut Animator), With<Player>>,
+    time: Res<FixedTime>,
+    input: Res<SimulationInput>,
+    mut query: Query<(&mut Transform, &mut Animator, &mut Player)>,
     mut weapon_query: Query<
         (&mut TextureAtlasSprite, &mut PlayerAttach, &WeaponType),
         (Without<Player>, Without<WeaponAnimationEffect>),
@@ -254,18 +281,16 @@ pub fn move_player(
     gameplay: Res<GameplayEffectPluginState>,
     game_assets: Res<GameAssets>,
 ) {
-    let action = actions.single();
-
-    for (mut transform, mut animator) in query.iter_mut() {
+    for (mut transform, mut animator, mut player) in query.iter_mut() {
         let mut movement = Vec2::ZERO;
 
-        if action.pressed(GameAction::MoveUp) {
+        if input.pressed(GameAction::MoveUp) {
             movement.y += 1.0;
         }
-        if action.pressed(GameAction::MoveDown) {
+        if input.pressed(GameAction::MoveDown) {
             movement.y -= 1.0;
         }
-        if action.pressed(GameAction::MoveLeft) {
+        if input.pressed(GameAction::MoveLeft) {
             movement.x -= 1.0;
             transform.scale.x = movement.x.signum() * f32::abs(transform.scale.x);
 
@@ -279,7 +304,7 @@ pub fn move_player(
                 weapon.flip_x = true;
             }
         }
-        if action.pressed(GameAction::MoveRight) {
+        if input.pressed(GameAction::MoveRight) {
             movement.x += 1.0;
             transform.scale.x = movement.x.signum() * f32::abs(transform.scale.x);
 
@@ -296,13 +321,14 @@ pub fn move_player(
         if movement.length_squared() > 1.0 {
             movement = movement.normalize();
         }
+        player.heading = movement;
 
         // Move player at a constant speed
         let mut new_transform = transform.clone();
         new_transform.translation.x +=
-            movement.x * gameplay.player_effects.move_speed * time.delta_seconds();
+            movement.x * gameplay.player_effects.move_speed * time.period.as_secs_f32();
         new_transform.translation.y +=
-            movement.y * gameplay.player_effects.move_speed * time.delta_seconds();
+            movement.y * gameplay.player_effects.move_speed * time.period.as_secs_f32();
 
         if !game_assets.map.0.is_at_border(new_transform) {
             transform.translation = new_transform.translation;
@@ -1,6 +1,8 @@
 use crate::data::abilities::AbilityType;
-use crate::data::hero::HeroType;
+use crate::data::enemy::{Bestiary, BestiaryLoader, EnemyRegistry, BESTIARY_PATH};
+use crate::data::hero::{HeroRegistry, HeroRoster, HeroRosterLoader, HERO_ROSTER_PATH};
 use crate::data::map::MapId;
+use crate::data::wave::{WaveRegistry, WaveTimeline, WaveTimelineLoader};
 use crate::enemy::enemy_type::EnemyType;
 use crate::weapon::weapon_animation_effect::WeaponAnimationEffect;
 use crate::weapon::weapon_type::WeaponType;
@@ -17,17 +19,20 @@ pub struct UiAssets {
     pub checkbox_o: UiImage,
     pub checkbox_x: UiImage,
     pub weapons: HashMap<WeaponType, UiImage>,
-    pub heroes: HashMap<HeroType, UiImage>,
+    /// Filled once the hero roster is loaded
+    pub heroes: HashMap<String, UiImage>,
     pub maps: HashMap<MapId, UiImage>,
     pub abilities: HashMap<AbilityType, UiImage>,
 }
 
 #[derive(Resource)]
 pub struct GameAssets {
-    pub heroes: HashMap<HeroType, Handle<Image>>,
+    /// Filled once the hero roster is loaded
+    pub heroes: HashMap<String, Handle<Image>>,
     pub map: (MapId, Handle<LdtkAsset>),
     pub weapons: HashMap<WeaponType, Handle<TextureAtlas>>,
     pub weapon_animation_effects: HashMap<WeaponAnimationEffect, Handle<TextureAtlas>>,
+    /// Filled once the bestiary is loaded
     pub enemies: HashMap<EnemyType, Handle<TextureAtlas>>,
     pub skull: Handle<TextureAtlas>,
     pub orca: Handle<Image>,
@@ -42,7 +47,24 @@ pub struct AssetsPlugin;
 
 impl Plugin for AssetsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup);
+        app.add_asset::<HeroRoster>()
+            .init_asset_loader::<HeroRosterLoader>()
+            .init_resource::<HeroRegistry>()
+            .add_asset::<Bestiary>()
+            .init_asset_loader::<BestiaryLoader>()
+            .init_resource::<EnemyRegistry>()
+            .add_asset::<WaveTimeline>()
+            .init_asset_loader::<WaveTimelineLoader>()
+            .init_resource::<WaveRegistry>()
+            .add_systems(Startup, setup)
+            .add_systems(
+                Update,
+                (
+                    on_hero_roster_loaded,
+                    on_bestiary_loaded,
+                    on_wave_timeline_loaded,
+                ),
+            );
     }
 }
 
@@ -50,17 +72,22 @@ fn setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut heroes: ResMut<HeroRegistry>,
+    mut enemies: ResMut<EnemyRegistry>,
+    mut waves: ResMut<WaveRegistry>,
 ) {
+    // Hero images and enemy atlases are loaded once their data is
+    heroes.handle = asset_server.load(HERO_ROSTER_PATH);
+    enemies.handle = asset_server.load(BESTIARY_PATH);
+    waves.handles = MapId::iter()
+        .map(|map| (map, asset_server.load(map.get_waves_path())))
+        .collect();
+
     // Load ui image for each weapon
     let weapons: HashMap<WeaponType, UiImage> = WeaponType::iter()
         .map(|weapon| (weapon, asset_server.load(weapon.get_ui_image_name()).into()))
         .collect();
 
-    // Load ui image for each hero
-    let heroes: HashMap<HeroType, UiImage> = HeroType::iter()
-        .map(|hero| (hero, asset_server.load(hero.get_ui_image_name()).into()))
-        .collect();
-
     // Load ui image for each map
     let maps: HashMap<MapId, UiImage> = MapId::iter()
         .map(|map| (map, asset_server.load(map.get_ui_image_name()).into()))
@@ -83,16 +110,11 @@ fn setup(
         checkbox_o: asset_server.load("ui/checkbox_o.png").into(),
         checkbox_x: asset_server.load("ui/checkbox_x.png").into(),
         weapons,
-        heroes,
+        heroes: HashMap::new(),
         maps,
         abilities,
     });
 
-    // Load sprite sheets for each hero
-    let heroes: HashMap<HeroType, Handle<Image>> = HeroType::iter()
-        .map(|hero| (hero, asset_server.load(hero.get_sprite_name()).into()))
-        .collect();
-
     let map_id = MapId::Map1;
     let map_asset = asset_server.load(map_id.get_map_path());
 
@@ -112,16 +134,6 @@ fn setup(
             .map(|anim| (anim, texture_atlases.add(anim.texture_atlas(&asset_server))))
             .collect();
 
-    // Load enemy texture atlases
-    let enemies: HashMap<EnemyType, Handle<TextureAtlas>> = EnemyType::iter()
-        .map(|enemy| {
-            (
-                enemy,
-                texture_atlases.add(enemy.texture_atlas(&asset_server)),
-            )
-        })
-        .collect();
-
     let skull = TextureAtlas::from_grid(
         asset_server.load("sprites/misc/skull.png"),
         Vec2::new(64., 64.),
@@ -132,11 +144,11 @@ fn setup(
     );
 
     commands.insert_resource(GameAssets {
-        heroes,
+        heroes: HashMap::new(),
         map: (map_id, map_asset),
         weapons,
         weapon_animation_effects,
-        enemies,
+        enemies: HashMap::new(),
         skull: texture_atlases.add(skull),
         orca: asset_server.load("sprites/passives/orca.png"),
         whale: asset_server.load("sprites/passives/whale.png"),
@@ -146,3 +158,90 @@ fn setup(
         pickup_coin: asset_server.load("sprites/misc/coin.png"),
     });
 }
+
+/// Take over the heroes of the roster, also when it is edited, and load their images
+fn on_hero_roster_loaded(
+    mut rx_asset: EventReader<AssetEvent<HeroRoster>>,
+    rosters: Res<Assets<HeroRoster>>,
+    asset_server: Res<AssetServer>,
+    mut heroes: ResMut<HeroRegistry>,
+    mut ui_assets: ResMut<UiAssets>,
+    mut game_assets: ResMut<GameAssets>,
+) {
+    for ev in rx_asset.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = ev else {
+            continue;
+        };
+        if *handle != heroes.handle {
+            continue;
+        }
+        let Some(roster) = rosters.get(handle) else {
+            continue;
+        };
+
+        for hero in &roster.heroes {
+            ui_assets.heroes.insert(
+                hero.id.clone(),
+                asset_server.load(hero.ui_image.as_str()).into(),
+            );
+            game_assets
+                .heroes
+                .insert(hero.id.clone(), asset_server.load(hero.sprite.as_str()));
+        }
+        heroes.set_heroes(roster.heroes.clone());
+        info!("Loaded {} heroes", roster.heroes.len());
+    }
+}
+
+/// Take over the enemy archetypes of the bestiary, also when it is edited, and build their atlases
+fn on_bestiary_loaded(
+    mut rx_asset: EventReader<AssetEvent<Bestiary>>,
+    bestiaries: Res<Assets<Bestiary>>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut enemies: ResMut<EnemyRegistry>,
+    mut game_assets: ResMut<GameAssets>,
+) {
+    for ev in rx_asset.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = ev else {
+            continue;
+        };
+        if *handle != enemies.handle {
+            continue;
+        }
+        let Some(bestiary) = bestiaries.get(handle) else {
+            continue;
+        };
+
+        for enemy in &bestiary.enemies {
+            let atlas = enemy.texture_atlas(asset_server.load(enemy.sprite.as_str()));
+            game_assets
+                .enemies
+                .insert(enemy.kind, texture_atlases.add(atlas));
+        }
+        enemies.set_enemies(bestiary.enemies.clone());
+        info!("Loaded {} enemies", bestiary.enemies.len());
+    }
+}
+
+/// Take over the wave timelines, also when they are edited
+fn on_wave_timeline_loaded(
+    mut rx_asset: EventReader<AssetEvent<WaveTimeline>>,
+    timelines: Res<Assets<WaveTimeline>>,
+    mut waves: ResMut<WaveRegistry>,
+) {
+    for ev in rx_asset.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = ev else {
+            continue;
+        };
+        let Some(timeline) = timelines.get(handle) else {
+            continue;
+        };
+
+        waves.set_timeline(handle, timeline);
+        info!(
+            "Loaded a wave timeline with {} phases",
+            timeline.phases.len()
+        );
+    }
+}
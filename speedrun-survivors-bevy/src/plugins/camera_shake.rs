@@ -1,3 +1,5 @@
+use crate::player::player_camera::sync_player_camera;
+use crate::plugins::game_rng::{GameRng, RngStream};
 use crate::state::AppState;
 use bevy::prelude::*;
 use rand::Rng;
@@ -8,7 +10,12 @@ pub struct CameraShakePlugin;
 impl Plugin for CameraShakePlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.add_systems(OnEnter(AppState::GameInitializing), on_enter_game_init)
-            .add_systems(Update, on_update.run_if(in_state(AppState::GameRunning)))
+            .add_systems(
+                Update,
+                on_update
+                    .after(sync_player_camera)
+                    .run_if(in_state(AppState::GameRunning)),
+            )
             .add_event::<CameraImpact>()
             .insert_resource(Shake::create_shake(&CameraImpactStrength::Idle));
     }
@@ -23,6 +30,7 @@ fn on_update(
     mut query_camera: Query<(&Camera2d, &mut Transform)>,
     time: Res<Time>,
     mut rx_impact: EventReader<CameraImpact>,
+    mut game_rng: ResMut<GameRng>,
 ) {
     // If there is a new impact event, reset our data from that, will cancel out the current shake
     if let Some(impact) = rx_impact.iter().last() {
@@ -40,7 +48,7 @@ fn on_update(
     };
 
     // Update shake
-    let mut rng = rand::thread_rng();
+    let rng = game_rng.stream(RngStream::CameraShake);
 
     shake.trauma = f32::max(shake.trauma - shake.decay * time.delta_seconds(), 0.0);
 
@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 
+use crate::plugins::simulation::{SimulationEventApp, SimulationSet};
 use crate::state::AppState;
 
 pub struct CoinRewardsPlugin;
@@ -8,13 +9,9 @@ impl Plugin for CoinRewardsPlugin {
     // TODO maybe merge this into Player Plugin? nothing else gains coins; (Debateable)
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(AppState::GameInitializing), on_enter_game_init)
-            .add_systems(Update, on_update.run_if(in_state(AppState::GameRunning)))
-            .add_event::<CoinAccumulated>()
-            .insert_resource(CoinAccumulator {
-                total_coin: 0,
-                timer: Timer::from_seconds(0.1, TimerMode::Repeating),
-                coin_rate: 1,
-            });
+            .add_systems(FixedUpdate, on_update.in_set(SimulationSet::Rewards))
+            .add_simulation_event::<CoinAccumulated>()
+            .init_resource::<CoinAccumulator>();
     }
 }
 
@@ -26,7 +23,7 @@ fn on_enter_game_init(mut coin_accumulator: ResMut<CoinAccumulator>) {
 fn on_update(
     mut rx_coin: EventReader<CoinAccumulated>,
     mut coin_accumulator: ResMut<CoinAccumulator>,
-    time: Res<Time>,
+    time: Res<FixedTime>,
 ) {
     let mut coins_gained: u64 = 0;
     for ev in rx_coin.iter() {
@@ -34,7 +31,7 @@ fn on_update(
     }
 
     // Issue coins based on timer
-    coin_accumulator.timer.tick(time.delta());
+    coin_accumulator.timer.tick(time.period);
     if coin_accumulator.timer.finished() {
         coins_gained +=
             coin_accumulator.coin_rate * coin_accumulator.timer.times_finished_this_tick() as u64;
@@ -55,3 +52,13 @@ pub struct CoinAccumulator {
     timer: Timer,
     coin_rate: u64,
 }
+
+impl Default for CoinAccumulator {
+    fn default() -> Self {
+        Self {
+            total_coin: 0,
+            timer: Timer::from_seconds(0.1, TimerMode::Repeating),
+            coin_rate: 1,
+        }
+    }
+}
@@ -3,14 +3,19 @@ use bevy::prelude::*;
 use crate::state::{for_game_states, AppState};
 
 use super::health::{Health, HealthUpdateEvent, TargetType};
+use super::simulation::SimulationSet;
 
 pub struct CombatTextPlugin;
 
 impl Plugin for CombatTextPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
+            FixedUpdate,
+            on_health_change_event.in_set(SimulationSet::Damage),
+        )
+        .add_systems(
             Update,
-            (on_health_change_event, float_combat_text).run_if(in_state(AppState::GameRunning)),
+            float_combat_text.run_if(in_state(AppState::GameRunning)),
         );
     }
 }
@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use sha2::{Digest, Sha256};
+
+use crate::menu::MenuGameConfig;
+use crate::state::AppState;
+
+/// All gameplay randomness is derived from a single seed (the session entropy), so a run can be reproduced from its replay
+pub struct GameRngPlugin;
+
+impl Plugin for GameRngPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::GameInitializing), reseed_game_rng)
+            .insert_resource(GameRng::from_entropy(""));
+    }
+}
+
+/// Independent random streams; cosmetic randomness must never draw from a simulation stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RngStream {
+    /// Enemy types and spawn locations
+    Spawning,
+    /// Passive abilities (orcas, rugs, shitcoins, whales)
+    Passives,
+    /// Cosmetic: scattering of dropped pickups
+    PickupScatter,
+    /// Cosmetic: camera shake
+    CameraShake,
+}
+
+#[derive(Resource)]
+pub struct GameRng {
+    seed: [u8; 32],
+    streams: HashMap<RngStream, ChaCha8Rng>,
+}
+
+impl GameRng {
+    pub fn from_entropy(entropy: &str) -> Self {
+        Self {
+            seed: Sha256::digest(entropy.as_bytes()).into(),
+            streams: HashMap::new(),
+        }
+    }
+
+    /// Each stream is a separate ChaCha stream of the same seed
+    pub fn stream(&mut self, stream: RngStream) -> &mut ChaCha8Rng {
+        let seed = self.seed;
+        self.streams.entry(stream).or_insert_with(|| {
+            let mut rng = ChaCha8Rng::from_seed(seed);
+            rng.set_stream(stream as u64);
+            rng
+        })
+    }
+}
+
+/// Seed all streams for the new run; without a session entropy (offline play) a random one is used
+/// The entropy is consumed, so it can never seed more than one run
+pub fn reseed_game_rng(mut game_config: ResMut<MenuGameConfig>, mut rng: ResMut<GameRng>) {
+    let entropy = game_config.entropy.take().unwrap_or_else(|| {
+        rand::thread_rng()
+            .sample_iter(rand::distributions::Alphanumeric)
+            .take(44)
+            .map(char::from)
+            .collect()
+    });
+
+    *rng = GameRng::from_entropy(&entropy);
+}
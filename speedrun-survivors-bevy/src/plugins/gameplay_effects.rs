@@ -1,12 +1,14 @@
 use crate::data::abilities::AbilityType;
-use crate::data::hero::HeroType;
+use crate::data::hero::HeroRegistry;
 use crate::data::item::ItemType;
 use crate::data::level::Level;
 use crate::data::map::MapId;
 use crate::plugins::hud::HudRedraw;
+use crate::plugins::simulation::SimulationSet;
 use crate::state::AppState;
 use bevy::prelude::*;
 use bevy::utils::HashMap;
+use serde::Deserialize;
 use strum::{EnumIter, IntoEnumIterator};
 
 pub struct GameplayEffectsPlugin;
@@ -15,9 +17,20 @@ impl Plugin for GameplayEffectsPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(AppState::GameMenuMain), on_enter_game_main_menu)
             .add_systems(OnEnter(AppState::GameInitializing), on_enter_game_init)
-            .add_systems(Update, on_update)
-            .add_event::<GameplayEffectEvent>()
-            .add_event::<GameplayStatsRecalculatedEvent>()
+            // Effects are applied once per tick, also outside of a run so menu selections are not lost
+            .add_systems(
+                FixedUpdate,
+                (
+                    Events::<GameplayEffectEvent>::update_system,
+                    Events::<GameplayStatsRecalculatedEvent>::update_system,
+                    on_update,
+                )
+                    .chain()
+                    .before(SimulationSet::Input),
+            )
+            .add_systems(FixedUpdate, tick_effects.in_set(SimulationSet::Input))
+            .init_resource::<Events<GameplayEffectEvent>>()
+            .init_resource::<Events<GameplayStatsRecalculatedEvent>>()
             .insert_resource(GameplayEffectPluginState::default());
     }
 }
@@ -28,29 +41,30 @@ fn on_enter_game_main_menu(mut state: ResMut<GameplayEffectPluginState>) {
 }
 
 /// Runs when the game is initializing
-fn on_enter_game_init(mut state: ResMut<GameplayEffectPluginState>) {
+fn on_enter_game_init(mut state: ResMut<GameplayEffectPluginState>, heroes: Res<HeroRegistry>) {
     // If the menu was skipped, we do not have stats, so we call select hero here
     if state.player_effects.move_speed <= 0. {
-        state
-            .player_effects
-            .equip_hero(HeroType::Pepe.get_gameplay_effects())
+        if let Some(hero) = heroes.default_hero() {
+            state.player_effects.equip_hero(hero.get_gameplay_effects())
+        }
     }
 }
 
 fn on_update(
-    time: Res<Time>,
     mut state: ResMut<GameplayEffectPluginState>,
     mut rx_gameplay: EventReader<GameplayEffectEvent>,
     mut tx_recalculated: EventWriter<GameplayStatsRecalculatedEvent>,
     mut tx_hud: EventWriter<HudRedraw>,
+    heroes: Res<HeroRegistry>,
 ) {
     let mut debug_count = 0;
     for ev in rx_gameplay.iter() {
         debug_count += 1;
         match ev {
-            GameplayEffectEvent::HeroSelected(hero) => {
-                state.player_effects.equip_hero(hero.get_gameplay_effects())
-            }
+            GameplayEffectEvent::HeroSelected(id) => match heroes.get(id) {
+                Some(hero) => state.player_effects.equip_hero(hero.get_gameplay_effects()),
+                None => warn!("Selected unknown hero {id}"),
+            },
             GameplayEffectEvent::MapSelected(map) => {
                 state.player_effects.equip_map(map.get_gameplay_effects())
             }
@@ -83,17 +97,21 @@ fn on_update(
         eprintln!("DEBUG EFFECTS {:?}", state.player_effects)
     }
 
-    // Update gameplay tags & temporary effects
-    state.player_tags.tick(time.delta_seconds());
-    state.player_effects.update_temporary(time.delta_seconds());
-
     // Emit a recalculated event
     tx_recalculated.send(GameplayStatsRecalculatedEvent {});
 }
 
+/// Update gameplay tags & temporary effects once per simulation tick
+fn tick_effects(time: Res<FixedTime>, mut state: ResMut<GameplayEffectPluginState>) {
+    state.player_tags.tick(time.period.as_secs_f32());
+    state
+        .player_effects
+        .update_temporary(time.period.as_secs_f32());
+}
+
 #[derive(Debug, Event)]
 pub enum GameplayEffectEvent {
-    HeroSelected(HeroType),
+    HeroSelected(String),
     MapSelected(MapId),
     NFTEquipped(String, ItemType),
     NFTUnEquipped(String),
@@ -133,7 +151,7 @@ impl GameplayEffectOperation {
     }
 }
 
-#[derive(Clone, Copy, Debug, EnumIter, Hash, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, EnumIter, Hash, Eq, PartialEq, Deserialize)]
 pub enum GameplayStat {
     MovementSpeed,
     AttackRate,
@@ -1,6 +1,7 @@
 use crate::enemy::enemy_type::EnemyType;
 use crate::enemy::EnemyEvent;
 use crate::player::{Player, PlayerEvent};
+use crate::plugins::simulation::{interpolate_translation, SimulationEventApp, SimulationSet};
 use crate::state::{for_game_states, AppState};
 use bevy::prelude::*;
 
@@ -12,10 +13,15 @@ pub struct HealthPlugin;
 
 impl Plugin for HealthPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<HealthUpdateEvent>();
+        app.add_simulation_event::<HealthUpdateEvent>();
         app.add_systems(
+            FixedUpdate,
+            (on_health_change_event, regenerate_health).in_set(SimulationSet::Damage),
+        )
+        .add_systems(
             Update,
-            (on_health_change_event, regenerate_health, update_health_bar)
+            update_health_bar
+                .after(interpolate_translation)
                 .run_if(in_state(AppState::GameRunning)),
         );
     }
@@ -108,9 +114,9 @@ fn on_health_change_event(
 }
 
 /// Regenerate health, if applicable
-pub fn regenerate_health(time: Res<Time>, mut health: Query<&mut Health>) {
+pub fn regenerate_health(time: Res<FixedTime>, mut health: Query<&mut Health>) {
     for mut health in health.iter_mut() {
-        health.apply_regen(time.delta_seconds());
+        health.apply_regen(time.period.as_secs_f32());
     }
 }
 
@@ -2,10 +2,12 @@ pub mod assets;
 pub mod camera_shake;
 pub mod coin_rewards;
 pub mod combat_text;
+pub mod game_rng;
 pub mod gameplay_effects;
 pub mod health;
 pub mod hud;
 pub mod pickup;
 pub mod sfx_manager;
+pub mod simulation;
 pub mod status_effect;
 pub mod vfx_manager;
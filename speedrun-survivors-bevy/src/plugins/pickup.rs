@@ -1,9 +1,11 @@
 use crate::player::{Player, PlayerEvent};
 use crate::plugins::assets::GameAssets;
 use crate::plugins::coin_rewards::CoinAccumulated;
+use crate::plugins::game_rng::{GameRng, RngStream};
 use crate::plugins::gameplay_effects::{
     GameplayEffectPluginState, GameplayStat, GameplayStatsRecalculatedEvent,
 };
+use crate::plugins::simulation::{Interpolated, SimulationEventApp, SimulationSet};
 use crate::state::{for_game_states, AppState};
 use bevy::prelude::*;
 use rand::Rng;
@@ -17,10 +19,13 @@ impl Plugin for PickupPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(AppState::GameInitializing), on_enter_game_init)
             .add_systems(
-                Update,
-                (on_update, pickup_magnet, pickup_consume).run_if(in_state(AppState::GameRunning)),
+                FixedUpdate,
+                (
+                    pickup_magnet.in_set(SimulationSet::Movement),
+                    (on_update, pickup_consume).in_set(SimulationSet::Rewards),
+                ),
             )
-            .add_event::<PickupEvent>()
+            .add_simulation_event::<PickupEvent>()
             .insert_resource(PickupPluginState::default());
     }
 }
@@ -38,6 +43,7 @@ fn on_update(
     mut rx_gameplay: EventReader<GameplayStatsRecalculatedEvent>,
     gameplay_state: Res<GameplayEffectPluginState>,
     mut rx_pickup: EventReader<PickupEvent>,
+    mut game_rng: ResMut<GameRng>,
 ) {
     // If there was some recalculate event, update stats
     if rx_gameplay.iter().len() > 0 {
@@ -50,8 +56,21 @@ fn on_update(
     for ev in rx_pickup.iter() {
         match ev {
             PickupEvent::SpawnCoinExpLoc(coin, exp, loc) => {
-                spawn_pickup(&mut commands, *loc, &game_assets, PickupType::Exp(*exp));
-                spawn_pickup(&mut commands, *loc, &game_assets, PickupType::Coin(*coin));
+                let rng = game_rng.stream(RngStream::PickupScatter);
+                spawn_pickup(
+                    &mut commands,
+                    rng,
+                    *loc,
+                    &game_assets,
+                    PickupType::Exp(*exp),
+                );
+                spawn_pickup(
+                    &mut commands,
+                    rng,
+                    *loc,
+                    &game_assets,
+                    PickupType::Coin(*coin),
+                );
             }
         }
     }
@@ -59,7 +78,7 @@ fn on_update(
 
 /// This function handles moving pickups towards the player
 fn pickup_magnet(
-    time: Res<Time>,
+    time: Res<FixedTime>,
     pickup_state: Res<PickupPluginState>,
     player: Query<&Transform, (With<Player>, Without<Pickup>)>,
     mut pickups: Query<&mut Transform, (With<Pickup>, Without<Player>)>,
@@ -75,8 +94,9 @@ fn pickup_magnet(
             continue;
         }
 
-        let moving =
-            Vec3::normalize(player - tf.translation) * PICKUP_MOVE_SPEED * time.delta_seconds();
+        let moving = Vec3::normalize(player - tf.translation)
+            * PICKUP_MOVE_SPEED
+            * time.period.as_secs_f32();
 
         tf.translation += moving;
     }
@@ -119,12 +139,12 @@ fn pickup_consume(
 
 fn spawn_pickup(
     commands: &mut Commands,
+    rng: &mut impl Rng,
     location: Vec3,
     game_assets: &Res<GameAssets>,
     kind: PickupType,
 ) {
     // Modify location with some randomness
-    let mut rng = rand::thread_rng();
     let mut spawn_transform = Transform::from_translation(location);
     spawn_transform.translation.x += rng.gen_range(-20.0..20.0);
     spawn_transform.translation.y += rng.gen_range(-20.0..20.0);
@@ -141,7 +161,8 @@ fn spawn_pickup(
             },
             for_game_states(),
         ))
-        .insert(Pickup { kind });
+        .insert(Pickup { kind })
+        .insert(Interpolated::default());
 }
 
 #[derive(Event)]
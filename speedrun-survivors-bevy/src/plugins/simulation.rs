@@ -0,0 +1,215 @@
+use bevy::ecs::event::Event;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use leafwing_input_manager::prelude::*;
+use speedrun_survivors_replay::TICKS_PER_SECOND;
+use strum::{EnumIter, IntoEnumIterator};
+
+use crate::player::Player;
+use crate::replay::quantize_aim;
+use crate::state::AppState;
+use crate::GameAction;
+
+/// The area around the player that gameplay treats as on screen
+/// Must not depend on the actual window, or the same replay would play out differently per machine
+pub const SIMULATED_VIEWPORT: Vec2 = Vec2::new(1920., 1080.);
+
+/// Runs the gameplay at a fixed tick rate, independent of the frame rate
+/// Rendering interpolates the position of moving entities between the last two ticks
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        for set in SimulationSet::iter() {
+            app.configure_set(FixedUpdate, set.run_if(simulation_running));
+        }
+
+        app.configure_sets(
+            FixedUpdate,
+            (
+                SimulationSet::Input,
+                SimulationSet::Movement,
+                SimulationSet::Attacks,
+                SimulationSet::Damage,
+                SimulationSet::Death,
+                SimulationSet::Rewards,
+            )
+                .chain(),
+        )
+        .add_systems(OnEnter(AppState::GameInitializing), on_enter_game_init)
+        .add_systems(
+            FixedUpdate,
+            (init_interpolation, restore_simulated_translation)
+                .chain()
+                .before(SimulationSet::Input)
+                .run_if(simulation_running),
+        )
+        .add_systems(
+            FixedUpdate,
+            (sample_aim, sample_input)
+                .chain()
+                .in_set(SimulationSet::Input),
+        )
+        .add_systems(
+            FixedUpdate,
+            store_simulated_translation
+                .after(SimulationSet::Rewards)
+                .run_if(simulation_running),
+        )
+        .add_systems(
+            Update,
+            (init_interpolation, interpolate_translation)
+                .chain()
+                .run_if(in_state(AppState::GameRunning)),
+        )
+        .insert_resource(FixedTime::new_from_secs(1. / TICKS_PER_SECOND as f32))
+        .insert_resource(SimulationInput::default());
+    }
+}
+
+/// The stages of a simulation tick, in the order they run
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
+pub enum SimulationSet {
+    /// Sample the player input for this tick
+    Input,
+    /// Spawn and move entities
+    Movement,
+    /// Fire weapons and passives, detect hits
+    Attacks,
+    /// Apply health changes and status effects
+    Damage,
+    /// Remove whatever died
+    Death,
+    /// Hand out exp and coins
+    Rewards,
+}
+
+/// Ticks only run while the game is running, and stop as soon as a state change is queued
+/// Otherwise a frame that catches up on several ticks would keep playing past a level up or death
+pub fn simulation_running(
+    state: Res<State<AppState>>,
+    next_state: Res<NextState<AppState>>,
+) -> bool {
+    *state.get() == AppState::GameRunning && next_state.0.is_none()
+}
+
+pub trait SimulationEventApp {
+    /// Registers an event that is kept for two simulation ticks rather than two frames
+    /// Regular events sent by one tick may be dropped before the next tick runs on a fast machine
+    fn add_simulation_event<T: Event>(&mut self) -> &mut Self;
+}
+
+impl SimulationEventApp for App {
+    fn add_simulation_event<T: Event>(&mut self) -> &mut Self {
+        if !self.world.contains_resource::<Events<T>>() {
+            self.init_resource::<Events<T>>().add_systems(
+                FixedUpdate,
+                Events::<T>::update_system
+                    .before(SimulationSet::Input)
+                    .run_if(simulation_running),
+            );
+        }
+        self
+    }
+}
+
+/// The player input as seen by the current simulation tick
+#[derive(Resource, Default)]
+pub struct SimulationInput {
+    /// Quantized aim direction relative to the player; see `quantize_aim`
+    pub aim: Option<u8>,
+    pressed: Vec<GameAction>,
+    previous: Vec<GameAction>,
+}
+
+impl SimulationInput {
+    pub fn pressed(&self, action: GameAction) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    /// Pressed on this tick, but not on the previous one
+    pub fn just_pressed(&self, action: GameAction) -> bool {
+        self.pressed(action) && !self.previous.contains(&action)
+    }
+}
+
+/// Smooths the rendered location of an entity moved by the simulation
+#[derive(Component, Default)]
+pub struct Interpolated {
+    previous: Vec3,
+    current: Vec3,
+}
+
+fn on_enter_game_init(mut input: ResMut<SimulationInput>) {
+    *input = SimulationInput::default();
+}
+
+/// Take a snapshot of the input once per tick
+pub fn sample_input(mut input: ResMut<SimulationInput>, actions: Query<&ActionState<GameAction>>) {
+    let action = actions.single();
+
+    input.previous = std::mem::take(&mut input.pressed);
+    input.pressed = action.get_pressed();
+}
+
+/// Aim from the player towards the cursor; the last aim is kept while the cursor is outside the window
+pub fn sample_aim(
+    mut input: ResMut<SimulationInput>,
+    primary_query: Query<&Window, With<PrimaryWindow>>,
+    query_camera: Query<(&Camera, &GlobalTransform)>,
+    query_player: Query<&Transform, With<Player>>,
+) {
+    let (Ok(window), Ok((camera, camera_transform)), Ok(player)) = (
+        primary_query.get_single(),
+        query_camera.get_single(),
+        query_player.get_single(),
+    ) else {
+        return;
+    };
+
+    let cursor_world_position = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
+        .map(|ray| ray.origin.truncate());
+
+    if let Some(cursor_world_position) = cursor_world_position {
+        input.aim = Some(quantize_aim(
+            cursor_world_position - player.translation.truncate(),
+        ));
+    }
+}
+
+/// New entities start out at rest
+fn init_interpolation(mut query: Query<(&Transform, &mut Interpolated), Added<Interpolated>>) {
+    for (transform, mut interpolated) in query.iter_mut() {
+        interpolated.previous = transform.translation;
+        interpolated.current = transform.translation;
+    }
+}
+
+/// Undo the interpolation, so the tick continues from the simulated location
+fn restore_simulated_translation(mut query: Query<(&mut Transform, &mut Interpolated)>) {
+    for (mut transform, mut interpolated) in query.iter_mut() {
+        interpolated.previous = interpolated.current;
+        transform.translation = interpolated.current;
+    }
+}
+
+/// Remember where the tick left each entity
+fn store_simulated_translation(mut query: Query<(&Transform, &mut Interpolated)>) {
+    for (transform, mut interpolated) in query.iter_mut() {
+        interpolated.current = transform.translation;
+    }
+}
+
+/// Render each entity between its last two simulated locations
+pub fn interpolate_translation(
+    time: Res<FixedTime>,
+    mut query: Query<(&mut Transform, &Interpolated)>,
+) {
+    let alpha = (time.accumulated().as_secs_f32() / time.period.as_secs_f32()).min(1.);
+
+    for (mut transform, interpolated) in query.iter_mut() {
+        transform.translation = interpolated.previous.lerp(interpolated.current, alpha);
+    }
+}
@@ -1,11 +1,18 @@
 use bevy::prelude::*;
 
+use crate::plugins::simulation::{SimulationEventApp, SimulationSet};
+
 pub struct StatusEffectPlugin;
 
 impl Plugin for StatusEffectPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<StatusEffectEvent>()
-            .add_systems(Update, (apply_status_effects, on_status_effect_event));
+        app.add_simulation_event::<StatusEffectEvent>().add_systems(
+            FixedUpdate,
+            (
+                apply_status_effects.in_set(SimulationSet::Movement),
+                on_status_effect_event.in_set(SimulationSet::Damage),
+            ),
+        );
     }
 }
 
@@ -40,7 +47,7 @@ pub enum StatusEffectType {
     DeathIsTemporary,
 }
 
-fn on_status_effect_event(
+pub fn on_status_effect_event(
     mut rx_status: EventReader<StatusEffectEvent>,
     mut status_effect_query: Query<&mut StatusEffectController>,
 ) {
@@ -63,7 +70,7 @@ fn on_status_effect_event(
 }
 
 pub fn apply_status_effects(
-    time: Res<Time>,
+    time: Res<FixedTime>,
     mut query: Query<(&mut StatusEffectController, &mut Transform, Entity)>,
     mut tx_status: EventWriter<StatusEffectEvent>,
 ) {
@@ -72,10 +79,10 @@ pub fn apply_status_effects(
             match status_effect.effect_type {
                 StatusEffectType::Knockback(knockback) => {
                     transform.translation +=
-                        knockback / status_effect.duration * time.delta_seconds();
+                        knockback / status_effect.duration * time.period.as_secs_f32();
                     transform.translation.z = 0.;
 
-                    status_effect.current_duration -= time.delta_seconds();
+                    status_effect.current_duration -= time.period.as_secs_f32();
 
                     if status_effect.current_duration <= 0. {
                         tx_status.send(StatusEffectEvent {
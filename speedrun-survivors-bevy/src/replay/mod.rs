@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+use speedrun_survivors_replay::ReplayAction;
+use std::f32::consts::TAU;
+use strum::IntoEnumIterator;
+
+use crate::data::abilities::AbilityType;
+use crate::GameAction;
+
+pub mod playback;
+pub mod recorder;
+
+/// Level up choices are stored as a press and release of these slots on the same tick
+const LEVEL_UP_SLOTS: [ReplayAction; 6] = [
+    ReplayAction::Slot1,
+    ReplayAction::Slot2,
+    ReplayAction::Slot3,
+    ReplayAction::Slot4,
+    ReplayAction::Slot5,
+    ReplayAction::Slot6,
+];
+
+impl From<ReplayAction> for GameAction {
+    fn from(action: ReplayAction) -> Self {
+        match action {
+            ReplayAction::MoveUp => GameAction::MoveUp,
+            ReplayAction::MoveLeft => GameAction::MoveLeft,
+            ReplayAction::MoveDown => GameAction::MoveDown,
+            ReplayAction::MoveRight => GameAction::MoveRight,
+            ReplayAction::Slot1 => GameAction::Slot1,
+            ReplayAction::Slot2 => GameAction::Slot2,
+            ReplayAction::Slot3 => GameAction::Slot3,
+            ReplayAction::Slot4 => GameAction::Slot4,
+            ReplayAction::Slot5 => GameAction::Slot5,
+            ReplayAction::Slot6 => GameAction::Slot6,
+            ReplayAction::Action1 => GameAction::Action1,
+            ReplayAction::Action2 => GameAction::Action2,
+            ReplayAction::Action3 => GameAction::Action3,
+            ReplayAction::Cancel => GameAction::Cancel,
+            ReplayAction::Confirm => GameAction::Confirm,
+            ReplayAction::Pause => GameAction::Pause,
+            ReplayAction::HackLevelUp => GameAction::HackLevelUp,
+        }
+    }
+}
+
+impl From<GameAction> for ReplayAction {
+    fn from(action: GameAction) -> Self {
+        match action {
+            GameAction::MoveUp => ReplayAction::MoveUp,
+            GameAction::MoveLeft => ReplayAction::MoveLeft,
+            GameAction::MoveDown => ReplayAction::MoveDown,
+            GameAction::MoveRight => ReplayAction::MoveRight,
+            GameAction::Slot1 => ReplayAction::Slot1,
+            GameAction::Slot2 => ReplayAction::Slot2,
+            GameAction::Slot3 => ReplayAction::Slot3,
+            GameAction::Slot4 => ReplayAction::Slot4,
+            GameAction::Slot5 => ReplayAction::Slot5,
+            GameAction::Slot6 => ReplayAction::Slot6,
+            GameAction::Action1 => ReplayAction::Action1,
+            GameAction::Action2 => ReplayAction::Action2,
+            GameAction::Action3 => ReplayAction::Action3,
+            GameAction::Cancel => ReplayAction::Cancel,
+            GameAction::Confirm => ReplayAction::Confirm,
+            GameAction::Pause => ReplayAction::Pause,
+            GameAction::HackLevelUp => ReplayAction::HackLevelUp,
+        }
+    }
+}
+
+/// Quantize an aim direction into the 256 increments used by the replay format
+pub fn quantize_aim(direction: Vec2) -> u8 {
+    let angle = direction.y.atan2(direction.x).rem_euclid(TAU);
+    ((angle / TAU * 256.).round() as u32 % 256) as u8
+}
+
+/// Unit direction of a quantized aim
+pub fn dequantize_aim(aim: u8) -> Vec2 {
+    let angle = aim as f32 / 256. * TAU;
+    Vec2::new(angle.cos(), angle.sin())
+}
+
+/// The slot a level up choice is recorded as, if there is one for it
+pub fn level_up_slot(ability: AbilityType) -> Option<ReplayAction> {
+    let idx = AbilityType::iter().position(|other| other == ability)?;
+    LEVEL_UP_SLOTS.get(idx).copied()
+}
+
+/// The level up choice recorded as the given slot
+pub fn level_up_choice(slot: ReplayAction) -> Option<AbilityType> {
+    let idx = LEVEL_UP_SLOTS.iter().position(|other| *other == slot)?;
+    AbilityType::iter().nth(idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_action_maps_both_ways() {
+        for action in ReplayAction::iter() {
+            assert_eq!(ReplayAction::from(GameAction::from(action)), action);
+        }
+    }
+
+    #[test]
+    fn aim_round_trips() {
+        for aim in 0..=u8::MAX {
+            assert_eq!(quantize_aim(dequantize_aim(aim)), aim);
+        }
+    }
+
+    #[test]
+    fn level_up_choices_round_trip() {
+        for ability in AbilityType::iter() {
+            let slot = level_up_slot(ability).expect("every ability has a slot");
+            assert_eq!(level_up_choice(slot), Some(ability));
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use speedrun_survivors_replay::{Replay, ReplayAction, ReplayInput, TICKS_PER_SECOND};
+use std::io::Read;
+
+use crate::menu::MenuGameConfig;
+use crate::player::PlayerEvent;
+use crate::plugins::game_rng::reseed_game_rng;
+use crate::plugins::simulation::{sample_aim, sample_input, SimulationInput, SimulationSet};
+use crate::replay::level_up_choice;
+use crate::state::{for_game_states, AppState, ForState};
+use crate::GameAction;
+
+/// Simulation speed while jumping to a tick
+const JUMP_SPEED: f32 = 16.;
+
+/// Drives a run from a recorded replay instead of the keyboard, mouse and gamepad
+/// Controls: Space pause, Tab toggle 1x / 2x / 4x speed, type a tick and press G to jump to it
+pub struct ReplayPlaybackPlugin;
+
+impl Plugin for ReplayPlaybackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_replay_from_args)
+            .add_systems(
+                OnEnter(AppState::GameInitializing),
+                on_enter_game_init.before(reseed_game_rng),
+            )
+            .add_systems(
+                FixedUpdate,
+                apply_replay_inputs
+                    .after(sample_aim)
+                    .before(sample_input)
+                    .in_set(SimulationSet::Input)
+                    .run_if(playback_active),
+            )
+            .add_systems(
+                Update,
+                (
+                    playback_controls.run_if(resource_exists::<Input<KeyCode>>()),
+                    restart_playback.after(playback_controls),
+                    update_playback_text,
+                )
+                    .run_if(in_state(AppState::GameRunning))
+                    .run_if(playback_active),
+            )
+            .add_systems(
+                OnEnter(AppState::GameLevelUp),
+                apply_level_up_choice.run_if(playback_active),
+            )
+            .add_systems(OnEnter(AppState::GameOver), stop_playback)
+            .add_systems(OnEnter(AppState::SplashScreen), stop_playback)
+            .insert_resource(ReplayPlayback::default());
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ReplayPlayback {
+    /// The replay to play back on the next run
+    pub replay: Option<Replay>,
+    /// The session entropy the replay was recorded with
+    pub entropy: String,
+    active: bool,
+    tick: u32,
+    cursor: usize,
+    aim: Option<u8>,
+    paused: bool,
+    speed: f32,
+    jump_target: Option<u32>,
+    restarting: bool,
+    jump_input: String,
+    input_map: Option<InputMap<GameAction>>,
+}
+
+impl ReplayPlayback {
+    /// Read replay data from a file, or from stdin when the path is `-`
+    pub fn read(path: &str) -> std::io::Result<Vec<u8>> {
+        match path {
+            "-" => {
+                let mut data = Vec::new();
+                std::io::stdin().read_to_end(&mut data)?;
+                Ok(data)
+            }
+            path => std::fs::read(path),
+        }
+    }
+
+    /// Replays can be given as raw binary or as base64 text
+    pub fn load(data: &[u8]) -> anyhow::Result<Replay> {
+        Replay::decode(data)
+            .or_else(|_| Replay::from_base64(String::from_utf8(data.to_vec())?.trim()))
+    }
+
+    /// Fast forward to the given tick, restarting the run first if it was already passed
+    pub fn jump_to(&mut self, tick: u32) {
+        if tick <= self.tick {
+            self.restarting = true;
+        }
+        self.jump_target = Some(tick);
+    }
+}
+
+#[derive(Component)]
+struct PlaybackText;
+
+fn playback_active(playback: Res<ReplayPlayback>) -> bool {
+    playback.active
+}
+
+/// Load a replay given as `--replay <file> --seed <entropy>` on the command line
+fn load_replay_from_args(mut playback: ResMut<ReplayPlayback>) {
+    // Already provided, i.e. by the headless simulator
+    if playback.replay.is_some() {
+        return;
+    }
+
+    let args: Vec<String> = std::env::args().collect();
+    let arg = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|idx| args.get(idx + 1))
+    };
+    let Some(path) = arg("--replay") else {
+        return;
+    };
+    playback.entropy = arg("--seed").cloned().unwrap_or_default();
+
+    match ReplayPlayback::read(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|data| ReplayPlayback::load(&data))
+    {
+        Ok(replay) => {
+            info!("Loaded replay {path} ({} ticks)", replay.end_tick);
+            playback.replay = Some(replay);
+        }
+        Err(e) => error!("Failed to load replay {path}: {e:?}"),
+    }
+}
+
+/// Start playback and take the input away from the player
+fn on_enter_game_init(
+    mut commands: Commands,
+    mut playback: ResMut<ReplayPlayback>,
+    mut game_config: ResMut<MenuGameConfig>,
+    query_input: Query<(Entity, &InputMap<GameAction>)>,
+) {
+    if playback.replay.is_none() {
+        return;
+    }
+
+    // Same seed as the recorded run
+    game_config.entropy = Some(playback.entropy.clone());
+
+    if let Ok((entity, input_map)) = query_input.get_single() {
+        playback.input_map = Some(input_map.clone());
+        commands.entity(entity).remove::<InputMap<GameAction>>();
+    }
+
+    playback.active = true;
+    playback.tick = 0;
+    playback.cursor = 0;
+    playback.aim = None;
+
+    // A restart keeps the controls and the jump target it was started for
+    if !std::mem::take(&mut playback.restarting) {
+        playback.paused = false;
+        playback.speed = 1.;
+        playback.jump_target = None;
+        playback.jump_input.clear();
+    }
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 30.0,
+                color: Color::rgb(1.0, 0.5, 0.5),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.),
+            left: Val::Px(10.),
+            ..default()
+        }),
+        PlaybackText,
+        for_game_states(),
+    ));
+}
+
+/// Inject all replay events of the current tick
+pub fn apply_replay_inputs(
+    mut playback: ResMut<ReplayPlayback>,
+    mut actions: Query<&mut ActionState<GameAction>>,
+    mut input: ResMut<SimulationInput>,
+) {
+    let mut action_state = actions.single_mut();
+    let playback = &mut *playback;
+    let Some(replay) = &playback.replay else {
+        return;
+    };
+
+    while let Some(event) = replay.events.get(playback.cursor) {
+        if event.tick > playback.tick {
+            break;
+        }
+        playback.cursor += 1;
+
+        match event.input {
+            // Pausing is handled by the playback controls, the recorded pause would stall the run
+            ReplayInput::Press(ReplayAction::Pause) | ReplayInput::Release(ReplayAction::Pause) => {
+            }
+            ReplayInput::Press(action) => action_state.press(action.into()),
+            ReplayInput::Release(action) => action_state.release(action.into()),
+            ReplayInput::Aim(aim) => playback.aim = Some(aim),
+        }
+    }
+
+    // The recorded aim replaces the cursor
+    input.aim = playback.aim;
+    playback.tick += 1;
+}
+
+/// Pick the recorded level up choice and continue right away
+/// The choice is the press and release of a slot on the upcoming tick, see `record_level_up_choice`
+fn apply_level_up_choice(
+    playback: Res<ReplayPlayback>,
+    mut tx_player: EventWriter<PlayerEvent>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Some(replay) = &playback.replay else {
+        return;
+    };
+
+    let upcoming = replay.events[playback.cursor..]
+        .iter()
+        .take_while(|event| event.tick <= playback.tick)
+        .collect::<Vec<_>>();
+    let choice = upcoming
+        .windows(2)
+        .find_map(|pair| match (pair[0].input, pair[1].input) {
+            (ReplayInput::Press(pressed), ReplayInput::Release(released))
+                if pressed == released =>
+            {
+                level_up_choice(pressed)
+            }
+            _ => None,
+        });
+
+    match choice {
+        Some(ability) => tx_player.send(PlayerEvent::AbilityUpgrade(ability)),
+        None => warn!("No level up choice recorded for tick {}", playback.tick),
+    }
+    next_state.set(AppState::GameRunning);
+}
+
+fn playback_controls(
+    mut playback: ResMut<ReplayPlayback>,
+    mut time: ResMut<Time>,
+    keys: Res<Input<KeyCode>>,
+    mut rx_chars: EventReader<ReceivedCharacter>,
+) {
+    if keys.just_pressed(KeyCode::Space) {
+        playback.paused = !playback.paused;
+    }
+    if keys.just_pressed(KeyCode::Tab) {
+        playback.speed = match playback.speed as u32 {
+            1 => 2.,
+            2 => 4.,
+            _ => 1.,
+        };
+    }
+
+    // Tick number entry for jumping
+    for ev in rx_chars.iter() {
+        if ev.char.is_ascii_digit() {
+            playback.jump_input.push(ev.char);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        playback.jump_input.pop();
+    }
+    if keys.just_pressed(KeyCode::G) {
+        if let Ok(tick) = playback.jump_input.parse() {
+            playback.jump_to(tick);
+        }
+        playback.jump_input.clear();
+    }
+
+    // Jump complete? A restart still has to rewind the tick
+    if !playback.restarting
+        && playback
+            .jump_target
+            .is_some_and(|target| playback.tick >= target)
+    {
+        playback.jump_target = None;
+    }
+
+    let speed = match playback.jump_target {
+        Some(_) => JUMP_SPEED,
+        None => playback.speed,
+    };
+    time.set_relative_speed(speed);
+    match playback.paused && playback.jump_target.is_none() {
+        true => time.pause(),
+        false => time.unpause(),
+    }
+}
+
+/// Play the run again from the start with the same entropy, to jump back in time
+fn restart_playback(
+    mut commands: Commands,
+    playback: Res<ReplayPlayback>,
+    mut actions: Query<&mut ActionState<GameAction>>,
+    query_entities: Query<(Entity, &ForState<AppState>)>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if !playback.restarting {
+        return;
+    }
+
+    // Staying within the game states keeps the run from being torn down, so do it here
+    for (entity, for_state) in &query_entities {
+        if for_state.states.contains(&AppState::GameRunning) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    actions.single_mut().release_all();
+    next_state.set(AppState::GameInitializing);
+}
+
+fn update_playback_text(
+    playback: Res<ReplayPlayback>,
+    mut query: Query<&mut Text, With<PlaybackText>>,
+) {
+    let Ok(mut text) = query.get_single_mut() else {
+        return;
+    };
+    let end_tick = playback.replay.as_ref().map_or(0, |replay| replay.end_tick);
+
+    let mut value = format!(
+        "REPLAY {:.1}s  tick {} / {}  {}x",
+        playback.tick as f32 / TICKS_PER_SECOND as f32,
+        playback.tick,
+        end_tick,
+        playback.speed
+    );
+    if playback.paused {
+        value.push_str("  PAUSED");
+    }
+    if let Some(target) = playback.jump_target {
+        value.push_str(&format!("  jumping to {target}"));
+    } else if !playback.jump_input.is_empty() {
+        value.push_str(&format!("  goto {}_", playback.jump_input));
+    }
+    text.sections[0].value = value;
+}
+
+/// Give the input back to the player
+fn stop_playback(
+    mut commands: Commands,
+    mut playback: ResMut<ReplayPlayback>,
+    mut time: ResMut<Time>,
+    query_input: Query<Entity, With<ActionState<GameAction>>>,
+) {
+    if !playback.active {
+        return;
+    }
+    playback.active = false;
+
+    if let (Some(input_map), Ok(entity)) = (playback.input_map.take(), query_input.get_single()) {
+        commands.entity(entity).insert(input_map);
+    }
+
+    time.set_relative_speed(1.);
+    time.unpause();
+}
@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use speedrun_survivors_replay::{Replay, ReplayInput};
+
+use crate::player::PlayerEvent;
+use crate::plugins::simulation::{sample_input, SimulationInput, SimulationSet};
+use crate::replay::level_up_slot;
+use crate::state::AppState;
+use crate::GameAction;
+
+/// Records the player input of a run so it can be verified and played back later
+pub struct ReplayRecorderPlugin;
+
+impl Plugin for ReplayRecorderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::GameInitializing), on_enter_game_init)
+            .add_systems(
+                FixedUpdate,
+                record_inputs
+                    .after(sample_input)
+                    .in_set(SimulationSet::Input),
+            )
+            .add_systems(OnExit(AppState::GameLevelUp), record_level_up_choice)
+            .add_systems(OnEnter(AppState::GameOver), on_enter_game_over)
+            .insert_resource(ReplayRecorder::default());
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ReplayRecorder {
+    /// The simulation tick of the current run
+    pub tick: u32,
+    /// Recorded input transitions
+    pub replay: Replay,
+    /// Base64 encoded replay; available once the run is over and submitted as the `replay` of
+    /// `GameCompleteRequest` by the online session
+    pub exported: Option<String>,
+    pressed: Vec<GameAction>,
+    aim: Option<u8>,
+}
+
+fn on_enter_game_init(mut recorder: ResMut<ReplayRecorder>) {
+    *recorder = ReplayRecorder::default();
+}
+
+/// Sample the input once per simulation tick and store every change
+pub fn record_inputs(
+    mut recorder: ResMut<ReplayRecorder>,
+    actions: Query<&ActionState<GameAction>>,
+    input: Res<SimulationInput>,
+) {
+    let action = actions.single();
+    let tick = recorder.tick;
+
+    for game_action in GameAction::variants() {
+        let was_pressed = recorder.pressed.contains(&game_action);
+        match (action.pressed(game_action), was_pressed) {
+            (true, false) => {
+                recorder.pressed.push(game_action);
+                recorder
+                    .replay
+                    .push(tick, ReplayInput::Press(game_action.into()));
+            }
+            (false, true) => {
+                recorder.pressed.retain(|pressed| *pressed != game_action);
+                recorder
+                    .replay
+                    .push(tick, ReplayInput::Release(game_action.into()));
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(aim) = input.aim.filter(|aim| recorder.aim != Some(*aim)) {
+        recorder.aim = Some(aim);
+        recorder.replay.push(tick, ReplayInput::Aim(aim));
+    }
+
+    recorder.tick += 1;
+}
+
+/// Level up choices are made between ticks, so there is no input to sample for them
+/// They are stored as a press and release of the matching slot on the tick they take effect;
+/// sampled input can never change twice within one tick
+fn record_level_up_choice(
+    mut recorder: ResMut<ReplayRecorder>,
+    mut rx_player: EventReader<PlayerEvent>,
+) {
+    let tick = recorder.tick;
+
+    for ev in rx_player.iter() {
+        if let PlayerEvent::AbilityUpgrade(ability) = ev {
+            let Some(slot) = level_up_slot(*ability) else {
+                error!("No replay slot to record the level up choice {ability:?} as");
+                continue;
+            };
+            recorder.replay.push(tick, ReplayInput::Press(slot));
+            recorder.replay.push(tick, ReplayInput::Release(slot));
+        }
+    }
+}
+
+impl ReplayRecorder {
+    /// End the replay on the current tick and export it
+    pub fn seal(&mut self) {
+        self.replay.end_tick = self.tick;
+
+        match self.replay.to_base64() {
+            Ok(exported) => self.exported = Some(exported),
+            Err(e) => error!("Failed to export replay: {e:?}"),
+        }
+    }
+}
+
+/// The run is over; seal the replay so it can be submitted
+fn on_enter_game_over(mut recorder: ResMut<ReplayRecorder>) {
+    recorder.seal();
+}
@@ -0,0 +1,18 @@
+use bevy::log::warn;
+use std::sync::{Arc, OnceLock};
+
+use crate::wallet::Signer;
+
+static BROWSER_WALLET: OnceLock<Arc<dyn Signer>> = OnceLock::new();
+
+/// Hook for the web page: register the signer that talks to the browser wallet before the app runs
+/// Systems sign synchronously, so the page has to hand over a signer that does not wait on the user
+pub fn register_browser_wallet(signer: Arc<dyn Signer>) {
+    if BROWSER_WALLET.set(signer).is_err() {
+        warn!("A browser wallet was registered already");
+    }
+}
+
+pub(crate) fn browser_wallet() -> Option<Arc<dyn Signer>> {
+    BROWSER_WALLET.get().cloned()
+}
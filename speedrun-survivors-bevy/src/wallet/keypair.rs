@@ -0,0 +1,193 @@
+use anyhow::{bail, Context, Result};
+use bevy::log::info;
+use ed25519_dalek::{Signer as _, SigningKey};
+use rand::rngs::OsRng;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::wallet::Signer;
+
+/// Env variable with the path of the keypair file; `--keypair <path>` takes precedence
+const KEYPAIR_PATH_ENV: &str = "SPEEDRUN_KEYPAIR";
+
+/// Signs with a local ed25519 keypair, stored like the Solana CLI does: a JSON array of 64 bytes
+pub struct KeypairSigner {
+    signing_key: SigningKey,
+    pubkey: String,
+}
+
+impl KeypairSigner {
+    /// Load the keypair at `path`, or generate and save a new one if there is none
+    pub fn load_or_generate(path: &Path) -> Result<Self> {
+        let signing_key = match path.exists() {
+            true => read_keypair(path)?,
+            false => {
+                let signing_key = SigningKey::generate(&mut OsRng);
+                write_keypair(path, &signing_key)?;
+                info!("Generated a new keypair at {}", path.display());
+                signing_key
+            }
+        };
+
+        Ok(Self {
+            pubkey: bs58::encode(signing_key.verifying_key().as_bytes()).into_string(),
+            signing_key,
+        })
+    }
+
+    /// `--keypair <path>`, the env variable, or a keypair of our own in the user config dir
+    /// Never defaults to the Solana CLI wallet, it may hold funds
+    pub fn path_from_args() -> PathBuf {
+        let args: Vec<String> = std::env::args().collect();
+        if let Some(path) = args
+            .iter()
+            .position(|arg| arg == "--keypair")
+            .and_then(|idx| args.get(idx + 1))
+        {
+            return PathBuf::from(path);
+        }
+        if let Ok(path) = std::env::var(KEYPAIR_PATH_ENV) {
+            return PathBuf::from(path);
+        }
+
+        std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(|home| {
+                PathBuf::from(home)
+                    .join(".config")
+                    .join("speedrun-survivors")
+            })
+            .unwrap_or_default()
+            .join("keypair.json")
+    }
+}
+
+impl Signer for KeypairSigner {
+    fn pubkey(&self) -> String {
+        self.pubkey.clone()
+    }
+
+    fn sign(&self, message: &str) -> Result<String> {
+        let signature = self.signing_key.sign(message.as_bytes());
+        Ok(bs58::encode(signature.to_bytes()).into_string())
+    }
+}
+
+fn read_keypair(path: &Path) -> Result<SigningKey> {
+    let data = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read keypair {}", path.display()))?;
+    let bytes: Vec<u8> = serde_json::from_str(&data)
+        .with_context(|| format!("Invalid keypair file {}", path.display()))?;
+    let Ok(bytes) = <[u8; 64]>::try_from(bytes.as_slice()) else {
+        bail!("Keypair {} must hold 64 bytes", path.display());
+    };
+
+    // Also checks that the public half belongs to the secret half
+    SigningKey::from_keypair_bytes(&bytes)
+        .with_context(|| format!("Invalid keypair {}", path.display()))
+}
+
+fn write_keypair(path: &Path, signing_key: &SigningKey) -> Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+
+    let data = serde_json::to_string(&signing_key.to_keypair_bytes().to_vec())?;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    // The secret key must only be readable by the user
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(data.as_bytes()))
+        .with_context(|| format!("Failed to write keypair {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use speedrun_survivors_model::{start_game_message, verify_signature};
+
+    /// An empty directory of its own for each test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("keypair-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn generated_keypair_is_loaded_again() {
+        let path = temp_dir("reload").join("nested").join("keypair.json");
+        let generated = KeypairSigner::load_or_generate(&path).unwrap();
+        assert!(path.exists());
+
+        let loaded = KeypairSigner::load_or_generate(&path).unwrap();
+        assert_eq!(loaded.pubkey(), generated.pubkey());
+        assert_eq!(bs58::decode(loaded.pubkey()).into_vec().unwrap().len(), 32);
+    }
+
+    #[test]
+    fn malformed_keypair_files_are_errors() {
+        let dir = temp_dir("malformed");
+        let mismatched = {
+            let mut bytes = SigningKey::generate(&mut OsRng).to_keypair_bytes();
+            bytes[32..]
+                .copy_from_slice(SigningKey::generate(&mut OsRng).verifying_key().as_bytes());
+            serde_json::to_string(&bytes.to_vec()).unwrap()
+        };
+        let files = [
+            ("empty", String::new()),
+            ("not_json", "not a keypair".to_string()),
+            ("object", r#"{"secret": [1, 2, 3]}"#.to_string()),
+            ("out_of_range", format!("[{}]", vec!["256"; 64].join(","))),
+            ("short", serde_json::to_string(&vec![1u8; 32]).unwrap()),
+            ("long", serde_json::to_string(&vec![1u8; 65]).unwrap()),
+            // The public half does not belong to the secret half
+            ("mismatched", mismatched),
+        ];
+        for (name, data) in files {
+            let path = dir.join(name);
+            fs::write(&path, data).unwrap();
+            assert!(
+                KeypairSigner::load_or_generate(&path).is_err(),
+                "{name} was accepted"
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keypair_is_only_readable_by_the_user() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_dir("mode").join("keypair.json");
+        KeypairSigner::load_or_generate(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn signatures_pass_the_server_verification() {
+        let path = temp_dir("sign").join("keypair.json");
+        let signer = KeypairSigner::load_or_generate(&path).unwrap();
+        let pubkey = signer.pubkey();
+        let nft_list = vec!["AssetA".to_string(), "AssetB".to_string()];
+
+        let message = start_game_message(&pubkey, "entropy", &nft_list, "Pepe", "Map1");
+        let signature = signer.sign(&message).unwrap();
+        assert!(verify_signature(&pubkey, &message, &signature));
+
+        // Bound to the message and the key
+        let other = start_game_message(&pubkey, "entropy", &nft_list[..1], "Pepe", "Map1");
+        assert!(!verify_signature(&pubkey, &other, &signature));
+        let stranger = KeypairSigner::load_or_generate(&temp_dir("stranger").join("keypair.json"))
+            .unwrap()
+            .pubkey();
+        assert!(!verify_signature(&stranger, &message, &signature));
+    }
+}
@@ -0,0 +1,70 @@
+#[cfg(target_arch = "wasm32")]
+pub mod browser;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod keypair;
+
+use bevy::prelude::*;
+use std::sync::Arc;
+
+/// Provides the signer: a local keypair file on native builds, the hook of the web page in the browser
+pub struct WalletPlugin;
+
+impl Plugin for WalletPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PreStartup, setup_wallet)
+            .init_resource::<Wallet>();
+    }
+}
+
+/// Signs the messages of the session protocol on behalf of the player
+pub trait Signer: Send + Sync {
+    /// Base58 encoded public key of the wallet
+    fn pubkey(&self) -> String;
+    /// Base58 encoded ed25519 signature of the message
+    fn sign(&self, message: &str) -> anyhow::Result<String>;
+}
+
+/// The wallet of the player; without a signer the game can only be played offline
+#[derive(Resource, Default, Clone)]
+pub struct Wallet {
+    signer: Option<Arc<dyn Signer>>,
+}
+
+impl Wallet {
+    pub fn new(signer: Arc<dyn Signer>) -> Self {
+        Self {
+            signer: Some(signer),
+        }
+    }
+
+    pub fn signer(&self) -> Option<Arc<dyn Signer>> {
+        self.signer.clone()
+    }
+
+    pub fn pubkey(&self) -> Option<String> {
+        self.signer.as_ref().map(|signer| signer.pubkey())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn setup_wallet(mut wallet: ResMut<Wallet>) {
+    let path = keypair::KeypairSigner::path_from_args();
+    match keypair::KeypairSigner::load_or_generate(&path) {
+        Ok(signer) => {
+            info!("Wallet {} ({})", signer.pubkey(), path.display());
+            *wallet = Wallet::new(Arc::new(signer));
+        }
+        Err(e) => error!("No wallet, playing offline: {e:?}"),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn setup_wallet(mut wallet: ResMut<Wallet>) {
+    match browser::browser_wallet() {
+        Some(signer) => {
+            info!("Wallet {}", signer.pubkey());
+            *wallet = Wallet::new(signer);
+        }
+        None => info!("No browser wallet registered, playing offline"),
+    }
+}
@@ -1,9 +1,8 @@
 use std::collections::HashMap;
 
 use bevy::prelude::*;
-use leafwing_input_manager::action_state::ActionState;
 
-use crate::menu::MenuGameConfig;
+use crate::data::hero::HeroDefinition;
 use crate::state::for_game_states;
 use crate::{
     animation,
@@ -12,8 +11,8 @@ use crate::{
     plugins::{
         assets::GameAssets,
         health::{HealthUpdateEvent, TargetType},
+        simulation::{SimulationInput, SimulationSet},
     },
-    state::AppState,
     GameAction,
 };
 
@@ -21,14 +20,19 @@ use super::{weapon_animation_effect::WeaponAnimationEffect, weapon_type::WeaponT
 
 const FLAME_HITBOX: f32 = 80.;
 const FLAME_DAMAGE: f32 = 0.1;
+/// Matches the length of the flame animation
+const FLAME_LIFETIME: f32 = 0.3;
 
 pub struct FlameThrowerPlugin;
 
 impl Plugin for FlameThrowerPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
-            Update,
-            (flame_thrower_controls, update_flame_hits).run_if(in_state(AppState::GameRunning)),
+            FixedUpdate,
+            (
+                (flame_thrower_controls, update_flame_hits).in_set(SimulationSet::Attacks),
+                expire_flames.in_set(SimulationSet::Death),
+            ),
         );
     }
 }
@@ -45,6 +49,7 @@ pub struct FlameThrowerController {
 pub struct Flame {
     pub hitbox: f32,
     pub damage: f32,
+    pub lifetime: f32,
 }
 
 fn update_flame_hits(
@@ -69,6 +74,20 @@ fn update_flame_hits(
     }
 }
 
+/// Flames burn out on a simulation timer rather than with their animation, so the hits do not depend on the frame rate
+fn expire_flames(
+    mut commands: Commands,
+    time: Res<FixedTime>,
+    mut query: Query<(Entity, &mut Flame)>,
+) {
+    for (entity, mut flame) in query.iter_mut() {
+        flame.lifetime -= time.period.as_secs_f32();
+        if flame.lifetime <= 0. {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
 fn create_flame_anim_hashmap() -> HashMap<String, animation::Animation> {
     let mut hash_map = HashMap::new();
     hash_map.insert(
@@ -107,33 +126,32 @@ fn spawn_flame_effect(commands: &mut Commands, game_assets: &Res<GameAssets>, tr
             last_animation: "Fire".to_string(),
             current_animation: "Fire".to_string(),
             animation_bank: create_flame_anim_hashmap(),
-            destroy_on_end: true,
+            destroy_on_end: false,
         })
         .insert(Flame {
             hitbox: FLAME_HITBOX,
             damage: FLAME_DAMAGE,
+            lifetime: FLAME_LIFETIME,
         })
         .insert(WeaponAnimationEffect::FlameThrowerFlame);
 }
 
 fn flame_thrower_controls(
     mut commands: Commands,
-    time: Res<Time>,
+    time: Res<FixedTime>,
     mut query: Query<(
         &mut FlameThrowerController,
         &mut animation::Animator,
         &Transform,
     )>,
-    actions: Query<&ActionState<GameAction>>,
+    input: Res<SimulationInput>,
     game_assets: Res<GameAssets>,
 ) {
-    let action = actions.single();
-
     for (mut controller, mut animator, transform) in query.iter_mut() {
         if controller.is_firing {
             animator.current_animation = "Fire".to_string();
 
-            controller.timer.tick(time.delta());
+            controller.timer.tick(time.period);
 
             if controller.timer.finished() {
                 spawn_flame_effect(&mut commands, &game_assets, transform.translation);
@@ -142,7 +160,7 @@ fn flame_thrower_controls(
             animator.current_animation = "Idle".to_string();
         }
 
-        if action.pressed(GameAction::Action1) {
+        if input.pressed(GameAction::Action1) {
             controller.is_firing = true;
         } else {
             controller.is_firing = false;
@@ -175,7 +193,7 @@ fn create_flame_thrower_anim_hashmap() -> HashMap<String, animation::Animation>
 
 pub fn spawn_flame_thrower(
     commands: &mut Commands,
-    game_config: &Res<MenuGameConfig>,
+    hero: &HeroDefinition,
     game_assets: &Res<GameAssets>,
 ) {
     commands
@@ -200,7 +218,7 @@ pub fn spawn_flame_thrower(
             destroy_on_end: false,
         })
         .insert(player_attach::PlayerAttach::new(
-            game_config.hero.weapon_offset(WeaponType::FlameThrower),
+            hero.weapon_offset(WeaponType::FlameThrower),
         ))
         .insert(FlameThrowerController {
             hitbox: 10.,
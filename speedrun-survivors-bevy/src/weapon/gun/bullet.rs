@@ -11,12 +11,13 @@ pub struct Bullet {
 
 pub fn update_bullets(
     mut bullet_query: Query<(&mut Bullet, &mut Transform, Entity)>,
-    time: ResMut<Time>,
+    time: Res<FixedTime>,
     mut commands: Commands,
 ) {
     for (mut bullet, mut transform, entity) in bullet_query.iter_mut() {
-        bullet.lifetime -= time.delta_seconds();
-        let moving = bullet.speed * bullet.direction * time.delta_seconds();
+        let delta = time.period.as_secs_f32();
+        bullet.lifetime -= delta;
+        let moving = bullet.speed * bullet.direction * delta;
         transform.translation += Vec3::new(moving.x, moving.y, 0.);
         if bullet.lifetime <= 0. {
             commands.entity(entity).despawn();
@@ -4,14 +4,15 @@ pub mod bullet;
 
 use crate::plugins::assets::GameAssets;
 use crate::plugins::sfx_manager::{PlaySFX, SFX};
-use crate::state::{for_game_states, AppState};
+use crate::plugins::simulation::{Interpolated, SimulationInput, SimulationSet};
+use crate::replay::dequantize_aim;
+use crate::state::for_game_states;
 use crate::{
     animation::{self, Animator},
     player::player_attach,
     GameAction,
 };
-use bevy::{prelude::*, window::PrimaryWindow};
-use leafwing_input_manager::action_state::ActionState;
+use bevy::prelude::*;
 
 use self::bullet::Bullet;
 
@@ -26,13 +27,11 @@ pub struct GunPlugin;
 impl Plugin for GunPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
-            Update,
+            FixedUpdate,
             (
-                bullet::update_bullets,
-                bullet::update_bullet_hits,
-                gun_controls,
-            )
-                .run_if(in_state(AppState::GameRunning)),
+                bullet::update_bullets.in_set(SimulationSet::Movement),
+                (gun_controls, bullet::update_bullet_hits).in_set(SimulationSet::Attacks),
+            ),
         );
     }
 }
@@ -94,25 +93,14 @@ pub fn spawn_gun(commands: &mut Commands, game_assets: &Res<GameAssets>) {
 
 pub fn gun_controls(
     mut gun_query: Query<(&mut GunController, &mut Transform, &mut Animator)>,
-    primary_query: Query<&Window, With<PrimaryWindow>>,
-    actions: Query<&ActionState<GameAction>>,
-    time: Res<Time>,
+    input: Res<SimulationInput>,
+    time: Res<FixedTime>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    query_camera: Query<(&Camera, &GlobalTransform)>,
     mut tx_sfx: EventWriter<PlaySFX>,
 ) {
-    let action = actions.single();
-
     for (mut gun_controller, mut transform, mut animator) in gun_query.iter_mut() {
-        let Ok(window) = primary_query.get_single() else {
-            return;
-        };
-        let Ok((camera, camera_transform)) = query_camera.get_single() else {
-            return;
-        };
-
-        gun_controller.shoot_timer -= time.delta_seconds();
+        gun_controller.shoot_timer -= time.period.as_secs_f32();
 
         if gun_controller.shoot_timer > 0. {
             animator.current_animation = "Shoot".to_string();
@@ -120,30 +108,23 @@ pub fn gun_controls(
             animator.current_animation = "Idle".to_string();
         }
 
-        // Aim gun at world location
-        let Some(cursor_world_position) = window
-            .cursor_position()
-            .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
-            .map(|ray| ray.origin.truncate())
-        else {
+        // Aim gun along the sampled (or recorded) aim
+        let Some(diff) = input.aim.map(dequantize_aim) else {
             return;
         };
-
-        let diff =
-            cursor_world_position - Vec2::new(transform.translation.x, transform.translation.y);
         let angle = diff.y.atan2(diff.x);
         transform.rotation = Quat::from_axis_angle(Vec3::new(0., 0., 1.), angle);
 
         // The character can walk left or right, but the gun should not flip as we rotate it here and flip it on the y axis so it doesnt go upside down
-        if cursor_world_position.x < transform.translation.x && transform.scale.y > 0. {
+        if diff.x < 0. && transform.scale.y > 0. {
             transform.scale.y *= -1.;
         }
-        if cursor_world_position.x >= transform.translation.x && transform.scale.y < 0. {
+        if diff.x >= 0. && transform.scale.y < 0. {
             transform.scale.y *= -1.;
         }
 
         if gun_controller.shoot_timer <= 0. {
-            if action.pressed(GameAction::Action1) {
+            if input.pressed(GameAction::Action1) {
                 let mut spawn_transform = Transform::from_scale(Vec3::splat(2.0));
                 spawn_transform.translation = transform.translation;
                 spawn_transform.rotation = Quat::from_axis_angle(Vec3::new(0., 0., 1.), angle);
@@ -160,8 +141,9 @@ pub fn gun_controls(
                     .insert(Bullet {
                         lifetime: BULLET_LIFETIME,
                         speed: BULLET_SPEED,
-                        direction: diff.normalize(),
-                    });
+                        direction: diff,
+                    })
+                    .insert(Interpolated::default());
                 tx_sfx.send(PlaySFX {
                     sfx: SFX::AttackGun,
                     location: None,
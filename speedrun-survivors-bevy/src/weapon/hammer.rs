@@ -1,19 +1,20 @@
 use std::collections::HashMap;
 
 use bevy::prelude::*;
-use leafwing_input_manager::action_state::ActionState;
 
-use crate::menu::MenuGameConfig;
+use crate::data::hero::HeroDefinition;
 use crate::player::Player;
 use crate::plugins::assets::GameAssets;
 use crate::plugins::camera_shake::{CameraImpact, CameraImpactStrength};
 use crate::plugins::gameplay_effects::{GameplayEffectPluginState, GameplayTag};
 use crate::plugins::sfx_manager::{PlaySFX, SFX};
+use crate::plugins::simulation::{SimulationEventApp, SimulationInput, SimulationSet};
 use crate::plugins::status_effect::{
-    StatusEffect, StatusEffectEvent, StatusEffectEventType, StatusEffectType,
+    on_status_effect_event, StatusEffect, StatusEffectEvent, StatusEffectEventType,
+    StatusEffectType,
 };
 use crate::plugins::vfx_manager::{PlayVFX, VFX};
-use crate::state::{for_game_states, AppState};
+use crate::state::for_game_states;
 use crate::{
     animation::{self, Animator},
     enemy::Enemy,
@@ -31,12 +32,16 @@ pub struct HammerPlugin;
 
 impl Plugin for HammerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<HammerStomp>()
-            .add_systems(
-                Update,
-                hammer_controls.run_if(in_state(AppState::GameRunning)),
-            )
-            .add_systems(Update, on_hammer_stomp.run_if(on_event::<HammerStomp>()));
+        app.add_simulation_event::<HammerStomp>().add_systems(
+            FixedUpdate,
+            (
+                hammer_controls.in_set(SimulationSet::Attacks),
+                on_hammer_stomp
+                    .before(on_status_effect_event)
+                    .run_if(on_event::<HammerStomp>())
+                    .in_set(SimulationSet::Damage),
+            ),
+        );
     }
 }
 
@@ -132,11 +137,7 @@ fn create_hammer_anim_hashmap() -> HashMap<String, animation::Animation> {
     hash_map
 }
 
-pub fn spawn_hammer(
-    commands: &mut Commands,
-    game_config: &Res<MenuGameConfig>,
-    game_assets: &Res<GameAssets>,
-) {
+pub fn spawn_hammer(commands: &mut Commands, hero: &HeroDefinition, game_assets: &Res<GameAssets>) {
     commands
         .spawn((
             SpriteSheetBundle {
@@ -159,7 +160,7 @@ pub fn spawn_hammer(
             destroy_on_end: false,
         })
         .insert(player_attach::PlayerAttach::new(
-            game_config.hero.weapon_offset(WeaponType::Hammer),
+            hero.weapon_offset(WeaponType::Hammer),
         ))
         .insert(HammerController {
             hitbox: HAMMER_HITBOX,
@@ -172,12 +173,10 @@ pub fn spawn_hammer(
 
 pub fn hammer_controls(
     mut hammer_query: Query<(&mut HammerController, &Transform, &mut Animator)>,
-    actions: Query<&ActionState<GameAction>>,
+    input: Res<SimulationInput>,
     mut tx_stomp: EventWriter<HammerStomp>,
     mut gameplay_state: ResMut<GameplayEffectPluginState>,
 ) {
-    let action = actions.single();
-
     for (mut hammer, transform, mut animator) in hammer_query.iter_mut() {
         if hammer.stomp_time > 0. {
             animator.current_animation = "Stomp".to_string();
@@ -197,7 +196,7 @@ pub fn hammer_controls(
         }
 
         if hammer.stomp_time <= 0.
-            && action.pressed(GameAction::Action1)
+            && input.pressed(GameAction::Action1)
             && gameplay_state.player_tags.add_tag(GameplayTag::Attack, 0.9)
         {
             hammer.stomp_time = 3.5;
@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 
+use crate::state::AppState;
+
 use self::{switch_weapon::SwitchWeaponEvent, weapon_type::WeaponType};
 
 pub mod flame_thrower;
@@ -27,6 +29,6 @@ impl Plugin for WeaponPlugin {
             gun::GunPlugin,
             flame_thrower::FlameThrowerPlugin,
         ))
-        .add_systems(Startup, spawn_initial_weapon);
+        .add_systems(OnEnter(AppState::GameInitializing), spawn_initial_weapon);
     }
 }
@@ -1,9 +1,10 @@
 use bevy::prelude::*;
-use leafwing_input_manager::action_state::ActionState;
 
+use crate::data::hero::HeroRegistry;
 use crate::menu::MenuGameConfig;
 use crate::plugins::gameplay_effects::{GameplayEffectPluginState, GameplayTag};
-use crate::{plugins::assets::GameAssets, state::AppState, GameAction};
+use crate::plugins::simulation::{SimulationEventApp, SimulationInput, SimulationSet};
+use crate::{plugins::assets::GameAssets, GameAction};
 
 use super::weapon_type::WeaponType;
 
@@ -11,9 +12,11 @@ pub struct SwitchWeaponPlugin;
 
 impl Plugin for SwitchWeaponPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<SwitchWeaponEvent>().add_systems(
-            Update,
-            (on_switch_weapon, switch_weapon_controls).run_if(in_state(AppState::GameRunning)),
+        app.add_simulation_event::<SwitchWeaponEvent>().add_systems(
+            FixedUpdate,
+            (switch_weapon_controls, on_switch_weapon)
+                .chain()
+                .in_set(SimulationSet::Input),
         );
     }
 }
@@ -25,12 +28,10 @@ pub struct SwitchWeaponEvent {
 
 fn switch_weapon_controls(
     mut tx_switch: EventWriter<SwitchWeaponEvent>,
-    actions: Query<&ActionState<GameAction>>,
+    input: Res<SimulationInput>,
     mut gameplay_state: ResMut<GameplayEffectPluginState>,
 ) {
-    let action = actions.single();
-
-    if action.just_pressed(GameAction::Slot1)
+    if input.just_pressed(GameAction::Slot1)
         && gameplay_state.player_tags.add_tag(GameplayTag::Attack, 0.5)
     {
         tx_switch.send(SwitchWeaponEvent {
@@ -38,7 +39,7 @@ fn switch_weapon_controls(
         });
     }
 
-    if action.just_pressed(GameAction::Slot2)
+    if input.just_pressed(GameAction::Slot2)
         && gameplay_state.player_tags.add_tag(GameplayTag::Attack, 0.5)
     {
         tx_switch.send(SwitchWeaponEvent {
@@ -46,7 +47,7 @@ fn switch_weapon_controls(
         });
     }
 
-    if action.just_pressed(GameAction::Slot3)
+    if input.just_pressed(GameAction::Slot3)
         && gameplay_state.player_tags.add_tag(GameplayTag::Attack, 0.5)
     {
         tx_switch.send(SwitchWeaponEvent {
@@ -54,7 +55,7 @@ fn switch_weapon_controls(
         });
     }
 
-    if action.just_pressed(GameAction::Slot4)
+    if input.just_pressed(GameAction::Slot4)
         && gameplay_state.player_tags.add_tag(GameplayTag::Attack, 0.5)
     {
         tx_switch.send(SwitchWeaponEvent {
@@ -68,20 +69,25 @@ fn on_switch_weapon(
     mut rx_switch: EventReader<SwitchWeaponEvent>,
     mut weapon_query: Query<(&mut Transform, Entity), With<WeaponType>>,
     game_config: Res<MenuGameConfig>,
+    heroes: Res<HeroRegistry>,
     game_assets: Res<GameAssets>,
 ) {
+    let Some(hero) = heroes.get_or_default(&game_config.hero) else {
+        return;
+    };
+
     for switch_weapon_event in rx_switch.iter() {
-        println!("Switching weapon to {:?}", switch_weapon_event.weapon_type);
+        debug!("Switching weapon to {:?}", switch_weapon_event.weapon_type);
 
         // delete all weapons
         for (_transform, entity) in weapon_query.iter_mut() {
-            println!("Deleting weapon {:?}", entity);
+            debug!("Deleting weapon {:?}", entity);
             commands.entity(entity).despawn_recursive();
         }
 
         // spawn new weapon
         switch_weapon_event
             .weapon_type
-            .spawn(&mut commands, &game_config, &game_assets);
+            .spawn(&mut commands, hero, &game_assets);
     }
 }
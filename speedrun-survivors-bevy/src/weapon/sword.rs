@@ -1,14 +1,14 @@
 use std::collections::HashMap;
 
 use bevy::prelude::*;
-use leafwing_input_manager::action_state::ActionState;
 
-use crate::menu::MenuGameConfig;
+use crate::data::hero::HeroDefinition;
 use crate::plugins::assets::GameAssets;
 use crate::plugins::gameplay_effects::{GameplayEffectPluginState, GameplayTag};
 use crate::plugins::health::{self};
+use crate::plugins::simulation::{Interpolated, SimulationInput, SimulationSet};
 use crate::plugins::vfx_manager::{PlayVFX, VFX};
-use crate::state::{for_game_states, AppState};
+use crate::state::for_game_states;
 use crate::{
     animation::{self, Animator},
     enemy::Enemy,
@@ -31,14 +31,16 @@ pub struct SwordPlugin;
 impl Plugin for SwordPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
-            Update,
+            FixedUpdate,
             (
-                sword_controls,
-                //update_sword_hits,
-                move_sword_swing_effect,
-                update_sword_effect_hits,
-            )
-                .run_if(in_state(AppState::GameRunning)),
+                move_sword_swing_effect.in_set(SimulationSet::Movement),
+                (
+                    sword_controls,
+                    //update_sword_hits,
+                    update_sword_effect_hits,
+                )
+                    .in_set(SimulationSet::Attacks),
+            ),
         );
     }
 }
@@ -80,11 +82,11 @@ pub struct SwordEffect {
 }
 
 fn move_sword_swing_effect(
-    time: Res<Time>,
+    time: Res<FixedTime>,
     mut sword_query: Query<(&mut Transform, &mut SwordEffect, Entity)>,
     mut commands: Commands,
 ) {
-    let delta = time.delta_seconds();
+    let delta = time.period.as_secs_f32();
     let move_by = SWORD_EFFECT_SPEED * delta;
 
     for (mut transform, mut effect, entity) in sword_query.iter_mut() {
@@ -126,16 +128,13 @@ fn spawn_sword_swing_effect(
                 },
                 hit_list: vec![],
             },
+            Interpolated::default(),
             for_game_states(),
         ))
         .id()
 }
 
-pub fn spawn_sword(
-    commands: &mut Commands,
-    game_config: &Res<MenuGameConfig>,
-    game_assets: &Res<GameAssets>,
-) {
+pub fn spawn_sword(commands: &mut Commands, hero: &HeroDefinition, game_assets: &Res<GameAssets>) {
     commands
         .spawn((
             SpriteSheetBundle {
@@ -154,7 +153,7 @@ pub fn spawn_sword(
             destroy_on_end: false,
         })
         .insert(player_attach::PlayerAttach::new(
-            game_config.hero.weapon_offset(WeaponType::Sword),
+            hero.weapon_offset(WeaponType::Sword),
         ))
         .insert(SwordController {
             swing_time: 0.,
@@ -170,14 +169,12 @@ pub fn sword_controls(
         &mut Animator,
         &TextureAtlasSprite,
     )>,
-    actions: Query<&ActionState<GameAction>>,
+    input: Res<SimulationInput>,
     mut commands: Commands,
     game_assets: Res<GameAssets>,
     mut gameplay_state: ResMut<GameplayEffectPluginState>,
     mut tx_vfx: EventWriter<PlayVFX>,
 ) {
-    let action = actions.single();
-
     for (mut sword_controller, transform, mut animator, ta) in sword_query.iter_mut() {
         if sword_controller.swing_time > 0. {
             // this if clause is run once on swing start
@@ -207,7 +204,7 @@ pub fn sword_controls(
         }
 
         if sword_controller.swing_time <= 0.
-            && action.just_pressed(GameAction::Action1)
+            && input.just_pressed(GameAction::Action1)
             && gameplay_state
                 .player_tags
                 .add_tag(GameplayTag::Attack, SWORD_COOLDOWN)
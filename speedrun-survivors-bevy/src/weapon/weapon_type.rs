@@ -6,10 +6,11 @@ use super::{
     flame_thrower::spawn_flame_thrower, gun::spawn_gun, hammer::spawn_hammer, sword::spawn_sword,
 };
 
-use crate::menu::MenuGameConfig;
+use crate::data::hero::HeroDefinition;
+use serde::Deserialize;
 use strum::EnumIter;
 
-#[derive(Debug, Clone, Copy, Hash, Default, Component, PartialEq, Eq, EnumIter)]
+#[derive(Debug, Clone, Copy, Hash, Default, Component, PartialEq, Eq, EnumIter, Deserialize)]
 pub enum WeaponType {
     Gun,
     Hammer,
@@ -22,14 +23,14 @@ impl WeaponType {
     pub fn spawn(
         &self,
         commands: &mut Commands,
-        game_config: &Res<MenuGameConfig>,
+        hero: &HeroDefinition,
         game_assets: &Res<GameAssets>,
     ) {
         match self {
-            WeaponType::Hammer => spawn_hammer(commands, game_config, game_assets),
-            WeaponType::Sword => spawn_sword(commands, game_config, game_assets),
+            WeaponType::Hammer => spawn_hammer(commands, hero, game_assets),
+            WeaponType::Sword => spawn_sword(commands, hero, game_assets),
             WeaponType::Gun => spawn_gun(commands, game_assets),
-            WeaponType::FlameThrower => spawn_flame_thrower(commands, game_config, game_assets),
+            WeaponType::FlameThrower => spawn_flame_thrower(commands, hero, game_assets),
         };
     }
 
@@ -0,0 +1,574 @@
+//! Request and response types of the API server, shared by the game client and the server
+//!
+//! Field names and renames are part of the wire format; changing them breaks older clients.
+
+use serde::{Deserialize, Serialize};
+
+mod signature;
+
+pub use signature::verify_signature;
+
+/// Version of the client API; bump it on any incompatible change of these types or the routes
+pub const API_VERSION: u32 = 4;
+
+/// Ids of the maps a run can be played on, the names of `MapId` in the game
+pub const MAP_IDS: [&str; 4] = ["Map1", "Map2", "Map3", "Map4"];
+
+/// Answer of `GET /`, and of any other route the server doesn't know
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VersionResponse {
+    pub app: String,
+    pub version: String,
+    #[serde(rename = "apiVersion")]
+    pub api_version: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NftListRequest {
+    pub pubkey: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum NftKind {
+    Hero,
+    Weapon,
+    PowerUp,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NftData {
+    pub key: String,
+    pub kind: NftKind,
+    #[serde(rename = "imgUrl")]
+    pub image_url: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NftListResponse {
+    #[serde(rename = "nftList")]
+    pub nft_list: Vec<NftData>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionGetRequest {
+    pub pubkey: String,
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum SessionStateClient {
+    #[default]
+    None,
+    Expired,
+    Active,
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+pub struct SessionGetResponse {
+    pub state: SessionStateClient,
+    pub entropy: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionInitRequest {
+    pub pubkey: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionInitResponse {
+    pub entropy: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionCancelRequest {
+    pub pubkey: String,
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameStartRequest {
+    pub pubkey: String,
+    pub entropy: String,
+    /// The NFTs to equip for this run; locked once the game started
+    #[serde(rename = "nftList")]
+    pub nft_list: Option<Vec<String>>,
+    /// Roster id of the hero and one of `MAP_IDS`; locked like the NFTs
+    pub hero: String,
+    pub map: String,
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameCompleteRequest {
+    pub pubkey: String,
+    pub entropy: String,
+    #[serde(rename = "nftList")]
+    pub nft_list: Option<Vec<String>>,
+    /// Roster id of the hero and `MapId` the run was played with; passed on to the simulator
+    pub hero: String,
+    pub map: String,
+    pub replay: String,
+    pub outcome: RunOutcome,
+    pub signature: String,
+}
+
+/// The result of a run, as claimed by the client or recomputed from the replay by the server
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RunOutcome {
+    pub ticks: u32,
+    pub kills: u32,
+    pub coins: u64,
+    pub level: u32,
+}
+
+impl RunOutcome {
+    /// Points for the score board; surviving longer pays off through kills, coins and levels
+    pub fn score(&self) -> u64 {
+        self.coins + self.kills as u64 * 10 + self.level as u64 * 100
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ReplayVerdict {
+    /// The replay was not checked (yet)
+    #[default]
+    None,
+    Valid,
+    Mismatch,
+    Undecodable,
+    /// The replay is longer than the time that passed since the game started
+    Implausible,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameCompleteResponse {
+    pub replay: ReplayVerdict,
+    /// Coins credited to the ledger for this run
+    pub reward: u64,
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum LeaderboardKind {
+    /// Longest survival time
+    #[default]
+    Time,
+    Kills,
+    Score,
+}
+
+/// Selects a board; filters that are not given match every run
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+pub struct LeaderboardFilter {
+    #[serde(default)]
+    pub board: LeaderboardKind,
+    pub map: Option<String>,
+    pub hero: Option<String>,
+    pub season: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LeaderboardRequest {
+    #[serde(flatten)]
+    pub filter: LeaderboardFilter,
+    #[serde(default)]
+    pub page: u32,
+    #[serde(rename = "pageSize")]
+    pub page_size: Option<u32>,
+}
+
+/// The best verified run of a player on a board
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LeaderboardEntry {
+    pub rank: u64,
+    pub pubkey: String,
+    pub hero: String,
+    pub map: String,
+    pub season: String,
+    pub ticks: u32,
+    pub kills: u32,
+    pub coins: u64,
+    pub level: u32,
+    pub score: u64,
+    #[serde(rename = "completedAt")]
+    pub completed_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LeaderboardResponse {
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LeaderboardRankRequest {
+    #[serde(flatten)]
+    pub filter: LeaderboardFilter,
+    pub pubkey: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LeaderboardRankResponse {
+    pub entry: LeaderboardEntry,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum LedgerKind {
+    /// Coins collected in a run with a valid replay; the reference is the session entropy
+    RunReward,
+}
+
+/// A single entry of the append-only ledger; the balance is the sum of all amounts
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LedgerTransaction {
+    pub kind: LedgerKind,
+    pub amount: i64,
+    pub reference: String,
+    pub unixtime: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BalanceRequest {
+    pub pubkey: String,
+    #[serde(default)]
+    pub page: u32,
+    #[serde(rename = "pageSize")]
+    pub page_size: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BalanceResponse {
+    pub balance: i64,
+    /// Newest first
+    pub transactions: Vec<LedgerTransaction>,
+}
+
+/// The JSON body of every error response; `error` is a stable code clients can match on
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiErrorBody {
+    pub error: String,
+    pub message: String,
+}
+
+impl ApiErrorBody {
+    pub fn new(code: ApiErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            error: code.as_str().to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// The codes of `ApiErrorBody::error`; a newer server may send codes that are not listed here
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiErrorCode {
+    BadRequest,
+    NoSuchSession,
+    GameActive,
+    RequestDataDoesNotMatch,
+    SignatureInvalid,
+    TooManyNfts,
+    NftNotOwned,
+    NftLoadoutMismatch,
+    EntropyUsed,
+    ReplayRejected,
+    NotRanked,
+    RateLimited,
+    Busy,
+    Upstream,
+    Internal,
+}
+
+impl ApiErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiErrorCode::BadRequest => "BadRequest",
+            ApiErrorCode::NoSuchSession => "NoSuchSession",
+            ApiErrorCode::GameActive => "GameActive",
+            ApiErrorCode::RequestDataDoesNotMatch => "RequestDataDoesNotMatch",
+            ApiErrorCode::SignatureInvalid => "SignatureInvalid",
+            ApiErrorCode::TooManyNfts => "TooManyNfts",
+            ApiErrorCode::NftNotOwned => "NftNotOwned",
+            ApiErrorCode::NftLoadoutMismatch => "NftLoadoutMismatch",
+            ApiErrorCode::EntropyUsed => "EntropyUsed",
+            ApiErrorCode::ReplayRejected => "ReplayRejected",
+            ApiErrorCode::NotRanked => "NotRanked",
+            ApiErrorCode::RateLimited => "RateLimited",
+            ApiErrorCode::Busy => "Busy",
+            ApiErrorCode::Upstream => "Upstream",
+            ApiErrorCode::Internal => "Internal",
+        }
+    }
+
+    /// HTTP status of the responses carrying this code
+    pub fn status(&self) -> u16 {
+        match self {
+            ApiErrorCode::BadRequest | ApiErrorCode::TooManyNfts => 400,
+            ApiErrorCode::SignatureInvalid => 401,
+            ApiErrorCode::NftNotOwned => 403,
+            ApiErrorCode::NoSuchSession | ApiErrorCode::NotRanked => 404,
+            ApiErrorCode::GameActive
+            | ApiErrorCode::RequestDataDoesNotMatch
+            | ApiErrorCode::NftLoadoutMismatch
+            | ApiErrorCode::EntropyUsed => 409,
+            ApiErrorCode::ReplayRejected => 422,
+            ApiErrorCode::RateLimited => 429,
+            ApiErrorCode::Internal => 500,
+            ApiErrorCode::Upstream => 502,
+            ApiErrorCode::Busy => 503,
+        }
+    }
+}
+
+/// Message the wallet signs to cancel its session
+pub fn cancel_game_message(pubkey: &str, entropy: &str) -> String {
+    format!("CANCEL GAME {pubkey}:{entropy}")
+}
+
+/// Message the wallet signs to start a game with the equipped NFTs, hero and map
+pub fn start_game_message(
+    pubkey: &str,
+    entropy: &str,
+    nft_list: &[String],
+    hero: &str,
+    map: &str,
+) -> String {
+    format!(
+        "START GAME {pubkey}:{entropy}:{}:{hero}:{map}",
+        nft_list.join(NFT_DELIMITER)
+    )
+}
+
+/// Message the wallet signs to submit a completed game
+/// The hero and map are signed too, as the server simulates the replay with them
+pub fn complete_game_message(
+    pubkey: &str,
+    entropy: &str,
+    nft_list: &[String],
+    hero: &str,
+    map: &str,
+) -> String {
+    format!(
+        "COMPLETE GAME {pubkey}:{entropy}:{}:{hero}:{map}",
+        nft_list.join(NFT_DELIMITER)
+    )
+}
+
+/// Separates the NFT ids in signed messages, so no two loadouts sign the same text
+/// Asset ids are base58 and never contain it
+const NFT_DELIMITER: &str = ",";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{from_value, json, to_value};
+
+    fn nfts(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    #[test]
+    fn signed_messages_separate_the_nfts() {
+        assert_eq!(
+            start_game_message("pk", "ent", &nfts(&["a", "b"]), "Pepe", "Map1"),
+            "START GAME pk:ent:a,b:Pepe:Map1"
+        );
+        assert_eq!(
+            complete_game_message("pk", "ent", &nfts(&["a"]), "Pepe", "Map1"),
+            "COMPLETE GAME pk:ent:a:Pepe:Map1"
+        );
+        assert_eq!(
+            complete_game_message("pk", "ent", &[], "Pepe", "Map1"),
+            "COMPLETE GAME pk:ent::Pepe:Map1"
+        );
+    }
+
+    #[test]
+    fn different_loadouts_sign_different_messages() {
+        let loadouts = [nfts(&["ab", "c"]), nfts(&["a", "bc"]), nfts(&["abc"])];
+        for (idx, loadout) in loadouts.iter().enumerate() {
+            for other in &loadouts[idx + 1..] {
+                assert_ne!(
+                    start_game_message("pk", "ent", loadout, "Pepe", "Map1"),
+                    start_game_message("pk", "ent", other, "Pepe", "Map1")
+                );
+                assert_ne!(
+                    complete_game_message("pk", "ent", loadout, "Pepe", "Map1"),
+                    complete_game_message("pk", "ent", other, "Pepe", "Map1")
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn start_and_completion_sign_the_hero_and_map() {
+        let signed = start_game_message("pk", "ent", &nfts(&["a"]), "Pepe", "Map1");
+        assert_ne!(
+            signed,
+            start_game_message("pk", "ent", &nfts(&["a"]), "Orca", "Map1")
+        );
+        assert_ne!(
+            signed,
+            start_game_message("pk", "ent", &nfts(&["a"]), "Pepe", "Map2")
+        );
+
+        let signed = complete_game_message("pk", "ent", &nfts(&["a"]), "Pepe", "Map1");
+        assert_ne!(
+            signed,
+            complete_game_message("pk", "ent", &nfts(&["a"]), "Orca", "Map1")
+        );
+        assert_ne!(
+            signed,
+            complete_game_message("pk", "ent", &nfts(&["a"]), "Pepe", "Map2")
+        );
+    }
+
+    #[test]
+    fn nft_list_uses_the_client_field_names() {
+        let response = NftListResponse {
+            nft_list: vec![NftData {
+                key: "key".to_string(),
+                kind: NftKind::PowerUp,
+                image_url: "https://img".to_string(),
+            }],
+        };
+        assert_eq!(
+            to_value(&response).unwrap(),
+            json!({"nftList": [{"key": "key", "kind": "PowerUp", "imgUrl": "https://img"}]})
+        );
+
+        // An empty wallet is an empty list, never null or missing
+        assert_eq!(
+            to_value(NftListResponse { nft_list: vec![] }).unwrap(),
+            json!({"nftList": []})
+        );
+        assert!(from_value::<NftListResponse>(json!({"nftList": null})).is_err());
+        assert!(from_value::<NftListResponse>(json!({})).is_err());
+    }
+
+    #[test]
+    fn game_requests_take_an_optional_nft_list() {
+        let start: GameStartRequest = from_value(json!({
+            "pubkey": "pk",
+            "entropy": "ent",
+            "nftList": ["a", "b"],
+            "hero": "Pepe",
+            "map": "Map1",
+            "signature": "sig",
+        }))
+        .unwrap();
+        assert_eq!(start.nft_list, Some(vec!["a".to_string(), "b".to_string()]));
+
+        let start: GameStartRequest = from_value(json!({
+            "pubkey": "pk",
+            "entropy": "ent",
+            "hero": "Pepe",
+            "map": "Map1",
+            "signature": "sig",
+        }))
+        .unwrap();
+        assert_eq!(start.nft_list, None);
+
+        let complete: GameCompleteRequest = from_value(json!({
+            "pubkey": "pk",
+            "entropy": "ent",
+            "nftList": ["a"],
+            "hero": "hero",
+            "map": "map",
+            "replay": "U1NSUA==",
+            "outcome": {"ticks": 60, "kills": 1, "coins": 2, "level": 3},
+            "signature": "sig",
+        }))
+        .unwrap();
+        assert_eq!(complete.nft_list, Some(vec!["a".to_string()]));
+        assert_eq!(to_value(&complete).unwrap()["nftList"], json!(["a"]));
+    }
+
+    #[test]
+    fn leaderboard_filter_is_flattened() {
+        let request: LeaderboardRequest = from_value(json!({
+            "board": "Kills",
+            "map": "map",
+            "season": "1",
+            "page": 2,
+            "pageSize": 5,
+        }))
+        .unwrap();
+        assert_eq!(request.filter.board, LeaderboardKind::Kills);
+        assert_eq!(request.filter.map.as_deref(), Some("map"));
+        assert_eq!(request.filter.hero, None);
+        assert_eq!(request.filter.season.as_deref(), Some("1"));
+        assert_eq!((request.page, request.page_size), (2, Some(5)));
+
+        let value = to_value(&request).unwrap();
+        assert!(value.get("filter").is_none());
+        assert_eq!(value["board"], "Kills");
+        assert_eq!(value["pageSize"], 5);
+
+        // Everything is optional, the time board comes first
+        let request: LeaderboardRequest = from_value(json!({})).unwrap();
+        assert_eq!(request.filter.board, LeaderboardKind::Time);
+        assert_eq!((request.page, request.page_size), (0, None));
+
+        assert!(from_value::<LeaderboardRequest>(json!({"board": "Coins"})).is_err());
+    }
+
+    #[test]
+    fn leaderboard_rank_filter_is_flattened() {
+        let request: LeaderboardRankRequest =
+            from_value(json!({"board": "Score", "hero": "hero", "pubkey": "pk"})).unwrap();
+        assert_eq!(request.filter.board, LeaderboardKind::Score);
+        assert_eq!(request.filter.hero.as_deref(), Some("hero"));
+        assert_eq!(request.pubkey, "pk");
+        assert!(to_value(&request).unwrap().get("filter").is_none());
+    }
+
+    #[test]
+    fn error_codes_are_sent_by_name() {
+        let codes = [
+            ApiErrorCode::BadRequest,
+            ApiErrorCode::NoSuchSession,
+            ApiErrorCode::GameActive,
+            ApiErrorCode::RequestDataDoesNotMatch,
+            ApiErrorCode::SignatureInvalid,
+            ApiErrorCode::TooManyNfts,
+            ApiErrorCode::NftNotOwned,
+            ApiErrorCode::NftLoadoutMismatch,
+            ApiErrorCode::EntropyUsed,
+            ApiErrorCode::ReplayRejected,
+            ApiErrorCode::NotRanked,
+            ApiErrorCode::RateLimited,
+            ApiErrorCode::Busy,
+            ApiErrorCode::Upstream,
+            ApiErrorCode::Internal,
+        ];
+        for code in codes {
+            assert_eq!(to_value(code).unwrap(), code.as_str());
+            assert!((400..600).contains(&code.status()), "{code:?}");
+        }
+
+        assert_eq!(
+            to_value(ApiErrorBody::new(ApiErrorCode::SignatureInvalid, "bad")).unwrap(),
+            json!({"error": "SignatureInvalid", "message": "bad"})
+        );
+    }
+
+    #[test]
+    fn paging_uses_page_size() {
+        let request: BalanceRequest = from_value(json!({"pubkey": "pk", "pageSize": 10})).unwrap();
+        assert_eq!((request.page, request.page_size), (0, Some(10)));
+        assert_eq!(to_value(&request).unwrap()["pageSize"], 10);
+
+        let entry = LeaderboardEntry {
+            rank: 1,
+            pubkey: "pk".to_string(),
+            hero: "hero".to_string(),
+            map: "map".to_string(),
+            season: "1".to_string(),
+            ticks: 60,
+            kills: 1,
+            coins: 2,
+            level: 3,
+            score: 312,
+            completed_at: 100,
+        };
+        assert_eq!(to_value(&entry).unwrap()["completedAt"], 100);
+    }
+}
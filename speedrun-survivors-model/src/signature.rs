@@ -0,0 +1,95 @@
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+/// Verify a Solana style (`signMessage`) ed25519 signature over `message`
+/// Both `pubkey` and `signature` are expected to be base58 encoded
+pub fn verify_signature(pubkey: &str, message: &str, signature: &str) -> bool {
+    let Ok(pubkey) = bs58::decode(pubkey).into_vec() else {
+        return false;
+    };
+    let Ok(signature) = bs58::decode(signature).into_vec() else {
+        return false;
+    };
+
+    let Ok(pubkey) = <[u8; 32]>::try_from(pubkey.as_slice()) else {
+        return false;
+    };
+    let Ok(pubkey) = VerifyingKey::from_bytes(&pubkey) else {
+        return false;
+    };
+    let Ok(signature) = Signature::from_slice(&signature) else {
+        return false;
+    };
+
+    pubkey.verify(message.as_bytes(), &signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use rand::rngs::OsRng;
+
+    const MESSAGE: &str = "Sign to start the game";
+
+    fn keypair() -> (SigningKey, String) {
+        let key = SigningKey::generate(&mut OsRng);
+        let pubkey = bs58::encode(key.verifying_key().as_bytes()).into_string();
+        (key, pubkey)
+    }
+
+    fn sign(key: &SigningKey, message: &str) -> String {
+        bs58::encode(key.sign(message.as_bytes()).to_bytes()).into_string()
+    }
+
+    #[test]
+    fn accepts_valid_signature() {
+        let (key, pubkey) = keypair();
+        assert!(verify_signature(&pubkey, MESSAGE, &sign(&key, MESSAGE)));
+    }
+
+    #[test]
+    fn rejects_other_message() {
+        let (key, pubkey) = keypair();
+        let signature = sign(&key, MESSAGE);
+        assert!(!verify_signature(
+            &pubkey,
+            "Sign to cancel the game",
+            &signature
+        ));
+    }
+
+    #[test]
+    fn rejects_other_key() {
+        let (key, _) = keypair();
+        let (_, other_pubkey) = keypair();
+        assert!(!verify_signature(
+            &other_pubkey,
+            MESSAGE,
+            &sign(&key, MESSAGE)
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_base58() {
+        let (key, pubkey) = keypair();
+        let signature = sign(&key, MESSAGE);
+        // 0, O, I and l are not part of the base58 alphabet
+        assert!(!verify_signature("0OIl", MESSAGE, &signature));
+        assert!(!verify_signature(&pubkey, MESSAGE, "0OIl"));
+    }
+
+    #[test]
+    fn rejects_wrong_length() {
+        let (key, pubkey) = keypair();
+        let signature = key.sign(MESSAGE.as_bytes()).to_bytes();
+        let short_pubkey = bs58::encode(&key.verifying_key().as_bytes()[..31]).into_string();
+        let short_signature = bs58::encode(&signature[..63]).into_string();
+        assert!(!verify_signature(
+            &short_pubkey,
+            MESSAGE,
+            &sign(&key, MESSAGE)
+        ));
+        assert!(!verify_signature(&pubkey, MESSAGE, &short_signature));
+        assert!(!verify_signature("", MESSAGE, ""));
+    }
+}
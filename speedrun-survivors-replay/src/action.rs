@@ -0,0 +1,47 @@
+use strum::{EnumIter, FromRepr};
+
+/// Every input the game client can record; mirrors `GameAction` in the client
+/// The discriminants are part of the binary format, never reorder or reuse them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, FromRepr)]
+#[repr(u8)]
+pub enum ReplayAction {
+    MoveUp = 0,
+    MoveLeft = 1,
+    MoveDown = 2,
+    MoveRight = 3,
+    Slot1 = 4,
+    Slot2 = 5,
+    Slot3 = 6,
+    Slot4 = 7,
+    Slot5 = 8,
+    Slot6 = 9,
+    Action1 = 10,
+    Action2 = 11,
+    Action3 = 12,
+    Cancel = 13,
+    Confirm = 14,
+    Pause = 15,
+    HackLevelUp = 16,
+}
+
+impl ReplayAction {
+    /// Movement actions (WASD) have their own compact opcodes
+    pub(crate) fn move_index(&self) -> Option<u8> {
+        match self {
+            ReplayAction::MoveUp
+            | ReplayAction::MoveLeft
+            | ReplayAction::MoveDown
+            | ReplayAction::MoveRight => Some(*self as u8),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn from_move_index(idx: u8) -> Self {
+        match idx {
+            0 => ReplayAction::MoveUp,
+            1 => ReplayAction::MoveLeft,
+            2 => ReplayAction::MoveDown,
+            _ => ReplayAction::MoveRight,
+        }
+    }
+}
@@ -0,0 +1,471 @@
+//! Compact binary input replay format shared by the game client and the API server
+//!
+//! Layout (all integers little endian):
+//! - 4 byte magic `SSRP`
+//! - 1 byte format version
+//! - 3 byte end tick
+//! - events, each a 1 byte opcode followed by a 2 or 3 byte tick delta and an optional payload
+//! - 4 byte CRC32 of everything before it
+//!
+//! Ticks are counted at [`TICKS_PER_SECOND`]; 3 bytes allow for ~77 hours of play.
+//! Tick deltas are relative to the previous event, so most events only need the short 2 byte form.
+
+mod action;
+mod opcode;
+
+pub use action::ReplayAction;
+
+use anyhow::{bail, ensure, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use opcode::*;
+
+/// Simulation rate the ticks are counted at
+pub const TICKS_PER_SECOND: u32 = 60;
+
+/// Largest tick that fits into 3 bytes
+pub const MAX_TICK: u32 = 0xFF_FFFF;
+
+/// Current version of the binary format; bump it on any incompatible change
+pub const FORMAT_VERSION: u8 = 1;
+
+const MAGIC: &[u8; 4] = b"SSRP";
+const HEADER_LEN: usize = 8;
+const CHECKSUM_LEN: usize = 4;
+
+/// A single recorded input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayInput {
+    Press(ReplayAction),
+    Release(ReplayAction),
+    /// Aim direction in 256 increments (~1.4° each), starting at +X counter clockwise
+    Aim(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayEvent {
+    pub tick: u32,
+    pub input: ReplayInput,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Replay {
+    /// Events ordered by tick
+    pub events: Vec<ReplayEvent>,
+    /// The tick the run ended at
+    pub end_tick: u32,
+}
+
+impl Replay {
+    /// Append an event; events must be pushed in tick order
+    pub fn push(&mut self, tick: u32, input: ReplayInput) {
+        self.events.push(ReplayEvent { tick, input });
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        ensure!(self.end_tick <= MAX_TICK, "Replay too long");
+
+        let mut buf = Vec::with_capacity(HEADER_LEN + self.events.len() * 4 + CHECKSUM_LEN);
+        buf.extend_from_slice(MAGIC);
+        buf.push(FORMAT_VERSION);
+        push_u24(&mut buf, self.end_tick);
+
+        let mut last_tick = 0;
+        for event in &self.events {
+            ensure!(
+                event.tick >= last_tick && event.tick <= self.end_tick,
+                "Replay event at tick {} out of order",
+                event.tick
+            );
+            let delta = event.tick - last_tick;
+            last_tick = event.tick;
+
+            let short = delta <= u16::MAX as u32;
+            match event.input {
+                ReplayInput::Press(action) | ReplayInput::Release(action) => {
+                    let pressed = matches!(event.input, ReplayInput::Press(_));
+                    match action.move_index() {
+                        Some(idx) => buf.push(op_move(idx, pressed, short)),
+                        None => buf.push(op_action(pressed, short)),
+                    }
+                    push_delta(&mut buf, delta, short);
+                    if action.move_index().is_none() {
+                        buf.push(action as u8);
+                    }
+                }
+                ReplayInput::Aim(rotation) => {
+                    buf.push(op_aim(short));
+                    push_delta(&mut buf, delta, short);
+                    buf.push(rotation);
+                }
+            }
+        }
+
+        let checksum = crc32fast::hash(&buf);
+        buf.extend_from_slice(&checksum.to_le_bytes());
+
+        Ok(buf)
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        ensure!(data.len() >= HEADER_LEN + CHECKSUM_LEN, "Replay truncated");
+        ensure!(&data[..4] == MAGIC, "Not a replay");
+        ensure!(
+            data[4] == FORMAT_VERSION,
+            "Unsupported replay version {}",
+            data[4]
+        );
+
+        let (body, checksum) = data.split_at(data.len() - CHECKSUM_LEN);
+        let checksum = u32::from_le_bytes(checksum.try_into()?);
+        ensure!(
+            crc32fast::hash(body) == checksum,
+            "Replay checksum mismatch"
+        );
+
+        let end_tick = read_u24(&body[5..8]);
+        let mut reader = Reader {
+            data: &body[HEADER_LEN..],
+        };
+
+        let mut events = Vec::new();
+        let mut tick = 0;
+        while let Some(op) = reader.u8() {
+            let Some(kind) = OpKind::from_opcode(op) else {
+                bail!("Unknown replay opcode {op:#04x}");
+            };
+            tick += match kind.short() {
+                true => reader.u16()? as u32,
+                false => reader.u24()?,
+            };
+            ensure!(tick <= end_tick, "Replay event after end tick");
+
+            let input = match kind {
+                OpKind::Move { idx, pressed, .. } => {
+                    press_or_release(ReplayAction::from_move_index(idx), pressed)
+                }
+                OpKind::Action { pressed, .. } => {
+                    let Some(action) = reader.u8().and_then(ReplayAction::from_repr) else {
+                        bail!("Invalid replay action");
+                    };
+                    press_or_release(action, pressed)
+                }
+                OpKind::Aim { .. } => {
+                    let Some(rotation) = reader.u8() else {
+                        bail!("Replay truncated");
+                    };
+                    ReplayInput::Aim(rotation)
+                }
+            };
+            events.push(ReplayEvent { tick, input });
+        }
+
+        Ok(Self { events, end_tick })
+    }
+
+    /// Text representation used to transport replays in JSON requests
+    pub fn to_base64(&self) -> Result<String> {
+        Ok(BASE64.encode(self.encode()?))
+    }
+
+    pub fn from_base64(data: &str) -> Result<Self> {
+        Self::decode(&BASE64.decode(data)?)
+    }
+}
+
+fn press_or_release(action: ReplayAction, pressed: bool) -> ReplayInput {
+    match pressed {
+        true => ReplayInput::Press(action),
+        false => ReplayInput::Release(action),
+    }
+}
+
+fn push_u24(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes()[..3]);
+}
+
+fn read_u24(data: &[u8]) -> u32 {
+    u32::from_le_bytes([data[0], data[1], data[2], 0])
+}
+
+fn push_delta(buf: &mut Vec<u8>, delta: u32, short: bool) {
+    match short {
+        true => buf.extend_from_slice(&(delta as u16).to_le_bytes()),
+        false => push_u24(buf, delta),
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8]> {
+        ensure!(self.data.len() >= len, "Replay truncated");
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).ok().map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u24(&mut self) -> Result<u32> {
+        Ok(read_u24(self.take(3)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    /// Deltas around the border of the 2 and 3 byte forms
+    const DELTAS: [u32; 5] = [0, 1, u16::MAX as u32, u16::MAX as u32 + 1, 0x2_0000];
+
+    /// Deterministic xorshift, enough to shake out the decoder without a dependency
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, max: u64) -> u64 {
+            self.next() % max
+        }
+    }
+
+    /// Every input kind in both delta widths
+    fn every_input() -> Replay {
+        let mut inputs: Vec<ReplayInput> = ReplayAction::iter()
+            .flat_map(|action| [ReplayInput::Press(action), ReplayInput::Release(action)])
+            .collect();
+        inputs.extend([0, 1, 127, 255].map(ReplayInput::Aim));
+
+        let mut replay = Replay::default();
+        let mut tick = 0;
+        for delta in DELTAS {
+            for input in &inputs {
+                tick += delta;
+                replay.push(tick, *input);
+            }
+        }
+        replay.end_tick = tick;
+        replay
+    }
+
+    fn random_replay(rng: &mut Rng) -> Replay {
+        let actions: Vec<ReplayAction> = ReplayAction::iter().collect();
+        let mut replay = Replay::default();
+        let mut tick = 0;
+        for _ in 0..rng.below(64) {
+            tick += match rng.below(4) {
+                0 => rng.below(0x1_0000 * 4) as u32,
+                _ => rng.below(120) as u32,
+            };
+            let action = actions[rng.below(actions.len() as u64) as usize];
+            let input = match rng.below(3) {
+                0 => ReplayInput::Press(action),
+                1 => ReplayInput::Release(action),
+                _ => ReplayInput::Aim(rng.next() as u8),
+            };
+            replay.push(tick, input);
+        }
+        replay.end_tick = tick + rng.below(600) as u32;
+        replay
+    }
+
+    /// Replace the checksum, so only the decoder itself can reject the data
+    fn with_checksum(body: &[u8]) -> Vec<u8> {
+        let mut data = body.to_vec();
+        data.extend_from_slice(&crc32fast::hash(body).to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn every_input_round_trips() {
+        let replay = every_input();
+        let data = replay.encode().unwrap();
+        assert_eq!(Replay::decode(&data).unwrap(), replay);
+        assert_eq!(
+            Replay::from_base64(&replay.to_base64().unwrap()).unwrap(),
+            replay
+        );
+    }
+
+    #[test]
+    fn deltas_use_the_short_form_when_they_fit() {
+        for (delta, len) in [(u16::MAX as u32, 2), (u16::MAX as u32 + 1, 3)] {
+            let mut replay = Replay {
+                end_tick: delta,
+                ..Default::default()
+            };
+            replay.push(delta, ReplayInput::Press(ReplayAction::MoveUp));
+            let data = replay.encode().unwrap();
+            assert_eq!(data.len(), HEADER_LEN + 1 + len + CHECKSUM_LEN);
+            assert_eq!(Replay::decode(&data).unwrap(), replay);
+        }
+    }
+
+    #[test]
+    fn empty_and_longest_replays_round_trip() {
+        for end_tick in [0, MAX_TICK] {
+            let replay = Replay {
+                end_tick,
+                ..Default::default()
+            };
+            assert_eq!(Replay::decode(&replay.encode().unwrap()).unwrap(), replay);
+        }
+    }
+
+    #[test]
+    fn random_replays_round_trip() {
+        let mut rng = Rng(0x5EED_CAFE);
+        for _ in 0..500 {
+            let replay = random_replay(&mut rng);
+            assert_eq!(Replay::decode(&replay.encode().unwrap()).unwrap(), replay);
+        }
+    }
+
+    #[test]
+    fn encode_rejects_invalid_replays() {
+        let too_long = Replay {
+            end_tick: MAX_TICK + 1,
+            ..Default::default()
+        };
+        assert!(too_long.encode().is_err());
+
+        let mut out_of_order = Replay {
+            end_tick: 10,
+            ..Default::default()
+        };
+        out_of_order.push(5, ReplayInput::Aim(0));
+        out_of_order.push(4, ReplayInput::Aim(0));
+        assert!(out_of_order.encode().is_err());
+
+        let mut after_end = Replay {
+            end_tick: 10,
+            ..Default::default()
+        };
+        after_end.push(11, ReplayInput::Aim(0));
+        assert!(after_end.encode().is_err());
+    }
+
+    #[test]
+    fn truncated_replays_are_rejected() {
+        let replay = every_input();
+        let data = replay.encode().unwrap();
+        for len in 0..data.len() {
+            assert!(Replay::decode(&data[..len]).is_err(), "length {len}");
+        }
+
+        // With a matching checksum the decoder must notice a cut within an event
+        let body = &data[..data.len() - CHECKSUM_LEN];
+        for len in HEADER_LEN..body.len() {
+            if let Ok(decoded) = Replay::decode(&with_checksum(&body[..len])) {
+                assert!(replay.events.starts_with(&decoded.events), "length {len}");
+            }
+        }
+    }
+
+    #[test]
+    fn corrupted_replays_are_rejected() {
+        let data = every_input().encode().unwrap();
+        for idx in 0..data.len() {
+            for bit in 0..8 {
+                let mut corrupted = data.clone();
+                corrupted[idx] ^= 1 << bit;
+                assert!(Replay::decode(&corrupted).is_err(), "byte {idx} bit {bit}");
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_opcodes_are_rejected() {
+        let known = |op: u8| matches!(op, 0x10..=0x1F | 0x20..=0x23 | 0x30..=0x31);
+        for op in 0..=u8::MAX {
+            // Long enough for any known opcode: a 3 byte delta and a payload byte
+            let mut body = Replay {
+                end_tick: MAX_TICK,
+                ..Default::default()
+            }
+            .encode()
+            .unwrap();
+            body.truncate(HEADER_LEN);
+            body.extend_from_slice(&[op, 0, 0, 0, 0]);
+            let decoded = Replay::decode(&with_checksum(&body));
+            if !known(op) {
+                assert!(decoded.is_err(), "opcode {op:#04x}");
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_actions_are_rejected() {
+        let last = ReplayAction::iter().next_back().unwrap() as u8;
+        for action in [last + 1, u8::MAX] {
+            let mut body = Replay {
+                end_tick: 10,
+                ..Default::default()
+            }
+            .encode()
+            .unwrap();
+            body.truncate(HEADER_LEN);
+            body.extend_from_slice(&[op_action(true, true), 0, 0, action]);
+            assert!(Replay::decode(&with_checksum(&body)).is_err());
+        }
+    }
+
+    #[test]
+    fn events_after_the_end_are_rejected() {
+        let mut body = Replay {
+            end_tick: 10,
+            ..Default::default()
+        }
+        .encode()
+        .unwrap();
+        body.truncate(HEADER_LEN);
+        body.extend_from_slice(&[op_aim(true), 11, 0, 0]);
+        assert!(Replay::decode(&with_checksum(&body)).is_err());
+    }
+
+    #[test]
+    fn other_versions_and_magics_are_rejected() {
+        let data = every_input().encode().unwrap();
+        let body = &data[..data.len() - CHECKSUM_LEN];
+
+        let mut other_version = body.to_vec();
+        other_version[4] = FORMAT_VERSION + 1;
+        assert!(Replay::decode(&with_checksum(&other_version)).is_err());
+
+        let mut other_magic = body.to_vec();
+        other_magic[..4].copy_from_slice(b"SSRX");
+        assert!(Replay::decode(&with_checksum(&other_magic)).is_err());
+
+        assert!(Replay::from_base64("not base64!").is_err());
+    }
+
+    #[test]
+    fn random_data_never_panics() {
+        let mut rng = Rng(0x00DE_C0DE);
+        for _ in 0..5_000 {
+            let mut body = MAGIC.to_vec();
+            body.push(FORMAT_VERSION);
+            body.extend((0..rng.below(48) + 3).map(|_| rng.next() as u8));
+            // Either outcome is fine, as long as a decoded replay is consistent
+            if let Ok(replay) = Replay::decode(&with_checksum(&body)) {
+                assert!(replay.events.iter().all(|e| e.tick <= replay.end_tick));
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Opcode table
+//! - `0x10..=0x1F` WASD on / off with a 2 or 3 byte tick delta (16 opcodes)
+//! - `0x20..=0x23` any other action on / off with a 2 or 3 byte tick delta, followed by the action byte
+//! - `0x30..=0x31` aim with a 2 or 3 byte tick delta, followed by the rotation byte
+
+const OP_MOVE: u8 = 0x10;
+const OP_ACTION: u8 = 0x20;
+const OP_AIM: u8 = 0x30;
+
+const FLAG_LONG: u8 = 0b001;
+const FLAG_RELEASE: u8 = 0b010;
+
+pub(crate) fn op_move(idx: u8, pressed: bool, short: bool) -> u8 {
+    OP_MOVE | idx << 2 | flags(pressed, short)
+}
+
+pub(crate) fn op_action(pressed: bool, short: bool) -> u8 {
+    OP_ACTION | flags(pressed, short)
+}
+
+pub(crate) fn op_aim(short: bool) -> u8 {
+    OP_AIM | flags(true, short)
+}
+
+fn flags(pressed: bool, short: bool) -> u8 {
+    let mut flags = 0;
+    if !short {
+        flags |= FLAG_LONG;
+    }
+    if !pressed {
+        flags |= FLAG_RELEASE;
+    }
+    flags
+}
+
+pub(crate) enum OpKind {
+    Move { idx: u8, pressed: bool, short: bool },
+    Action { pressed: bool, short: bool },
+    Aim { short: bool },
+}
+
+impl OpKind {
+    pub(crate) fn from_opcode(op: u8) -> Option<Self> {
+        let short = op & FLAG_LONG == 0;
+        let pressed = op & FLAG_RELEASE == 0;
+        match op & 0xF0 {
+            OP_MOVE => Some(OpKind::Move {
+                idx: (op >> 2) & 0b11,
+                pressed,
+                short,
+            }),
+            OP_ACTION if op & 0x0F <= 0b11 => Some(OpKind::Action { pressed, short }),
+            OP_AIM if op & 0x0F <= FLAG_LONG => Some(OpKind::Aim { short }),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn short(&self) -> bool {
+        match self {
+            OpKind::Move { short, .. } | OpKind::Action { short, .. } | OpKind::Aim { short } => {
+                *short
+            }
+        }
+    }
+}